@@ -0,0 +1,622 @@
+//! Headless accessibility check for release pipelines: captures a scoped application,
+//! runs the audit rules and writes the findings as SARIF 2.1 and JUnit XML. Findings
+//! listed in a baseline file are reported as suppressed and don't fail the check.
+//!
+//! Usage: uiexplore audit [--window <handle>] [--process <pid>] [--subtree <handle>:<runtime id>] [--title <text>]
+//!                        [--sarif <file>] [--junit <file>] [--baseline <file>] [--update-baseline]
+//!                        [--all-rules] [--fail-on error|warning|info] [--max-depth <n>] [--timeout <seconds>]
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::audit::{self, AuditRule, Finding, Severity};
+use crate::escape::{json_string, xml_escape};
+use crate::uiexplore::{self, CaptureMessage, CaptureOptions, CaptureScope, ScopeArgs};
+use crate::{UIHashSet, UITree};
+
+// the problems checked unless --all-rules is given
+const CORE_RULES: [&str; 3] = ["interactive-without-name", "focusable-empty-bounds", "duplicate-sibling-name"];
+
+// process exit codes
+pub const EXIT_PASSED: i32 = 0;
+pub const EXIT_FAILED: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+const USAGE: &str = "Usage: uiexplore audit [--window <handle>] [--process <pid>] [--subtree <handle>:<runtime id>] [--title <text>] \
+[--sarif <file>] [--junit <file>] [--baseline <file>] [--update-baseline] [--all-rules] \
+[--fail-on error|warning|info] [--max-depth <n>] [--timeout <seconds>]";
+
+#[derive(Debug, Clone)]
+pub struct GateOptions {
+    pub scope: ScopeArgs,
+    pub sarif: Option<PathBuf>,
+    pub junit: Option<PathBuf>,
+    pub baseline: Option<PathBuf>,
+    /// writes all current findings to the baseline file instead of checking them
+    pub update_baseline: bool,
+    pub all_rules: bool,
+    /// unsuppressed findings of at least this severity fail the check
+    pub fail_on: Severity,
+    pub max_depth: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+impl Default for GateOptions {
+    fn default() -> Self {
+        GateOptions {
+            scope: ScopeArgs::default(),
+            sarif: None,
+            junit: None,
+            baseline: None,
+            update_baseline: false,
+            all_rules: false,
+            fail_on: Severity::Warning,
+            max_depth: None,
+            timeout: None,
+        }
+    }
+}
+
+impl GateOptions {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = GateOptions::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().cloned().ok_or(format!("missing value for {}", arg));
+            if options.scope.parse_arg(arg, &mut value)? {
+                continue;
+            }
+            match arg.as_str() {
+                "--sarif" => options.sarif = Some(PathBuf::from(value()?)),
+                "--junit" => options.junit = Some(PathBuf::from(value()?)),
+                "--baseline" => options.baseline = Some(PathBuf::from(value()?)),
+                "--update-baseline" => options.update_baseline = true,
+                "--all-rules" => options.all_rules = true,
+                "--fail-on" => {
+                    options.fail_on = match value()?.to_lowercase().as_str() {
+                        "error" => Severity::Error,
+                        "warning" => Severity::Warning,
+                        "info" => Severity::Info,
+                        other => return Err(format!("invalid severity '{}'", other)),
+                    }
+                },
+                "--max-depth" => options.max_depth = Some(value()?.parse().map_err(|_| "invalid max depth".to_string())?),
+                "--timeout" => options.timeout = Some(Duration::from_secs(value()?.parse().map_err(|_| "invalid timeout".to_string())?)),
+                other => return Err(format!("unknown argument '{}'", other)),
+            }
+        }
+        if options.update_baseline && options.baseline.is_none() {
+            return Err("--update-baseline requires --baseline".to_string());
+        }
+        Ok(options)
+    }
+
+    fn rules(&self) -> Vec<&'static AuditRule> {
+        audit::RULES.iter().filter(|rule| self.all_rules || CORE_RULES.contains(&rule.id)).collect()
+    }
+}
+
+/// A finding together with the path used to match it against the baseline
+#[derive(Debug, Clone)]
+pub struct GateFinding {
+    pub finding: Finding,
+    pub path: Vec<String>,
+    pub suppressed: bool,
+}
+
+impl GateFinding {
+    /// Identifies the finding across runs, runtime ids and tree indices change with every capture
+    pub fn fingerprint(&self) -> String {
+        fingerprint(self.finding.rule_id, &self.path)
+    }
+
+    /// Whether the finding fails the check, i.e. it isn't suppressed and at least as severe as `fail_on`
+    pub fn fails(&self, fail_on: Severity) -> bool {
+        !self.suppressed && self.finding.severity <= fail_on
+    }
+}
+
+fn fingerprint(rule_id: &str, path: &[String]) -> String {
+    format!("{}\t{}", rule_id, path.join(" > ").replace(['\t', '\r', '\n'], " "))
+}
+
+/// Known issues, one per line as rule id and element path separated by a tab.
+/// A path of `*` suppresses all findings of a rule, lines starting with # are comments.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    fingerprints: UIHashSet<String>,
+    rules: UIHashSet<String>,
+}
+
+impl Baseline {
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        // a missing baseline is the same as an empty one
+        if !path.exists() {
+            return Ok(Baseline::default());
+        }
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read baseline {}: {}", path.display(), e))?;
+        Ok(Baseline::parse(&text))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut baseline = Baseline::default();
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('\t') {
+                Some((rule_id, "*")) => { baseline.rules.insert(rule_id.to_string()); },
+                Some(_) => { baseline.fingerprints.insert(line.to_string()); },
+                None => { baseline.rules.insert(line.to_string()); },
+            }
+        }
+        baseline
+    }
+
+    pub fn contains(&self, finding: &GateFinding) -> bool {
+        self.rules.contains(finding.finding.rule_id) || self.fingerprints.contains(&finding.fingerprint())
+    }
+
+    /// Formats the findings as a baseline file
+    pub fn format(findings: &[GateFinding]) -> String {
+        let mut lines: Vec<String> = findings.iter().map(|finding| finding.fingerprint()).collect();
+        lines.sort();
+        lines.dedup();
+        let mut text = String::from("# accessibility baseline, known issues which don't fail the check\n# <rule id>\\t<element path> or <rule id>\\t* for all findings of a rule\n");
+        for line in lines {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Runs the check with the command line arguments following `audit` and returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    let options = match GateOptions::parse(args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{}\n{}", msg, USAGE);
+            return EXIT_ERROR;
+        },
+    };
+
+    match check(&options) {
+        Ok(exit_code) => exit_code,
+        Err(msg) => {
+            log::error!("accessibility check failed: {}", msg);
+            eprintln!("error: {}", msg);
+            EXIT_ERROR
+        },
+    }
+}
+
+/// The exit code of a check with these findings
+pub fn exit_code(findings: &[GateFinding], fail_on: Severity) -> i32 {
+    if findings.iter().any(|finding| finding.fails(fail_on)) { EXIT_FAILED } else { EXIT_PASSED }
+}
+
+fn check(options: &GateOptions) -> Result<i32, String> {
+    let _span = crate::logging::span("a11y_gate", "");
+
+    let scope = options.scope.resolve()?;
+    let ui_tree = capture(scope, options)?;
+    let rules = options.rules();
+    let baseline = match &options.baseline {
+        Some(path) if !options.update_baseline => Baseline::load(path)?,
+        _ => Baseline::default(),
+    };
+
+    let findings: Vec<GateFinding> = audit::run_audit(&ui_tree, rules.iter().copied())
+        .into_iter()
+        .map(|finding| {
+            let path = audit::element_path(&ui_tree, finding.index);
+            let mut gate_finding = GateFinding { finding, path, suppressed: false };
+            gate_finding.suppressed = baseline.contains(&gate_finding);
+            gate_finding
+        })
+        .collect();
+
+    if options.update_baseline {
+        if let Some(path) = &options.baseline {
+            write_file(path, &Baseline::format(&findings))?;
+            println!("wrote {} findings to the baseline {}", findings.len(), path.display());
+        }
+        return Ok(EXIT_PASSED);
+    }
+
+    if let Some(path) = &options.sarif {
+        write_file(path, &to_sarif(&rules, &findings))?;
+    }
+    if let Some(path) = &options.junit {
+        write_file(path, &to_junit(&rules, &findings, options.fail_on))?;
+    }
+
+    let failing: Vec<&GateFinding> = findings.iter().filter(|finding| finding.fails(options.fail_on)).collect();
+    for finding in &failing {
+        println!("{}: [{}] {}: {}", finding.finding.severity.label(), finding.finding.rule_id, finding.path.join(" > "), finding.finding.message);
+    }
+    let suppressed = findings.iter().filter(|finding| finding.suppressed).count();
+    println!("{} findings, {} suppressed by the baseline, {} failing", findings.len(), suppressed, failing.len());
+    log::info!("{} findings, {} suppressed, {} failing", findings.len(), suppressed, failing.len());
+
+    Ok(exit_code(&findings, options.fail_on))
+}
+
+fn write_file(path: &PathBuf, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+// waits for the complete tree, the streamed messages are of no use without the GUI
+fn capture(scope: CaptureScope, options: &GateOptions) -> Result<UITree, String> {
+    let capture_options = CaptureOptions {
+        scope,
+        max_depth: options.max_depth,
+        overall_timeout: options.timeout,
+        screenshot: false,
+        ..Default::default()
+    };
+    let (tx, rx): (Sender<CaptureMessage>, Receiver<CaptureMessage>) = channel();
+    let cancel = uiexplore::CancelToken::new();
+    thread::spawn(move || uiexplore::get_all_elements(tx, capture_options, cancel));
+
+    loop {
+        match rx.recv() {
+            Ok(CaptureMessage::Done(ui_tree)) => return Ok(ui_tree),
+            Ok(CaptureMessage::Failed(msg)) => return Err(format!("capture failed: {}", msg)),
+            Ok(CaptureMessage::Cancelled) | Err(_) => return Err("capture aborted".to_string()),
+            Ok(_) => (),
+        }
+    }
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+/// Formats the findings as a SARIF 2.1.0 log
+pub fn to_sarif(rules: &[&AuditRule], findings: &[GateFinding]) -> String {
+    let rule_entries: Vec<String> = rules.iter().map(|rule| {
+        format!(r#"            {{"id": {}, "shortDescription": {{"text": {}}}, "defaultConfiguration": {{"level": "{}"}}}}"#,
+            json_string(rule.id), json_string(rule.title), sarif_level(rule.severity))
+    }).collect();
+
+    let results: Vec<String> = findings.iter().map(|finding| {
+        let rule_index = rules.iter().position(|rule| rule.id == finding.finding.rule_id).unwrap_or_default();
+        let path: Vec<String> = finding.path.iter().map(|label| json_string(label)).collect();
+        let suppressions = if finding.suppressed { r#", "suppressions": [{"kind": "external", "justification": "listed in the baseline"}]"# } else { "" };
+        format!(concat!(
+            r#"        {{"ruleId": {}, "ruleIndex": {}, "level": "{}", "message": {{"text": {}}}, "#,
+            r#""locations": [{{"logicalLocations": [{{"name": {}, "fullyQualifiedName": {}, "kind": "element"}}]}}], "#,
+            r#""partialFingerprints": {{"elementPath/v1": {}}}, "properties": {{"ancestorPath": [{}]}}{}}}"#),
+            json_string(finding.finding.rule_id),
+            rule_index,
+            sarif_level(finding.finding.severity),
+            json_string(&finding.finding.message),
+            json_string(finding.path.last().map(String::as_str).unwrap_or_default()),
+            json_string(&finding.path.join(" > ")),
+            json_string(&finding.fingerprint()),
+            path.join(", "),
+            suppressions)
+    }).collect();
+
+    format!(concat!(
+        "{{\n",
+        "  \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\",\n",
+        "  \"version\": \"2.1.0\",\n",
+        "  \"runs\": [{{\n",
+        "    \"tool\": {{\n",
+        "      \"driver\": {{\n",
+        "        \"name\": \"UI Explore\",\n",
+        "        \"version\": \"{}\",\n",
+        "        \"rules\": [\n{}\n        ]\n",
+        "      }}\n",
+        "    }},\n",
+        "    \"results\": [\n{}\n    ]\n",
+        "  }}]\n",
+        "}}\n"),
+        env!("CARGO_PKG_VERSION"),
+        rule_entries.join(",\n"),
+        results.join(",\n"))
+}
+
+/// Formats the findings as JUnit XML with a test suite per rule and a test case per finding.
+/// Findings failing the check with `fail_on` are failures and suppressed findings are skipped,
+/// less severe findings are passed test cases reporting the finding as output
+pub fn to_junit(rules: &[&AuditRule], findings: &[GateFinding], fail_on: Severity) -> String {
+    let mut suites = String::new();
+    let (mut tests, mut failures, mut skipped) = (0, 0, 0);
+
+    for rule in rules {
+        let rule_findings: Vec<&GateFinding> = findings.iter().filter(|finding| finding.finding.rule_id == rule.id).collect();
+        // a rule without findings is a passed test
+        let rule_tests = rule_findings.len().max(1);
+        let rule_failures = rule_findings.iter().filter(|finding| finding.fails(fail_on)).count();
+        let rule_skipped = rule_findings.iter().filter(|finding| finding.suppressed).count();
+        tests += rule_tests;
+        failures += rule_failures;
+        skipped += rule_skipped;
+
+        suites.push_str(&format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+            xml_escape(rule.id), rule_tests, rule_failures, rule_skipped));
+        if rule_findings.is_empty() {
+            suites.push_str(&format!("    <testcase classname=\"accessibility.{}\" name=\"{}\"/>\n", xml_escape(rule.id), xml_escape(rule.title)));
+        }
+        for finding in rule_findings {
+            let path = finding.path.join(" > ");
+            let details = format!("rule: {}\nseverity: {}\npath: {}\n{}",
+                xml_escape(rule.id),
+                finding.finding.severity.label(),
+                xml_escape(&path),
+                xml_escape(&finding.finding.message));
+            suites.push_str(&format!("    <testcase classname=\"accessibility.{}\" name=\"{}\">\n", xml_escape(rule.id), xml_escape(&path)));
+            if finding.suppressed {
+                suites.push_str("      <skipped message=\"listed in the baseline\"/>\n");
+            } else if finding.fails(fail_on) {
+                suites.push_str(&format!("      <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                    finding.finding.severity.label().to_lowercase(),
+                    xml_escape(&finding.finding.message),
+                    details));
+            } else {
+                suites.push_str(&format!("      <system-out>{}</system-out>\n", details));
+            }
+            suites.push_str("    </testcase>\n");
+        }
+        suites.push_str("  </testsuite>\n");
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuites name=\"accessibility\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n", tests, failures, skipped));
+    xml.push_str(&suites);
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str) -> &'static AuditRule {
+        audit::RULES.iter().find(|rule| rule.id == id).unwrap()
+    }
+
+    fn finding(rule_id: &str, path: &[&str], suppressed: bool) -> GateFinding {
+        let rule = rule(rule_id);
+        GateFinding {
+            finding: Finding { rule_id: rule.id, severity: rule.severity, index: 0, message: format!("{} <&> \"found\"", rule.title) },
+            path: path.iter().map(|label| label.to_string()).collect(),
+            suppressed,
+        }
+    }
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    // the xml is well-formed, returns the number of test cases
+    fn count_test_cases(xml: &str) -> usize {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut open: Vec<Vec<u8>> = Vec::new();
+        let mut test_cases = 0;
+        loop {
+            match reader.read_event().unwrap() {
+                quick_xml::events::Event::Start(e) => {
+                    if e.name().as_ref() == b"testcase" {
+                        test_cases += 1;
+                    }
+                    open.push(e.name().as_ref().to_vec());
+                },
+                quick_xml::events::Event::Empty(e) => {
+                    if e.name().as_ref() == b"testcase" {
+                        test_cases += 1;
+                    }
+                },
+                quick_xml::events::Event::End(e) => assert_eq!(open.pop().as_deref(), Some(e.name().as_ref())),
+                quick_xml::events::Event::Eof => break,
+                _ => (),
+            }
+        }
+        assert!(open.is_empty());
+        test_cases
+    }
+
+    // the value of the attribute of the first element with the name
+    fn attribute(xml: &str, element: &str, name: &str) -> String {
+        let start = xml.find(&format!("<{} ", element)).unwrap();
+        let tag = &xml[start..start + xml[start..].find('>').unwrap()];
+        let value = &tag[tag.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3..];
+        value[..value.find('"').unwrap()].to_string()
+    }
+
+    // brackets and braces outside of strings are balanced and strings are terminated
+    fn assert_json_balanced(json: &str) {
+        let mut stack: Vec<char> = Vec::new();
+        let mut in_string = false;
+        let mut escaped = false;
+        for c in json.chars() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    c => assert!(c as u32 >= 0x20, "unescaped control character in string"),
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '{' | '[' => stack.push(c),
+                '}' => assert_eq!(stack.pop(), Some('{')),
+                ']' => assert_eq!(stack.pop(), Some('[')),
+                _ => (),
+            }
+        }
+        assert!(!in_string);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn baseline_suppresses_listed_paths() {
+        let baseline = Baseline::parse("# comment\n\ninteractive-without-name\tDesktop > App > Button\n");
+        assert!(baseline.contains(&finding("interactive-without-name", &["Desktop", "App", "Button"], false)));
+        assert!(!baseline.contains(&finding("interactive-without-name", &["Desktop", "App", "Other Button"], false)));
+        assert!(!baseline.contains(&finding("duplicate-sibling-name", &["Desktop", "App", "Button"], false)));
+    }
+
+    #[test]
+    fn baseline_suppresses_all_findings_of_a_rule() {
+        let baseline = Baseline::parse("duplicate-sibling-name\t*\nmissing-automation-id\n");
+        assert!(baseline.contains(&finding("duplicate-sibling-name", &["Desktop", "App"], false)));
+        assert!(baseline.contains(&finding("missing-automation-id", &["Desktop", "Other App"], false)));
+        assert!(!baseline.contains(&finding("interactive-without-name", &["Desktop", "App"], false)));
+    }
+
+    #[test]
+    fn baseline_ignores_comments() {
+        let baseline = Baseline::parse("# interactive-without-name\t*\n   \n");
+        assert!(!baseline.contains(&finding("interactive-without-name", &["Desktop"], false)));
+    }
+
+    #[test]
+    fn formatted_baseline_suppresses_its_findings() {
+        let findings = vec![
+            finding("interactive-without-name", &["Desktop", "App", "Button"], false),
+            finding("interactive-without-name", &["Desktop", "App", "Button"], false),
+            finding("child-outside-parent", &["Desktop", "App\twith tab", "Pane"], false),
+        ];
+        let text = Baseline::format(&findings);
+        // one line per fingerprint after the comment lines
+        assert_eq!(text.lines().filter(|line| !line.starts_with('#')).count(), 2);
+        let baseline = Baseline::parse(&text);
+        assert!(findings.iter().all(|finding| baseline.contains(finding)));
+    }
+
+    #[test]
+    fn fingerprint_has_no_line_breaks_in_the_path() {
+        let finding = finding("interactive-without-name", &["Desktop", "Multi\nline\tname"], false);
+        assert_eq!(finding.fingerprint(), "interactive-without-name\tDesktop > Multi line name");
+    }
+
+    #[test]
+    fn findings_at_least_as_severe_as_the_threshold_fail() {
+        // a warning
+        let warning = finding("duplicate-sibling-name", &["Desktop", "App"], false);
+        assert!(!warning.fails(Severity::Error));
+        assert!(warning.fails(Severity::Warning));
+        assert!(warning.fails(Severity::Info));
+
+        let suppressed = finding("interactive-without-name", &["Desktop", "App"], true);
+        assert!(!suppressed.fails(Severity::Info));
+    }
+
+    #[test]
+    fn exit_code_depends_on_the_threshold() {
+        let findings = vec![
+            finding("missing-automation-id", &["Desktop", "App", "Button"], false),
+            finding("interactive-without-name", &["Desktop", "App", "Edit"], true),
+        ];
+        assert_eq!(exit_code(&findings, Severity::Warning), EXIT_PASSED);
+        assert_eq!(exit_code(&findings, Severity::Info), EXIT_FAILED);
+        assert_eq!(exit_code(&[], Severity::Info), EXIT_PASSED);
+    }
+
+    #[test]
+    fn junit_counts_the_written_test_cases() {
+        let rules = [rule("duplicate-sibling-name"), rule("child-outside-parent")];
+        let findings = vec![
+            finding("duplicate-sibling-name", &["Desktop", "App", "A"], false),
+            finding("duplicate-sibling-name", &["Desktop", "App", "B"], false),
+            finding("duplicate-sibling-name", &["Desktop", "App", "C"], true),
+        ];
+        let xml = to_junit(&rules, &findings, Severity::Warning);
+        assert_eq!(count_test_cases(&xml), 4);
+        assert_eq!(attribute(&xml, "testsuites", "tests"), "4");
+        assert_eq!(attribute(&xml, "testsuites", "failures"), "2");
+        assert_eq!(attribute(&xml, "testsuites", "skipped"), "1");
+        assert_eq!(xml.matches("<failure ").count(), 2);
+        assert_eq!(xml.matches("<skipped ").count(), 1);
+    }
+
+    #[test]
+    fn junit_reports_findings_below_the_threshold_as_passed() {
+        let rules = [rule("missing-automation-id")];
+        let findings = vec![finding("missing-automation-id", &["Desktop", "App", "<Button>"], false)];
+
+        let xml = to_junit(&rules, &findings, Severity::Warning);
+        assert_eq!(count_test_cases(&xml), 1);
+        assert_eq!(attribute(&xml, "testsuites", "failures"), "0");
+        assert!(!xml.contains("<failure"));
+        assert!(xml.contains("<system-out>"));
+
+        let xml = to_junit(&rules, &findings, Severity::Info);
+        assert_eq!(attribute(&xml, "testsuites", "failures"), "1");
+        assert!(xml.contains("<failure "));
+    }
+
+    #[test]
+    fn sarif_lists_rules_and_results() {
+        let rules = [rule("interactive-without-name"), rule("missing-automation-id")];
+        let findings = vec![
+            finding("missing-automation-id", &["Desktop", "App \"quoted\"", "Button\\1"], false),
+            finding("interactive-without-name", &["Desktop", "App", "Line\nbreak"], true),
+        ];
+        let sarif = to_sarif(&rules, &findings);
+        assert_json_balanced(&sarif);
+        assert!(sarif.contains(r#""version": "2.1.0""#));
+        assert!(sarif.contains(r#""ruleId": "missing-automation-id", "ruleIndex": 1, "level": "note""#));
+        assert!(sarif.contains(r#""ruleId": "interactive-without-name", "ruleIndex": 0, "level": "error""#));
+        assert_eq!(sarif.matches(r#""suppressions""#).count(), 1);
+    }
+
+    #[test]
+    fn sarif_without_findings_is_valid() {
+        let sarif = to_sarif(&[rule("interactive-without-name")], &[]);
+        assert_json_balanced(&sarif);
+        assert!(sarif.contains(r#""results": ["#));
+    }
+
+    #[test]
+    fn parse_gate_options() {
+        let options = GateOptions::parse(&args("--process 1234 --junit out.xml --fail-on error --all-rules --max-depth 5")).unwrap();
+        assert_eq!(options.scope.scope, CaptureScope::Process(1234));
+        assert_eq!(options.junit, Some(PathBuf::from("out.xml")));
+        assert_eq!(options.fail_on, Severity::Error);
+        assert!(options.all_rules);
+        assert_eq!(options.max_depth, Some(5));
+        assert_eq!(options.rules().len(), audit::RULES.len());
+
+        let options = GateOptions::parse(&[]).unwrap();
+        assert_eq!(options.scope.scope, CaptureScope::Desktop);
+        assert_eq!(options.fail_on, Severity::Warning);
+        assert_eq!(options.rules().len(), CORE_RULES.len());
+    }
+
+    #[test]
+    fn parse_scope_flags() {
+        assert_eq!(GateOptions::parse(&args("--window 0x1A2b")).unwrap().scope.scope, CaptureScope::Window(0x1a2b));
+        assert_eq!(GateOptions::parse(&args("--handle 4242")).unwrap().scope.scope, CaptureScope::Window(4242));
+        assert_eq!(GateOptions::parse(&args("--subtree 0x10:42-1234-5")).unwrap().scope.scope,
+            CaptureScope::Subtree { handle: 0x10, runtime_id: vec![42, 1234, 5] });
+        assert_eq!(GateOptions::parse(&args("--title Notepad")).unwrap().scope.title.as_deref(), Some("Notepad"));
+    }
+
+    #[test]
+    fn parse_invalid_gate_options() {
+        assert!(GateOptions::parse(&args("--process")).is_err());
+        assert!(GateOptions::parse(&args("--process abc")).is_err());
+        assert!(GateOptions::parse(&args("--window xyz")).is_err());
+        assert!(GateOptions::parse(&args("--subtree 42-1234")).is_err());
+        assert!(GateOptions::parse(&args("--fail-on fatal")).is_err());
+        assert!(GateOptions::parse(&args("--update-baseline")).is_err());
+        assert!(GateOptions::parse(&args("--unknown")).is_err());
+    }
+}
@@ -8,29 +8,70 @@ use eframe::egui;
 use egui::Response;
 use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
 use windows::Win32::Foundation::{POINT, RECT};
+use uiautomation::types::Rect;
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 
 #[allow(unused)]
-use crate::{rectangle, uiexplore, UIElementProps, UIElementInTree, UITree, AppContext, winevent}; 
+use crate::{rectangle, uiexplore, UIElementProps, UIElementInTree, UITree, UIHashMap, AppContext, winevent}; 
+use crate::hotkey::{self, HotkeyAction, HotkeyListener};
+use crate::audit::AuditPanel;
+use crate::export::{self, ExportOutcome, ExportPanel};
+use crate::screenshot::{self, ScreenshotPanel};
+use crate::element_map::ElementMapPanel;
+use crate::event_monitor::{EventMonitorPanel, MonitorAction};
+use crate::overlay::{Highlight, LabelPosition, Overlay, OverlayLayer};
+use crate::logging;
+use crate::settings::AppSettings;
+use crate::tab_order::{TabOrder, TabOrderAction, TabOrderPanel};
+use crate::uiexplore::{CancelToken, CaptureMessage, CaptureOptions, CaptureProgress, CaptureScope};
+use crate::tree_view::{ConditionSpec, TreeView};
+
+// number of visible tree nodes skipped by PageUp / PageDown
+const TREE_PAGE_SIZE: usize = 20;
+
+// time after which the type-ahead search buffer is reset
+const TYPE_AHEAD_TIMEOUT_MS: i64 = 1000;
+
+// colors of the tab order badges drawn on the screen
+const TAB_ORDER_BADGE_COLOR: [u8; 3] = [0, 120, 215];
+const TAB_ORDER_ISSUE_COLOR: [u8; 3] = [215, 90, 0];
+
+// color of the element hovered on the element map, apart from the active element
+const HOVER_HIGHLIGHT_COLOR: [u8; 3] = [0, 162, 255];
+
+// A node of the ui tree as it was rendered in the last frame, used for keyboard navigation
+#[derive(Clone)]
+struct VisibleNode {
+    index: usize,
+    header_id: Option<egui::Id>, // id of the CollapsingHeader, None for leaf nodes
+    is_open: bool,
+}
 
 #[derive(Clone)]
 struct TreeState {
     active_element: Option<UIElementProps>,
-    prev_element: Option<UIElementProps>,
-    clear_frame: bool,
     active_ui_element: Option<usize>,
     path_to_active_ui_element: Option<Vec<usize>>,
     refresh_path_to_active_ui_element: bool,
+    visible_nodes: Vec<VisibleNode>,
+    scroll_to_active_ui_element: bool,
+    type_ahead: String,
+    type_ahead_expiry: Option<DateTime>,
+    hovered_ui_element: Option<usize>, // element under the pointer on the element map
 }
 
 impl TreeState {
     fn new() -> Self {
         Self {
             active_element: None,
-            prev_element: None,
-            clear_frame: false,
             active_ui_element: None,
             path_to_active_ui_element: None,
             refresh_path_to_active_ui_element: false,
+            visible_nodes: Vec::new(),
+            scroll_to_active_ui_element: false,
+            type_ahead: String::new(),
+            type_ahead_expiry: None,
+            hovered_ui_element: None,
         }
     }
 
@@ -41,17 +82,13 @@ impl TreeState {
         if let Some(current_element) = &self.active_element {
             // only update the state if there is a change in the active element
             if new_active_element.runtime_id != current_element.runtime_id {
-                self.prev_element = Some(current_element.clone());
-                self.clear_frame = true;    
                 self.active_element = Some(new_active_element);
                 self.active_ui_element = Some(new_active_ui_element);
                 self.refresh_path_to_active_ui_element = true;
             }
         } else {
-            // there was no active element, so set the active element, 
-            // and the active ui element to the proviced values and
-            // default the prev element to the provided active
-            self.prev_element = Some(new_active_element.clone());
+            // there was no active element, so set the active element
+            // and the active ui element to the provided values
             self.active_element = Some(new_active_element);
             self.active_ui_element = Some(new_active_ui_element);
             self.refresh_path_to_active_ui_element = true;
@@ -73,7 +110,101 @@ impl TreeState {
         self.refresh_path_to_active_ui_element = false;
     }
 
-    
+    /// The top level window of the active element, the path starts with the desktop
+    fn active_window(&self) -> Option<usize> {
+        self.path_to_active_ui_element.as_ref().and_then(|path| path.get(1).copied())
+    }
+
+    fn select_ui_element(&mut self, ui_tree: &UITree, index: usize) {
+        let (_name, ui_element) = ui_tree.node(index);
+        self.update_state(ui_element.clone(), index);
+        self.scroll_to_active_ui_element = true;
+    }
+
+    fn active_visible_position(&self) -> Option<usize> {
+        let active = self.active_ui_element?;
+        self.visible_nodes.iter().position(|node| node.index == active)
+    }
+
+    fn select_visible_node(&mut self, ui_tree: &UITree, position: usize) {
+        if let Some(node) = self.visible_nodes.get(position) {
+            let index = node.index;
+            self.select_ui_element(ui_tree, index);
+        }
+    }
+
+    fn move_selection(&mut self, ui_tree: &UITree, offset: isize) {
+        if self.visible_nodes.is_empty() {
+            return;
+        }
+        let last = self.visible_nodes.len() - 1;
+        let position = match self.active_visible_position() {
+            Some(current) => (current as isize + offset).clamp(0, last as isize) as usize,
+            None => 0,
+        };
+        self.select_visible_node(ui_tree, position);
+    }
+
+    fn collapse_or_select_parent(&mut self, ctx: &egui::Context, ui_tree: &UITree) {
+        let Some(position) = self.active_visible_position() else {
+            return;
+        };
+        let node = self.visible_nodes[position].clone();
+        if let (Some(header_id), true) = (node.header_id, node.is_open) {
+            set_header_open(ctx, header_id, false);
+        } else {
+            let parent = ui_tree.get_tree().node(node.index).parent;
+            // the root element is not rendered in the tree, so we stop at its children
+            if parent != ui_tree.root() {
+                self.select_ui_element(ui_tree, parent);
+            }
+        }
+    }
+
+    fn expand_or_select_first_child(&mut self, ctx: &egui::Context, ui_tree: &UITree) {
+        let Some(position) = self.active_visible_position() else {
+            return;
+        };
+        let node = self.visible_nodes[position].clone();
+        if let Some(header_id) = node.header_id {
+            if node.is_open {
+                if let Some(&first_child) = ui_tree.children(node.index).first() {
+                    self.select_ui_element(ui_tree, first_child);
+                }
+            } else {
+                set_header_open(ctx, header_id, true);
+            }
+        }
+    }
+
+    fn type_ahead_search(&mut self, ui_tree: &UITree, text: &str) {
+        if self.type_ahead_expiry.is_some_and(|expiry| DateTime::now_utc() > expiry) {
+            self.type_ahead.clear();
+        }
+        self.type_ahead.push_str(&text.to_lowercase());
+        self.type_ahead_expiry = Some(DateTime::now_utc() + Duration::milliseconds(TYPE_AHEAD_TIMEOUT_MS));
+
+        if self.visible_nodes.is_empty() {
+            return;
+        }
+
+        // a repeated single character cycles through the matches, a longer
+        // search string keeps the current element if it still matches
+        let start = match self.active_visible_position() {
+            Some(current) if self.type_ahead.chars().count() == 1 => current + 1,
+            Some(current) => current,
+            None => 0,
+        };
+        let count = self.visible_nodes.len();
+        for offset in 0..count {
+            let position = (start + offset) % count;
+            let (_name, ui_element) = ui_tree.node(self.visible_nodes[position].index);
+            if ui_element.name.to_lowercase().starts_with(&self.type_ahead) {
+                self.select_visible_node(ui_tree, position);
+                return;
+            }
+        }
+    }
 
 }
 #[derive(Clone)]
@@ -182,14 +313,145 @@ impl DeduplicatedHistory {
 
 
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SnapshotKind {
+    Live,
+    Refreshed,
+    Scoped,
+    Imported,
+    Merged,
+}
+
+// The scope of a new capture, relative to the active element
+#[derive(Clone, Copy, PartialEq)]
+enum ScopeChoice {
+    Desktop,
+    Window,
+    Process,
+    Subtree,
+}
+
+impl ScopeChoice {
+    fn label(&self) -> &'static str {
+        match self {
+            ScopeChoice::Desktop => "Desktop",
+            ScopeChoice::Window => "Window of active element",
+            ScopeChoice::Process => "Process of active element",
+            ScopeChoice::Subtree => "Subtree of active element",
+        }
+    }
+}
+
+// What is shown in the central panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CentralView {
+    Properties,
+    ElementMap,
+}
+
+// A captured ui tree shown in its own tab, together with the state of its tree view
+struct Snapshot {
+    kind: SnapshotKind,
+    ui_tree: UITree,
+    tree_state: TreeState,
+}
+
+impl Snapshot {
+    fn new(kind: SnapshotKind, ui_tree: UITree) -> Self {
+        Snapshot {
+            kind,
+            ui_tree,
+            tree_state: TreeState::new(),
+        }
+    }
+
+    fn title(&self) -> String {
+        let kind = match self.kind {
+            SnapshotKind::Live => "Live".to_string(),
+            SnapshotKind::Refreshed => "Refreshed".to_string(),
+            SnapshotKind::Scoped => self.ui_tree.get_scope().label(),
+            SnapshotKind::Imported => "Imported".to_string(),
+            SnapshotKind::Merged => "Merged".to_string(),
+        };
+        format!("{} {}", kind, self.ui_tree.get_captured_at().format("%H:%M:%S"))
+    }
+}
+
+// A capture running in the background, the resulting tree is added as a new tab
+struct PendingCapture {
+    kind: SnapshotKind,
+    rx: Receiver<CaptureMessage>,
+    cancel: CancelToken,
+    progress: CaptureProgress,
+    snapshot: Option<usize>, // the tab the windows are streamed into, once they are known
+    screenshot: bool, // the capture takes a screenshot, which the explorer window is kept out of
+}
+
+// interval in which the progress of a pending capture is polled
+const CAPTURE_POLL_INTERVAL_MS: u64 = 100;
+
+// An element looked up at the cursor position in the background
+struct ElementQuery {
+    rx: Receiver<Vec<UIElementProps>>,
+    started_at: DateTime,
+}
+
+// time after which the application under the cursor is considered hung
+const ELEMENT_QUERY_TIMEOUT_S: i64 = 10;
+
+// The trees and the element a counterpart was looked up for, the lookup walks the whole
+// compared tree and is only repeated if one of them changes
+#[derive(Debug, Clone, PartialEq)]
+struct CounterpartKey {
+    element: usize,
+    tree: (chrono::DateTime<chrono::Local>, usize),
+    other_tree: (chrono::DateTime<chrono::Local>, usize),
+}
+
+impl CounterpartKey {
+    fn new(ui_tree: &UITree, element: usize, other: &UITree) -> Self {
+        CounterpartKey {
+            element,
+            tree: (ui_tree.get_captured_at(), ui_tree.get_revision()),
+            other_tree: (other.get_captured_at(), other.get_revision()),
+        }
+    }
+}
+
 // #[allow(dead_code)]
 pub struct UIExplorer {
     app_context: AppContext,
-    recording: bool,
-    show_history: bool,
-    highlighting: bool,
-    ui_tree: UITree,
-    tree_state: Option<TreeState>,
+    main_window: isize, // native handle of the explorer window, 0 if unknown
+    excluded_from_screenshots: bool,
+    settings: AppSettings,
+    show_settings: bool,
+    pinned: bool,
+    hover_candidate: Option<(usize, DateTime)>,
+    hotkeys: Option<HotkeyListener>,
+    snapshots: Vec<Snapshot>,
+    active_snapshot: usize,
+    compare_snapshot: Option<usize>,
+    counterpart: Option<(CounterpartKey, Option<usize>)>, // the last counterpart found in the compared snapshot
+    pending_capture: Option<PendingCapture>,
+    element_query: Option<ElementQuery>,
+    scope_choice: ScopeChoice,
+    view: TreeView,
+    custom_condition: ConditionSpec,
+    show_condition_builder: bool,
+    log_level: log::LevelFilter,
+    event_monitor: EventMonitorPanel,
+    show_event_monitor: bool,
+    audit: AuditPanel,
+    show_audit: bool,
+    tab_order: TabOrderPanel,
+    show_tab_order: bool,
+    overlay: Overlay, // highlights shown on the screen
+    export: ExportPanel,
+    show_export: bool,
+    screenshot: ScreenshotPanel,
+    show_screenshot: bool,
+    central_view: CentralView,
+    element_map: ElementMapPanel,
     history: DeduplicatedHistory,
     status_msg: Option<AppStatusMsg>
 }
@@ -199,23 +461,51 @@ impl UIExplorer {
     pub fn new() -> Self {
 
         // get the ui tree in a separate thread
-        let (tx, rx): (Sender<_>, Receiver<uiexplore::UITree>) = channel();
+        let (tx, rx): (Sender<_>, Receiver<CaptureMessage>) = channel();
         thread::spawn(|| {
-            uiexplore::get_all_elements(tx, None);
+            uiexplore::get_all_elements(tx, CaptureOptions::default(), CancelToken::new());
         });
 
-        let ui_tree = rx.recv().unwrap();
+        let ui_tree = rx.iter()
+            .find_map(|msg| match msg { CaptureMessage::Done(ui_tree) => Some(ui_tree), _ => None })
+            .unwrap();
         let app_context = AppContext::new_from_screen(0.4, 0.8);
 
         // TODO: Add winevent hook and ui automation instance
 
         Self {
             app_context,
-            recording: false,
-            show_history: false,
-            highlighting: false,
-            ui_tree,
-            tree_state: None,
+            main_window: 0,
+            excluded_from_screenshots: false,
+            settings: AppSettings::default(),
+            show_settings: false,
+            pinned: false,
+            hover_candidate: None,
+            hotkeys: None,
+            snapshots: vec![Snapshot::new(SnapshotKind::Live, ui_tree)],
+            active_snapshot: 0,
+            compare_snapshot: None,
+            counterpart: None,
+            pending_capture: None,
+            element_query: None,
+            scope_choice: ScopeChoice::Window,
+            view: TreeView::Control,
+            custom_condition: ConditionSpec::default(),
+            show_condition_builder: false,
+            log_level: log::LevelFilter::Info,
+            event_monitor: EventMonitorPanel::new(),
+            show_event_monitor: false,
+            audit: AuditPanel::new(),
+            show_audit: false,
+            tab_order: TabOrderPanel::new(),
+            show_tab_order: false,
+            overlay: Overlay::start(),
+            export: ExportPanel::new(),
+            show_export: false,
+            screenshot: ScreenshotPanel::new(),
+            show_screenshot: false,
+            central_view: CentralView::Properties,
+            element_map: ElementMapPanel::new(),
             history: DeduplicatedHistory::default(),
             status_msg: None,
         }
@@ -223,33 +513,99 @@ impl UIExplorer {
 
     }
 
-    pub fn new_with_state(app_context: AppContext, ui_tree: UITree) -> Self {
+    /// Creates the explorer with the initial capture, `pending_capture` streams the content 
+    /// of its windows if the capture hasn't finished yet.
+    pub fn new_with_state(app_context: AppContext, ui_tree: UITree, pending_capture: Option<(Receiver<CaptureMessage>, CancelToken)>, cc: &eframe::CreationContext<'_>) -> Self {
 
         // TODO: Add winevent hook and ui automation instance
 
+        let hotkeys = HotkeyListener::start(hotkey::default_hotkeys(), cc.egui_ctx.clone());
+        let settings = AppSettings::load(cc.storage);
+        let main_window = match cc.window_handle().map(|handle| handle.as_raw()) {
+            Ok(RawWindowHandle::Win32(handle)) => handle.hwnd.get(),
+            _ => 0,
+        };
+
         Self {
             app_context,
-            recording: false,
-            show_history: false,
-            highlighting: false,
-            ui_tree,
-            tree_state: None,
+            main_window,
+            excluded_from_screenshots: false,
+            settings,
+            show_settings: false,
+            pinned: false,
+            hover_candidate: None,
+            hotkeys: Some(hotkeys),
+            snapshots: vec![Snapshot::new(SnapshotKind::Live, ui_tree)],
+            active_snapshot: 0,
+            compare_snapshot: None,
+            counterpart: None,
+            pending_capture: pending_capture.map(|(rx, cancel)| PendingCapture {
+                kind: SnapshotKind::Live,
+                rx,
+                cancel,
+                progress: CaptureProgress::default(),
+                snapshot: Some(0),
+                // taken before the explorer window was opened
+                screenshot: false,
+            }),
+            element_query: None,
+            scope_choice: ScopeChoice::Window,
+            view: TreeView::Control,
+            custom_condition: ConditionSpec::default(),
+            show_condition_builder: false,
+            log_level: log::LevelFilter::Info,
+            event_monitor: EventMonitorPanel::new(),
+            show_event_monitor: false,
+            audit: AuditPanel::new(),
+            show_audit: false,
+            tab_order: TabOrderPanel::new(),
+            show_tab_order: false,
+            overlay: Overlay::start(),
+            export: ExportPanel::new(),
+            show_export: false,
+            screenshot: ScreenshotPanel::new(),
+            show_screenshot: false,
+            central_view: CentralView::Properties,
+            element_map: ElementMapPanel::new(),
             history: DeduplicatedHistory::default(),
             status_msg: None,
         }
     }
 
 
+    // the explorer would cover the elements in the screenshots of refreshed captures, it is 
+    // only kept out of them while such a capture is pending as it also disappears from screen sharing
+    fn exclude_from_screenshots(&mut self) {
+        let exclude = self.pending_capture.as_ref().is_some_and(|pending| pending.screenshot);
+        if self.main_window == 0 || self.excluded_from_screenshots == exclude {
+            return;
+        }
+        if let Err(msg) = screenshot::exclude_from_screenshots(self.main_window, exclude) {
+            log::warn!("Failed to change the screenshot visibility of the explorer window: {}", msg);
+        }
+        self.excluded_from_screenshots = exclude;
+    }
+
     fn render_ui_tree(&mut self, ui: &mut egui::Ui, state: &mut TreeState) {
-        let tree = &self.ui_tree;
+        let tree = &self.snapshots[self.active_snapshot].ui_tree;
+        state.visible_nodes.clear();
         Self::render_ui_tree_recursive(ui, tree, 0, state);
     }
 
     fn render_ui_tree_recursive(ui: &mut egui::Ui, tree: &UITree, idx: usize, state: &mut TreeState) {
         
+        let mut salt_counts: UIHashMap<String, usize> = UIHashMap::default();
+
         for &child_index in tree.children(idx) {
             let (name, ui_element) = tree.node(child_index);
 
+            // header ids are based on the element properties rather than on the position in 
+            // the capture, so the (persisted) expand state survives refreshes and restarts
+            let salt_key = format!("{}|{}|{}", ui_element.control_type, ui_element.classname, ui_element.name);
+            let occurrence = salt_counts.entry(salt_key.clone()).or_insert(0);
+            let id_salt = format!("ch_node{}#{}", salt_key, occurrence);
+            *occurrence += 1;
+
             // flag if this is the active element
             let mut is_active_element: bool = false;
             if let Some(active_id) = state.active_ui_element {
@@ -260,7 +616,12 @@ impl UIExplorer {
             
             if tree.children(child_index).is_empty() {
                 // Node has no children, so just show a label
-                let lbl = egui::Label::new(format!("  {}", name));
+                let lbl = if tree.is_loading(child_index) {
+                    // window which is still being captured
+                    egui::Label::new(format!("  ⏳ {} (loading…)", name))
+                } else {
+                    egui::Label::new(format!("  {}", name))
+                };
                 let entry: Response;
                 // let entry = ui.label(format!("  {}", name)).on_hover_cursor(egui::CursorIcon::Default);
                 if is_active_element{
@@ -276,46 +637,67 @@ impl UIExplorer {
                     // render standard label without any visual highlights
                     entry = ui.add(lbl).on_hover_cursor(egui::CursorIcon::Default);                    
                 }
+                state.visible_nodes.push(VisibleNode { index: child_index, header_id: None, is_open: false });
+
+                if is_active_element && state.scroll_to_active_ui_element {
+                    entry.scroll_to_me(Some(egui::Align::Center));
+                    state.scroll_to_active_ui_element = false;
+                }
                 
                 if entry.clicked() {
                     state.update_state(ui_element.clone(), child_index);
                 }
-                if entry.hovered() {
+                if entry.hovered() || state.hovered_ui_element == Some(child_index) {
                     entry.highlight();                    
                 }
             }
             else {
                 // Render children under collapsing header
                 let header: egui::CollapsingHeader;                
-                // TODO: check if header is on path to active element, if yes open the header
-                if !is_in_path_to_active_element(state.active_ui_element, &state.path_to_active_ui_element) {
+                if !is_in_path_to_active_element(Some(child_index), &state.path_to_active_ui_element) {
                     // header is not on path, render a standard CollapsingHeader
                     header = egui::CollapsingHeader::new(name)
-                    .id_salt(format!("ch_node{}", child_index))
+                    .id_salt(&id_salt)
                 } else {
                     if is_active_element {
                         // show background to visually highlight the active element
                         header = egui::CollapsingHeader::new(name)
-                        .id_salt(format!("ch_node{}", child_index))
+                        .id_salt(&id_salt)
                         .default_open(true)
                         .show_background(true);
                     } else {
                         header = egui::CollapsingHeader::new(name)
-                        .id_salt(format!("ch_node{}", child_index))
+                        .id_salt(&id_salt)
                         .default_open(true);
                         // TODO: or maybe better .open(Some(true)) ?? test it out...    
                     }
                 }
                 
+                // the header goes into the list of visible nodes before its children,
+                // its id and open state are only known after it has been rendered
+                let visible_position = state.visible_nodes.len();
+                state.visible_nodes.push(VisibleNode { index: child_index, header_id: None, is_open: false });
+
                 let header_resp = header
                     .show(ui, |ui| {
                         // Recursively render children
                         Self::render_ui_tree_recursive(ui, tree, child_index, state);
                     });    
+
+                state.visible_nodes[visible_position].header_id = Some(header_resp.header_response.id);
+                state.visible_nodes[visible_position].is_open = header_resp.body_returned.is_some();
+
+                if is_active_element && state.scroll_to_active_ui_element {
+                    header_resp.header_response.scroll_to_me(Some(egui::Align::Center));
+                    state.scroll_to_active_ui_element = false;
+                }
                     
                 if header_resp.header_response.clicked() {
                     state.update_state(ui_element.clone(), child_index);
                 }
+                if state.hovered_ui_element == Some(child_index) {
+                    header_resp.header_response.highlight();
+                }
             }
         }
     }    
@@ -332,14 +714,569 @@ impl UIExplorer {
                     cursor_pos
                 };
                                 
-                if let Some(ui_element_props) = rectangle::get_point_bounding_rect(&cursor_position, self.ui_tree.get_elements()) {
-                    state.update_state(ui_element_props.get_element_props().clone(), ui_element_props.get_tree_index());
+                if let Some(ui_element_props) = rectangle::get_point_bounding_rect(&cursor_position, self.snapshots[self.active_snapshot].ui_tree.get_elements()) {
+                    let index = ui_element_props.get_tree_index();
+                    if self.settings.hover_delay_ms == 0 {
+                        state.update_state(ui_element_props.get_element_props().clone(), index);
+                    } else if self.hover_candidate.is_none_or(|(candidate, _since)| candidate != index) {
+                        // (re-)start the hover timer, the element only becomes active
+                        // once the cursor rests on it for the configured delay
+                        self.hover_candidate = Some((index, DateTime::now_utc()));
+                    }
                 } 
             }
             _ => (),
         }
     }
 
+    // tracking is frozen while the element is pinned or the cursor is over the explorer itself
+    fn is_tracking_frozen(&self, ctx: &egui::Context) -> bool {
+        self.pinned || (self.settings.freeze_over_explorer && ctx.input(|i| i.pointer.has_pointer()))
+    }
+
+    fn process_hover_delay(&mut self, ctx: &egui::Context, state: &mut TreeState) {
+        if let Some((index, since)) = self.hover_candidate {
+            let delay = Duration::milliseconds(self.settings.hover_delay_ms as i64);
+            let elapsed = DateTime::now_utc() - since;
+            if elapsed >= delay {
+                let (_name, ui_element) = self.snapshots[self.active_snapshot].ui_tree.node(index);
+                state.update_state(ui_element.clone(), index);
+                self.hover_candidate = None;
+            } else {
+                ctx.request_repaint_after((delay - elapsed).unsigned_abs());
+            }
+        }
+    }
+
+    // hotkeys taken by another application don't work, their hints are left out
+    fn is_hotkey_registered(&self, action: HotkeyAction) -> bool {
+        self.hotkeys.as_ref().is_some_and(|hotkeys| hotkeys.is_registered(action))
+    }
+
+    fn process_hotkeys(&mut self, ctx: &egui::Context) {
+        let actions = match &self.hotkeys {
+            Some(listener) => listener.triggered(),
+            None => return,
+        };
+
+        for action in actions {
+            match action {
+                HotkeyAction::TogglePin => self.toggle_pin(),
+                HotkeyAction::CaptureUnderCursor => self.capture_element_under_cursor(ctx),
+            }
+        }
+    }
+
+    fn capture_element_under_cursor(&mut self, ctx: &egui::Context) {
+        if self.element_query.is_some() {
+            self.set_status("Still looking for the element under the cursor".to_string(), Duration::seconds(2));
+            return;
+        }
+
+        let mut cursor_position = POINT::default();
+        if let Err(e) = unsafe { GetCursorPos(&mut cursor_position) } {
+            log::warn!("Failed to get the cursor position: {}", e);
+            self.set_status(format!("Failed to get the cursor position: {}", e), Duration::seconds(5));
+            return;
+        }
+
+        // query the element with a fresh ui automation instance in a separate thread, 
+        // the application under the cursor may not respond. The result is polled in update()
+        let view = self.snapshots[self.active_snapshot].ui_tree.get_view().clone();
+        let (tx, rx): (Sender<_>, Receiver<Vec<UIElementProps>>) = channel();
+        thread::spawn(move || {
+            uiexplore::get_element_chain_from_point(tx, cursor_position.x, cursor_position.y, view);
+        });
+        self.element_query = Some(ElementQuery { rx, started_at: DateTime::now_utc() });
+        ctx.request_repaint_after(std::time::Duration::from_millis(CAPTURE_POLL_INTERVAL_MS));
+    }
+
+    /// Merges the element found under the cursor into the active snapshot and selects it
+    fn poll_element_query(&mut self, ctx: &egui::Context, state: &mut TreeState) {
+        let Some(query) = &self.element_query else {
+            return;
+        };
+
+        let chain = match query.rx.try_recv() {
+            Ok(chain) => chain,
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                if DateTime::now_utc() - query.started_at > Duration::seconds(ELEMENT_QUERY_TIMEOUT_S) {
+                    // the thread is left behind, its result is dropped if it ever arrives
+                    self.element_query = None;
+                    self.set_status("The application under the cursor doesn't respond".to_string(), Duration::seconds(5));
+                } else {
+                    ctx.request_repaint_after(std::time::Duration::from_millis(CAPTURE_POLL_INTERVAL_MS));
+                }
+                return;
+            },
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Vec::new(),
+        };
+        self.element_query = None;
+
+        let ui_tree = &mut self.snapshots[self.active_snapshot].ui_tree;
+        match ui_tree.merge_element_chain(chain) {
+            Some(index) => {
+                state.select_ui_element(ui_tree, index);
+                state.refresh_path_to_active_ui_element = true;
+                // keep the captured element selected while the mouse moves to the explorer
+                if self.settings.recording {
+                    self.pinned = true;
+                    self.hover_candidate = None;
+                }
+                self.set_status("Element under cursor captured".to_string(), Duration::seconds(2));
+            },
+            None => {
+                self.set_status("No element found under cursor".to_string(), Duration::seconds(2));
+            },
+        }
+
+        // bring the explorer to the front
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+
+    fn toggle_pin(&mut self) {
+        self.pinned = !self.pinned;
+        self.hover_candidate = None;
+        let msg = if self.pinned { "Active element pinned" } else { "Active element unpinned" };
+        self.set_status(msg.to_string(), Duration::seconds(2));
+    }
+
+
+    fn render_breadcrumbs(&self, ui: &mut egui::Ui, state: &TreeState) -> Option<usize> {
+        let mut selected: Option<usize> = None;
+        let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+
+        let Some(path) = &state.path_to_active_ui_element else {
+            return None;
+        };
+
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for (pos, &index) in path.iter().enumerate() {
+                if pos > 0 {
+                    ui.label("›");
+                }
+                let (_name, ui_element) = ui_tree.node(index);
+                let is_active = state.active_ui_element == Some(index);
+                let segment = ui.selectable_label(is_active, breadcrumb_label(ui_element))
+                    .on_hover_text("Click to select, right-click to show siblings");
+                if segment.clicked() {
+                    selected = Some(index);
+                }
+                // offer the siblings of the segment to navigate sideways
+                let parent = ui_tree.get_tree().node(index).parent;
+                segment.context_menu(|ui| {
+                    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                        for &sibling in ui_tree.children(parent) {
+                            let (_name, sibling_element) = ui_tree.node(sibling);
+                            if ui.selectable_label(sibling == index, breadcrumb_label(sibling_element)).clicked() {
+                                selected = Some(sibling);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            }
+        });
+
+        selected
+    }
+
+    fn process_tree_navigation(&mut self, ctx: &egui::Context, state: &mut TreeState) {
+
+        // don't steal keystrokes from widgets expecting keyboard input
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let mut keys: Vec<egui::Key> = Vec::new();
+        let mut typed = String::new();
+        ctx.input_mut(|i| {
+            for key in [
+                egui::Key::ArrowUp, egui::Key::ArrowDown, egui::Key::ArrowLeft, egui::Key::ArrowRight,
+                egui::Key::Home, egui::Key::End, egui::Key::PageUp, egui::Key::PageDown,
+            ] {
+                while i.consume_key(egui::Modifiers::NONE, key) {
+                    keys.push(key);
+                }
+            }
+            for event in &i.events {
+                if let egui::Event::Text(text) = event {
+                    typed.push_str(text);
+                }
+            }
+        });
+
+        let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+        for key in keys {
+            match key {
+                egui::Key::ArrowUp => state.move_selection(ui_tree, -1),
+                egui::Key::ArrowDown => state.move_selection(ui_tree, 1),
+                egui::Key::PageUp => state.move_selection(ui_tree, -(TREE_PAGE_SIZE as isize)),
+                egui::Key::PageDown => state.move_selection(ui_tree, TREE_PAGE_SIZE as isize),
+                egui::Key::Home => state.select_visible_node(ui_tree, 0),
+                egui::Key::End => state.select_visible_node(ui_tree, state.visible_nodes.len().saturating_sub(1)),
+                egui::Key::ArrowLeft => state.collapse_or_select_parent(ctx, ui_tree),
+                egui::Key::ArrowRight => state.expand_or_select_first_child(ctx, ui_tree),
+                _ => (),
+            }
+        }
+
+        if !typed.is_empty() {
+            state.type_ahead_search(ui_tree, &typed);
+        }
+    }
+
+    fn render_snapshot_tabs(&mut self, ui: &mut egui::Ui) {
+        let mut close: Option<usize> = None;
+        let mut merge: Option<usize> = None;
+
+        ui.horizontal(|ui| {
+            for (index, snapshot) in self.snapshots.iter().enumerate() {
+                if ui.selectable_label(index == self.active_snapshot, snapshot.title()).clicked() {
+                    self.active_snapshot = index;
+                    self.hover_candidate = None;
+                }
+                if snapshot.ui_tree.get_scope() != &CaptureScope::Desktop 
+                    && ui.add_enabled(snapshot.ui_tree.is_complete(), egui::Button::new("⤵").small())
+                        .on_hover_text("Merge into the latest desktop capture")
+                        .on_disabled_hover_text("The capture is still running")
+                        .clicked() {
+                    merge = Some(index);
+                }
+                if self.snapshots.len() > 1 && ui.small_button("✖").on_hover_text("Close tab").clicked() {
+                    close = Some(index);
+                }
+                ui.separator();
+            }
+        });
+
+        if let Some(index) = close {
+            self.close_snapshot(index);
+        } else if let Some(index) = merge {
+            self.merge_snapshot(index);
+        }
+    }
+
+    /// Adds a tab with the latest complete desktop capture of the same view, into which the
+    /// scoped capture of the tab at `index` is merged
+    fn merge_snapshot(&mut self, index: usize) {
+        let scoped = &self.snapshots[index].ui_tree;
+        let target = self.snapshots.iter().rposition(|snapshot| {
+            snapshot.ui_tree.get_scope() == &CaptureScope::Desktop 
+                && snapshot.ui_tree.get_view() == scoped.get_view() 
+                && snapshot.ui_tree.is_complete()
+        });
+        let Some(target) = target else {
+            let msg = format!("No complete desktop capture of the {} to merge into", scoped.get_view().label());
+            self.set_status(msg, Duration::seconds(5));
+            return;
+        };
+
+        let mut merged = self.snapshots[target].ui_tree.clone();
+        let count = merged.merge_scoped(scoped);
+        log::info!("merged {} windows or subtrees of '{}' into '{}'", count, self.snapshots[index].title(), self.snapshots[target].title());
+        let msg = format!("Merged {} into {}", scoped.get_scope().label(), self.snapshots[target].title());
+        self.add_snapshot(Snapshot::new(SnapshotKind::Merged, merged));
+        self.set_status(msg, Duration::seconds(5));
+    }
+
+    fn refresh_snapshot(&mut self) {
+        // refresh with the same scope the active snapshot was captured with
+        let scope = self.snapshots[self.active_snapshot].ui_tree.get_scope().clone();
+        let Some(view) = self.selected_view() else {
+            return;
+        };
+        self.start_capture(SnapshotKind::Refreshed, scope, view);
+    }
+
+    fn capture_scope(&mut self, state: &TreeState) {
+        let Some(scope) = self.scope_for_active_element(state) else {
+            self.set_status(format!("No active element to capture '{}'", self.scope_choice.label()), Duration::seconds(3));
+            return;
+        };
+        let Some(view) = self.selected_view() else {
+            return;
+        };
+        self.start_capture(SnapshotKind::Scoped, scope, view);
+    }
+
+    fn start_capture(&mut self, kind: SnapshotKind, scope: CaptureScope, view: TreeView) {
+        if self.pending_capture.is_some() {
+            return;
+        }
+
+        let options = CaptureOptions {
+            scope,
+            view,
+            element_timeout: Some(std::time::Duration::from_secs(self.settings.capture_element_timeout_s as u64)),
+            overall_timeout: match self.settings.capture_overall_timeout_s {
+                0 => None,
+                timeout => Some(std::time::Duration::from_secs(timeout as u64)),
+            },
+            worker_threads: match self.settings.capture_worker_threads {
+                0 => None,
+                threads => Some(threads),
+            },
+            screenshot: self.settings.capture_screenshot,
+            ..Default::default()
+        };
+
+        // get the ui tree in a separate thread, the result is polled in update()
+        let (tx, rx): (Sender<_>, Receiver<CaptureMessage>) = channel();
+        let cancel = CancelToken::new();
+        let worker_cancel = cancel.clone();
+        self.pending_capture = Some(PendingCapture { kind, rx, cancel, progress: CaptureProgress::default(), snapshot: None, screenshot: options.screenshot });
+        // before the screenshot is taken at the start of the capture
+        self.exclude_from_screenshots();
+
+        log::info!("starting {:?} capture of {}", kind, options.scope.label());
+        thread::spawn(move || {
+            let _span = logging::span("refresh", &format!("kind={:?}", kind));
+            uiexplore::get_all_elements(tx, options, worker_cancel);
+        });
+    }
+
+    /// Processes the messages of the pending capture. A new tab is opened as soon as the windows 
+    /// are known, their content is added while it is captured.
+    fn poll_pending_capture(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &mut self.pending_capture else {
+            return;
+        };
+
+        let mut finished: Option<Result<UITree, String>> = None;
+        let mut grafted = false;
+        loop {
+            match pending.rx.try_recv() {
+                Ok(CaptureMessage::Progress(progress)) => pending.progress = progress,
+                Ok(CaptureMessage::Skeleton(ui_tree)) => {
+                    // compare the new snapshot with the one it was taken from
+                    self.compare_snapshot = Some(self.active_snapshot);
+                    self.snapshots.push(Snapshot::new(pending.kind, ui_tree));
+                    self.active_snapshot = self.snapshots.len() - 1;
+                    self.hover_candidate = None;
+                    pending.snapshot = Some(self.active_snapshot);
+                },
+                Ok(CaptureMessage::Subtree { placeholder, result }) => {
+                    if let Some(index) = pending.snapshot {
+                        self.snapshots[index].ui_tree.graft_subtree(placeholder, result);
+                        grafted = true;
+                    }
+                },
+                Ok(CaptureMessage::Done(ui_tree)) => finished = Some(Ok(ui_tree)),
+                Ok(CaptureMessage::Cancelled) => finished = Some(Err("Capture cancelled".to_string())),
+                Ok(CaptureMessage::Failed(msg)) => finished = Some(Err(format!("Capture failed: {}", msg))),
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    if finished.is_none() {
+                        finished = Some(Err("Capture failed: capture thread terminated".to_string()));
+                    }
+                    break;
+                },
+            }
+        }
+
+        let streamed_into = pending.snapshot;
+        let kind = pending.kind;
+        if grafted {
+            if let Some(index) = streamed_into {
+                self.snapshots[index].ui_tree.rebuild_elements();
+            }
+        }
+
+        match finished {
+            None => {
+                // keep polling, the capture thread doesn't know about the ui
+                ctx.request_repaint_after(std::time::Duration::from_millis(CAPTURE_POLL_INTERVAL_MS));
+            },
+            Some(Ok(ui_tree)) => {
+                self.pending_capture = None;
+                match streamed_into {
+                    // every subtree has been grafted into the streamed tree already. It is kept rather than
+                    // replaced, as elements may have been merged into it meanwhile, which shifts the indices
+                    // of the grafted nodes compared to the final tree and the tree state refers to them
+                    Some(_) => (),
+                    None => self.add_snapshot(Snapshot::new(kind, ui_tree)),
+                }
+            },
+            Some(Err(msg)) => {
+                self.pending_capture = None;
+                // keep what has been captured so far
+                if let Some(index) = streamed_into {
+                    self.snapshots[index].ui_tree.stop_loading(&msg);
+                    self.snapshots[index].ui_tree.rebuild_elements();
+                }
+                self.set_status(msg, Duration::seconds(5));
+            },
+        }
+    }
+
+    fn cancel_pending_capture(&mut self) {
+        if let Some(pending) = &self.pending_capture {
+            pending.cancel.cancel();
+        }
+    }
+
+    /// Returns the view used for the next capture, None if the custom condition is invalid
+    fn selected_view(&mut self) -> Option<TreeView> {
+        if let TreeView::Custom(_) = self.view {
+            if let Err(msg) = self.custom_condition.validate() {
+                self.set_status(format!("Invalid custom condition: {}", msg), Duration::seconds(5));
+                return None;
+            }
+            return Some(TreeView::Custom(self.custom_condition.clone()));
+        }
+        Some(self.view.clone())
+    }
+
+    fn scope_for_active_element(&self, state: &TreeState) -> Option<CaptureScope> {
+        if self.scope_choice == ScopeChoice::Desktop {
+            return Some(CaptureScope::Desktop);
+        }
+
+        let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+        let path = state.path_to_active_ui_element.as_ref()?;
+        let active_element = state.active_element.as_ref()?;
+        let top_level_window = state.active_window().map(|index| ui_tree.node(index).1)?;
+
+        match self.scope_choice {
+            ScopeChoice::Desktop => Some(CaptureScope::Desktop),
+            ScopeChoice::Window => Some(CaptureScope::Window(top_level_window.handle)),
+            ScopeChoice::Process => Some(CaptureScope::Process(active_element.process_id)),
+            ScopeChoice::Subtree => {
+                // the closest element on the path owning a native window is the starting point for the search
+                let handle = path.iter().rev()
+                    .map(|&index| ui_tree.node(index).1.handle)
+                    .find(|&handle| handle != 0)?;
+                Some(CaptureScope::Subtree { handle, runtime_id: active_element.runtime_id.clone() })
+            },
+        }
+    }
+
+    fn add_snapshot(&mut self, snapshot: Snapshot) {
+        // compare the new snapshot with the one it was taken from
+        self.compare_snapshot = Some(self.active_snapshot);
+        self.snapshots.push(snapshot);
+        self.active_snapshot = self.snapshots.len() - 1;
+        self.hover_candidate = None;
+    }
+
+    fn close_snapshot(&mut self, index: usize) {
+        // closing the tab of a running capture cancels it
+        let streamed_into = self.pending_capture.as_ref().and_then(|pending| pending.snapshot);
+        if streamed_into == Some(index) {
+            self.cancel_pending_capture();
+            self.pending_capture = None;
+        } else if let (Some(pending), Some(streamed_into)) = (&mut self.pending_capture, streamed_into) {
+            if streamed_into > index {
+                pending.snapshot = Some(streamed_into - 1);
+            }
+        }
+
+        self.snapshots.remove(index);
+        // keep the indices of the active and compared snapshot pointing to the same tabs
+        let shift = |i: usize| if i > index { i - 1 } else { i };
+        if self.active_snapshot >= self.snapshots.len() || self.active_snapshot > index {
+            self.active_snapshot = self.active_snapshot.saturating_sub(1);
+        }
+        self.compare_snapshot = self.compare_snapshot.filter(|&i| i != index).map(shift);
+        self.hover_candidate = None;
+    }
+
+    /// Shows the position of each tab stop at its element on the screen
+    fn draw_tab_order_badges(&mut self) {
+        let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+        let mut badges: Vec<Highlight> = Vec::new();
+        if self.show_tab_order && self.tab_order.show_badges {
+            if let Some(order) = self.tab_order.current_order(ui_tree) {
+                for stop in order.reachable() {
+                    badges.push(Highlight {
+                        rect: self.screen_rect(&ui_tree.node(stop.index).1.bounding_rect),
+                        color: if stop.issue.is_some() { TAB_ORDER_ISSUE_COLOR } else { TAB_ORDER_BADGE_COLOR },
+                        width: 0,
+                        label: Some(stop.position.unwrap_or_default().to_string()),
+                        label_position: LabelPosition::Inside,
+                        fade: None,
+                    });
+                }
+            }
+        }
+        self.overlay.set(OverlayLayer::TabOrder, badges);
+    }
+
+    /// Shows the frame around the active element and the element hovered on the element map
+    /// on the screen, if highlighting is switched on
+    fn draw_highlight(&mut self, state: &TreeState) {
+        if !self.settings.highlighting {
+            self.overlay.clear(OverlayLayer::ActiveElement);
+            self.overlay.clear(OverlayLayer::Hovered);
+            return;
+        }
+
+        let fade = match self.settings.highlight_fade_ms {
+            0 => None,
+            ms => Some(std::time::Duration::from_millis(ms as u64)),
+        };
+        let active: Vec<Highlight> = state.active_element.iter()
+            .map(|active_element| Highlight {
+                rect: self.screen_rect(&active_element.bounding_rect),
+                color: self.settings.highlight_color,
+                width: self.settings.highlight_width,
+                label: self.settings.highlight_labels.then(|| uiexplore::format_element_label(active_element)),
+                label_position: LabelPosition::Above,
+                fade,
+            })
+            .collect();
+        self.overlay.set(OverlayLayer::ActiveElement, active);
+
+        let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+        let hovered: Vec<Highlight> = state.hovered_ui_element
+            .filter(|&index| Some(index) != state.active_ui_element)
+            .map(|index| Highlight {
+                rect: self.screen_rect(&ui_tree.node(index).1.bounding_rect),
+                color: HOVER_HIGHLIGHT_COLOR,
+                width: self.settings.highlight_width,
+                label: self.settings.highlight_labels.then(|| uiexplore::format_element_label(ui_tree.node(index).1)),
+                label_position: LabelPosition::Above,
+                fade: None,
+            })
+            .into_iter()
+            .collect();
+        self.overlay.set(OverlayLayer::Hovered, hovered);
+    }
+
+    // bounding rectangles are in logical coordinates, the overlay works in physical pixels
+    fn screen_rect(&self, rect: &Rect) -> RECT {
+        let scale = self.app_context.screen_scale;
+        RECT {
+            left: (rect.get_left() as f32 * scale) as i32,
+            top: (rect.get_top() as f32 * scale) as i32,
+            right: (rect.get_right() as f32 * scale) as i32,
+            bottom: (rect.get_bottom() as f32 * scale) as i32,
+        }
+    }
+
+    /// Renders the element map of the top level window of the active element
+    fn render_element_map(&mut self, ui: &mut egui::Ui, state: &mut TreeState) {
+        let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+        let Some(window) = state.active_window() else {
+            ui.label("No active element, select an element to show the map of its window");
+            state.hovered_ui_element = None;
+            return;
+        };
+
+        let outcome = self.element_map.ui(ui, ui_tree, window, state.active_ui_element, self.settings.highlight_color);
+        if outcome.hovered != state.hovered_ui_element {
+            // the tree panel shows the hovered element in the next frame
+            state.hovered_ui_element = outcome.hovered;
+            ui.ctx().request_repaint();
+        }
+        if let Some(index) = outcome.selected {
+            state.select_ui_element(ui_tree, index);
+            ui.ctx().request_repaint();
+        }
+    }
 
     fn set_status(&mut self, msg: String, duration: Duration) {
         let status_msg = AppStatusMsg::new_with_duration(msg, duration);
@@ -355,16 +1292,37 @@ impl UIExplorer {
 impl eframe::App for UIExplorer {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
 
+        // snapshot tabs, rendered first so switching tabs takes effect in this frame
+        egui::TopBottomPanel::top("tab_panel").resizable(false).show(ctx, |ui| {
+            self.render_snapshot_tabs(ui);
+        });
+
+        // background captures started from the toolbar
+        self.poll_pending_capture(ctx);
+        // visible in screenshots and screen sharing again once the capture has finished or was closed
+        self.exclude_from_screenshots();
+
+        // ui automation events of the monitored scope
+        self.event_monitor.poll();
+
         // manage the TreeState
-        let mut state: TreeState;
-        if let Some(tree_state) = &self.tree_state { //.active_element 
-            state = tree_state.clone();
+        let mut state: TreeState = self.snapshots[self.active_snapshot].tree_state.clone();
+
+        // global hotkeys and delayed activation of the hovered element
+        self.process_hotkeys(ctx);
+        self.poll_element_query(ctx, &mut state);
+        if self.settings.recording && !self.is_tracking_frozen(ctx) {
+            self.process_hover_delay(ctx, &mut state);
         } else {
-            state = TreeState::new();
-        }        
+            // an element crossed on the way into the explorer must not be selected after the delay
+            self.hover_candidate = None;
+        }
+
+        // keyboard navigation in the ui tree, based on the nodes visible in the last frame
+        self.process_tree_navigation(ctx, &mut state);
 
         if state.refresh_path_to_active_ui_element {
-            state.update_path_to_active_ui_element(&self.ui_tree);
+            state.update_path_to_active_ui_element(&self.snapshots[self.active_snapshot].ui_tree);
         }
 
         // manage the AppStatusMsg lifecycle
@@ -387,45 +1345,101 @@ impl eframe::App for UIExplorer {
             ui.add_space(2.0);
         
             ui.horizontal(|ui| {
-                if let Some(msg) = &self.status_msg {
+                if let Some(pending) = &self.pending_capture {
+                    let progress = &pending.progress;
+                    ui.spinner();
+                    ui.label(format!("Capturing: {} elements, window {}/{} '{}'", 
+                        progress.nodes_visited, 
+                        (progress.windows_done + 1).min(progress.windows_total), 
+                        progress.windows_total, 
+                        progress.current_window));
+                    if ui.button("Cancel").clicked() {
+                        self.cancel_pending_capture();
+                    }
+                } else if let Some(msg) = &self.status_msg {
                     ui.label(&msg.status_msg);
                 } else {
                     ui.label("Ready");
                 }
-                ui.label(format!("Clear Frame: {}", state.clear_frame));
+                ui.label(format!("| {}", self.snapshots[self.active_snapshot].ui_tree.get_view().label()));
+                if self.pinned {
+                    if self.is_hotkey_registered(HotkeyAction::TogglePin) {
+                        ui.label("| 📌 Pinned (Ctrl+Shift+F to release)");
+                    } else {
+                        ui.label("| 📌 Pinned");
+                    }
+                } else if self.is_hotkey_registered(HotkeyAction::CaptureUnderCursor) {
+                    ui.label("| Ctrl+Shift+E: capture element under cursor");
+                }
             });
         
             ui.add_space(2.0);
         
         });
+
+        // log panel
+        if self.settings.show_log {
+            egui::TopBottomPanel::bottom("log_panel")
+            .resizable(true)
+            .default_height(200.0)
+            .show(ctx, |ui| {
+                logging::ui(ui, &mut self.log_level);
+            });
+        }
         
 
         // UI tree 
         egui::SidePanel::left("left_panel")
-        .min_width(800.0)
-        .max_width(1400.0)                
+        .min_width(self.settings.tree_panel_min_width)
+        .max_width(self.settings.tree_panel_max_width)
         .show(ctx, |ui| { // .min_width(300.0).max_width(600.0)
 
             egui::ScrollArea::vertical()
             .auto_shrink(false)
             .show(ui, |ui| {
-                // printfmt!("running 'render_ui_tree' function on UIExplorer");
+                // log::trace!("running 'render_ui_tree' function on UIExplorer");
                 self.render_ui_tree(ui, &mut state);
 
             });
 
         });
 
+        // tab order of the analyzed window
+        if self.show_tab_order {
+            let mut tab_order_action: Option<TabOrderAction> = None;
+            egui::SidePanel::right("tab_order_panel")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.heading("Tab Order");
+                tab_order_action = self.tab_order.ui(ui, &self.snapshots[self.active_snapshot].ui_tree, state.active_ui_element);
+            });
+            match tab_order_action {
+                Some(TabOrderAction::Analyze) => {
+                    let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+                    match state.active_window() {
+                        Some(window) => self.tab_order.order = Some(TabOrder::analyze(ui_tree, window)),
+                        None => self.set_status("Select an element of the window to analyze".to_string(), Duration::seconds(5)),
+                    }
+                },
+                Some(TabOrderAction::Select(index)) => state.select_ui_element(&self.snapshots[self.active_snapshot].ui_tree, index),
+                None => (),
+            }
+        }
+        self.draw_tab_order_badges();
+
         // options bar
         egui::TopBottomPanel::top("top_panel").resizable(true).show(ctx, |ui| {
 
             ui.add_space(2.0);
 
+            let frozen = self.is_tracking_frozen(ctx);
+
             ui.input(|i| {
                 
                 for event in &i.raw.events {
     
-                    if !self.recording && matches!(
+                    if (!self.settings.recording || frozen) && matches!(
                         event,
                         egui::Event::PointerMoved { .. }
                             | egui::Event::MouseMoved { .. }
@@ -436,8 +1450,8 @@ impl eframe::App for UIExplorer {
                 }
                     
                     // for the visual event summary
-                    if self.show_history {
-                        let summary = event_summary(event, self.ui_tree.get_elements());
+                    if self.settings.show_history {
+                        let summary = event_summary(event, self.snapshots[self.active_snapshot].ui_tree.get_elements());
                         let full = format!("{event:#?}");
                         self.history.add(summary, full);    
                     }
@@ -449,36 +1463,71 @@ impl eframe::App for UIExplorer {
     
             ui.horizontal(|ui| {
                 
-                let prev_highlight = self.highlighting;
-                ui.button("🔄").on_hover_text("Refresh");
+                let capture_idle = self.pending_capture.is_none();
+                if ui.add_enabled(capture_idle, egui::Button::new("🔄")).on_hover_text("Refresh (opens a new tab)").clicked() {
+                    self.refresh_snapshot();
+                }
+                egui::ComboBox::from_id_salt("capture_scope")
+                    .selected_text(self.scope_choice.label())
+                    .show_ui(ui, |ui| {
+                        for choice in [ScopeChoice::Desktop, ScopeChoice::Window, ScopeChoice::Process, ScopeChoice::Subtree] {
+                            ui.selectable_value(&mut self.scope_choice, choice, choice.label());
+                        }
+                    });
+                egui::ComboBox::from_id_salt("tree_view")
+                    .selected_text(match self.view { TreeView::Custom(_) => "Custom View".to_string(), _ => self.view.label() })
+                    .show_ui(ui, |ui| {
+                        for view in [TreeView::Raw, TreeView::Control, TreeView::Content, TreeView::Custom(self.custom_condition.clone())] {
+                            let label = match view { TreeView::Custom(_) => "Custom View".to_string(), _ => view.label() };
+                            let selected = std::mem::discriminant(&self.view) == std::mem::discriminant(&view);
+                            if ui.selectable_label(selected, label).clicked() {
+                                self.show_condition_builder = matches!(view, TreeView::Custom(_));
+                                self.view = view;
+                            }
+                        }
+                    });
+                if matches!(self.view, TreeView::Custom(_)) {
+                    ui.toggle_value(&mut self.show_condition_builder, "🔧").on_hover_text("Edit custom condition");
+                }
+                if ui.add_enabled(capture_idle, egui::Button::new("📷")).on_hover_text("Capture the selected scope (opens a new tab)").clicked() {
+                    self.capture_scope(&state);
+                }
                 ui.add_space(2.0);
                 ui.label(" | ");
                 ui.add_space(2.0);
-                ui.checkbox(&mut self.highlighting, "Show Highlight Rectangle");
-                ui.checkbox(&mut self.recording, "Track Cursor");
-                if self.recording {
-                    ui.checkbox(&mut self.show_history, "Show Event History");
-                }
-                let new_highlight = self.highlighting;
-                
-                // clear any highlighted surrounding rectangle as 
-                if new_highlight != prev_highlight && new_highlight == false {
-                    printfmt!("Old highlight value was {}, new one is {}", prev_highlight, new_highlight);
-                    let rect: RECT = RECT { 
-                        left: 0, 
-                        top: 0, 
-                        right: self.app_context.screen_width, 
-                        bottom: self.app_context.screen_height, 
-                    };
-                    rectangle::clear_frame(rect).unwrap();
-                    state.clear_frame = false;
+                ui.checkbox(&mut self.settings.highlighting, "Show Highlight Rectangle");
+                ui.checkbox(&mut self.settings.recording, "Track Cursor");
+                if self.settings.recording {
+                    let mut pinned = self.pinned;
+                    let mut pin_checkbox = ui.checkbox(&mut pinned, "📌 Pin Element");
+                    if self.is_hotkey_registered(HotkeyAction::TogglePin) {
+                        pin_checkbox = pin_checkbox.on_hover_text("Ctrl+Shift+F");
+                    }
+                    if pin_checkbox.changed() {
+                        self.toggle_pin();
+                    }
+                    ui.checkbox(&mut self.settings.freeze_over_explorer, "Freeze over Explorer")
+                        .on_hover_text("Stop tracking while the cursor is over this window");
+                    ui.label("Hover Delay:");
+                    ui.add(egui::DragValue::new(&mut self.settings.hover_delay_ms).range(0..=5000).speed(10).suffix(" ms"));
+                    ui.checkbox(&mut self.settings.show_history, "Show Event History");
                 }
+                ui.add_space(2.0);
+                ui.label(" | ");
+                ui.add_space(2.0);
+                ui.toggle_value(&mut self.show_screenshot, "🖼").on_hover_text("Screenshot");
+                ui.toggle_value(&mut self.show_export, "💾").on_hover_text("Export / Import");
+                ui.toggle_value(&mut self.show_tab_order, "⌨").on_hover_text("Tab Order");
+                ui.toggle_value(&mut self.show_audit, "♿").on_hover_text("Accessibility Audit");
+                ui.toggle_value(&mut self.show_event_monitor, "📡").on_hover_text("Event Monitor");
+                ui.toggle_value(&mut self.settings.show_log, "📜").on_hover_text("Show Log");
+                ui.toggle_value(&mut self.show_settings, "⚙").on_hover_text("Settings");
                 
             });
 
             ui.add_space(2.0);
 
-            if self.show_history {
+            if self.settings.show_history {
                 ui.add_space(6.0);
                 self.history.ui(ui);
             }
@@ -487,99 +1536,119 @@ impl eframe::App for UIExplorer {
         });
 
         
+        // optionally render the frame around the active element on the screen
+        self.draw_highlight(&state);
+
         // main screen with element details
         egui::CentralPanel::default().show(ctx, |ui| {
+
+            // breadcrumb bar with the path from the top level window to the active element
+            if let Some(index) = self.render_breadcrumbs(ui, &state) {
+                state.select_ui_element(&self.snapshots[self.active_snapshot].ui_tree, index);
+            }
+            ui.separator();
+
+            // the properties of the active element or the map of the elements of its window
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.central_view, CentralView::Properties, "📋 Properties");
+                ui.selectable_value(&mut self.central_view, CentralView::ElementMap, "🗺 Element Map");
+            });
+            ui.separator();
+
+            if self.central_view == CentralView::ElementMap {
+                self.render_element_map(ui, &mut state);
+                return;
+            }
+            state.hovered_ui_element = None;
+
+            // side by side comparison with another snapshot
+            if self.snapshots.len() > 1 {
+                ui.horizontal(|ui| {
+                    let mut compare_with = self.compare_snapshot.filter(|&other| other != self.active_snapshot);
+                    let selected_text = compare_with.map(|other| self.snapshots[other].title()).unwrap_or("-".to_string());
+                    ui.label("Compare with:");
+                    egui::ComboBox::from_id_salt("compare_snapshot")
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut compare_with, None, "-");
+                            for (other, snapshot) in self.snapshots.iter().enumerate() {
+                                if other != self.active_snapshot {
+                                    ui.selectable_value(&mut compare_with, Some(other), snapshot.title());
+                                }
+                            }
+                        });
+                    if ui.button("⇔").on_hover_text("Compare with the previous snapshot").clicked() {
+                        compare_with = Some(if self.active_snapshot > 0 { self.active_snapshot - 1 } else { 1 });
+                    }
+                    self.compare_snapshot = compare_with;
+                });
+                ui.separator();
+            }
                 
             ui.horizontal(|ui| {
 
                 if let Some(active_element) = &state.active_element {
-                    
-                    // Optionally render the frame around the active element on the screen
-                    if self.highlighting {
-                        let left: f32 = active_element.bounding_rect.get_left() as f32 * self.app_context.screen_scale;
-                        let top: f32 = active_element.bounding_rect.get_top() as f32 * self.app_context.screen_scale;
-                        let right: f32 = active_element.bounding_rect.get_right() as f32 * self.app_context.screen_scale;
-                        let bottom: f32 = active_element.bounding_rect.get_bottom() as f32 * self.app_context.screen_scale;
-
-                        let rect: RECT = RECT { 
-                            left: left as i32, 
-                            top: top as i32, 
-                            right: right as i32, 
-                            bottom: bottom as i32, 
-                        };
-                        
-                        if let Some(prev_element) = &state.prev_element {
-                            let prev_left: f32 = prev_element.bounding_rect.get_left() as f32 * self.app_context.screen_scale;
-                            let prev_top: f32 = prev_element.bounding_rect.get_top() as f32 * self.app_context.screen_scale;
-                            let prev_right: f32 = prev_element.bounding_rect.get_right() as f32 * self.app_context.screen_scale;
-                            let prev_bottom: f32 = prev_element.bounding_rect.get_bottom() as f32 * self.app_context.screen_scale;
-
-                            let prev_rect: RECT = RECT {
-                                left: prev_left as i32, 
-                                top: prev_top as i32, 
-                                right: prev_right as i32, 
-                                bottom: prev_bottom as i32,     
+
+                    // optionally look up the counterpart of the active element in another snapshot
+                    let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+                    let counterpart: Option<(String, Option<UIElementProps>)> = match (self.compare_snapshot, state.active_ui_element) {
+                        (Some(other), Some(index)) if other != self.active_snapshot => {
+                            let other_snapshot = &self.snapshots[other];
+                            let key = CounterpartKey::new(ui_tree, index, &other_snapshot.ui_tree);
+                            let other_index = match &self.counterpart {
+                                Some((cached_key, other_index)) if *cached_key == key => *other_index,
+                                _ => {
+                                    let other_index = ui_tree.find_counterpart(index, &other_snapshot.ui_tree);
+                                    self.counterpart = Some((key, other_index));
+                                    other_index
+                                },
                             };
-                            if state.clear_frame { //rect != prev_rect && 
-                                printfmt!("Cleanup needed - new: {:?} vs old: {:?}", rect, prev_rect);
-                                rectangle::clear_frame(prev_rect).unwrap();
-                                rectangle::draw_frame(rect, 4).unwrap();
-                                state.clear_frame = false;
-                            } else {
-                                rectangle::draw_frame(rect, 4).unwrap();
-                            }
-                        } else {
-                            rectangle::draw_frame(rect, 4).unwrap();
-                        }
-                    } 
-                    
+                            let other_element = other_index.map(|other_index| other_snapshot.ui_tree.node(other_index).1.clone());
+                            Some((other_snapshot.title(), other_element))
+                        },
+                        _ => None,
+                    };
+                    let counterpart_props = counterpart.as_ref().map(|(_title, other_element)| {
+                        other_element.as_ref().map(|other_element| other_element.property_list())
+                    });
+
                     // display the element properties 
+                    let mut copied = false;
                     egui::Grid::new("some_unique_id").min_col_width(100.0).show(ui, |ui| {
-                        ui.label("Name:");
-                        ui.label(active_element.name.clone());
-                        ui.end_row();
-                    
-                        ui.label("Control Type:");
-                        ui.label(active_element.control_type.clone());
-                        ui.end_row();
-
-                        ui.label("Localized Control Type:");
-                        ui.label(active_element.localized_control_type.clone());
-                        if ui.button("📋").clicked() {
-                            ui.ctx().copy_text(active_element.localized_control_type.clone());
-                            self.set_status("Value copied to clipboard".to_string(), Duration::seconds(2));
+                        if let Some((other_title, _other_element)) = &counterpart {
+                            ui.label("");
+                            ui.strong(self.snapshots[self.active_snapshot].title());
+                            ui.label("");
+                            ui.strong(other_title);
+                            ui.end_row();
                         }
-                        ui.end_row();
 
-                        ui.label("Framework ID:");
-                        ui.label(active_element.framework_id.clone());
-                        ui.end_row();
-
-                        ui.label("Class Name:");
-                        ui.label(active_element.classname.clone());
-                        if ui.button("📋").clicked() {
-                            ui.ctx().copy_text(active_element.classname.clone());
-                            self.set_status("Value copied to clipboard".to_string(), Duration::seconds(2));
+                        for (row, (label, value)) in active_element.property_list().into_iter().enumerate() {
+                            ui.label(format!("{}:", label));
+                            ui.label(value.clone());
+                            if ui.button("📋").clicked() {
+                                ui.ctx().copy_text(value.clone());
+                                copied = true;
+                            }
+                            match &counterpart_props {
+                                Some(Some(other_props)) => {
+                                    let other_value = &other_props[row].1;
+                                    if *other_value == value {
+                                        ui.label(other_value.clone());
+                                    } else {
+                                        // highlight properties that changed between the snapshots
+                                        ui.colored_label(ui.visuals().warn_fg_color, other_value.clone());
+                                    }
+                                },
+                                Some(None) if row == 0 => { ui.weak("No counterpart found"); },
+                                _ => (),
+                            }
+                            ui.end_row();
                         }
-                        ui.end_row();
-
-                        ui.label("Runtime ID:");
-                        ui.label(active_element.runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-"));
-                        ui.end_row();
-
-                        ui.label("Surrounding Rectangle:");
-                        ui.label(format!("{:?}", active_element.bounding_rect));
-                        ui.end_row();
-                        
-                        ui.label("level:");
-                        ui.label(active_element.level.to_string());
-                        ui.end_row();
-                        
-                        ui.label("z-order:");
-                        ui.label(active_element.z_order.to_string());
-                        ui.end_row();
-
                     });    
+                    if copied {
+                        self.set_status("Value copied to clipboard".to_string(), Duration::seconds(2));
+                    }
 
                 }
                 else {
@@ -593,8 +1662,135 @@ impl eframe::App for UIExplorer {
 
 
 
+        // settings dialog
+        let mut show_settings = self.show_settings;
+        egui::Window::new("Settings")
+            .open(&mut show_settings)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                self.settings.ui(ui);
+            });
+        self.show_settings = show_settings;
+
+        // condition builder for the custom tree view
+        let mut show_condition_builder = self.show_condition_builder && matches!(self.view, TreeView::Custom(_));
+        egui::Window::new("Custom Condition")
+            .open(&mut show_condition_builder)
+            .resizable(true)
+            .show(ctx, |ui| {
+                self.custom_condition.ui(ui, "custom_condition");
+                ui.separator();
+                match self.custom_condition.validate() {
+                    Ok(()) => ui.weak(self.custom_condition.to_string()),
+                    Err(msg) => ui.colored_label(ui.visuals().error_fg_color, msg),
+                };
+            });
+        self.show_condition_builder = show_condition_builder;
+
+        // ui automation event monitor
+        let mut show_event_monitor = self.show_event_monitor;
+        let mut monitor_action: Option<MonitorAction> = None;
+        egui::Window::new("Event Monitor")
+            .open(&mut show_event_monitor)
+            .resizable(true)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                monitor_action = self.event_monitor.ui(ui);
+            });
+        self.show_event_monitor = show_event_monitor;
+        match monitor_action {
+            Some(MonitorAction::Start) => {
+                // monitor the scope selected for captures
+                let scope = self.scope_for_active_element(&state).unwrap_or(CaptureScope::Desktop);
+                if let Err(msg) = self.event_monitor.start(scope, ctx) {
+                    self.set_status(format!("Failed to start the event monitor: {}", msg), Duration::seconds(5));
+                }
+            },
+            Some(MonitorAction::Stop) => self.event_monitor.stop(),
+            Some(MonitorAction::Select(runtime_id)) => {
+                let ui_tree = &self.snapshots[self.active_snapshot].ui_tree;
+                match ui_tree.find_by_runtime_id(&runtime_id) {
+                    Some(index) => state.select_ui_element(ui_tree, index),
+                    None => self.set_status("The source element is not part of this snapshot, refresh to include it".to_string(), Duration::seconds(5)),
+                }
+            },
+            None => (),
+        }
+
+        // accessibility audit of the active snapshot
+        let mut show_audit = self.show_audit;
+        let mut audit_selection: Option<usize> = None;
+        egui::Window::new("Accessibility Audit")
+            .open(&mut show_audit)
+            .resizable(true)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                audit_selection = self.audit.ui(ui, &self.snapshots[self.active_snapshot].ui_tree);
+            });
+        self.show_audit = show_audit;
+        if let Some(index) = audit_selection {
+            state.select_ui_element(&self.snapshots[self.active_snapshot].ui_tree, index);
+        }
+
+        // screenshot taken with the active snapshot, with the frame of the active element
+        let mut show_screenshot = self.show_screenshot;
+        let mut screenshot_status: Option<String> = None;
+        egui::Window::new("Screenshot")
+            .open(&mut show_screenshot)
+            .resizable(true)
+            .default_size([800.0, 500.0])
+            .show(ctx, |ui| {
+                screenshot_status = self.screenshot.ui(ui, &self.snapshots[self.active_snapshot].ui_tree, state.active_ui_element, 
+                    &mut self.settings.screenshot_path, self.settings.highlight_color, self.settings.highlight_width);
+            });
+        self.show_screenshot = show_screenshot;
+        if let Some(msg) = screenshot_status {
+            self.set_status(msg, Duration::seconds(5));
+        }
+
+        // export of the active snapshot and import of exported trees
+        let mut show_export = self.show_export;
+        let mut export_outcome: Option<ExportOutcome> = None;
+        egui::Window::new("Export / Import")
+            .open(&mut show_export)
+            .resizable(false)
+            .show(ctx, |ui| {
+                export_outcome = self.export.ui(ui, &self.snapshots[self.active_snapshot].ui_tree, state.active_ui_element, &mut self.settings.export_path);
+            });
+        self.show_export = show_export;
+
+        // xml files dropped onto the window are imported as well, each into its own tab
+        let mut export_outcomes: Vec<ExportOutcome> = export_outcome.into_iter().collect();
+        let dropped: Vec<std::path::PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect());
+        for path in dropped {
+            export_outcomes.push(match export::import_from_file(&path) {
+                Ok(ui_tree) => ExportOutcome::Imported(ui_tree),
+                Err(msg) => ExportOutcome::Status(format!("Import failed: {}", msg)),
+            });
+        }
+        let mut status_msgs: Vec<String> = Vec::new();
+        for export_outcome in export_outcomes {
+            match export_outcome {
+                ExportOutcome::Status(msg) => status_msgs.push(msg),
+                ExportOutcome::Imported(ui_tree) => {
+                    log::info!("imported {} elements", ui_tree.get_elements().len());
+                    // the state belongs to the snapshot shown so far, the imported one starts afresh
+                    self.snapshots[self.active_snapshot].tree_state = std::mem::replace(&mut state, TreeState::new());
+                    self.add_snapshot(Snapshot::new(SnapshotKind::Imported, ui_tree));
+                },
+            }
+        }
+        if !status_msgs.is_empty() {
+            self.set_status(status_msgs.join("; "), Duration::seconds(5));
+        }
+
         // self.active_element = state.active_element;
-        self.tree_state = Some(state);
+        self.snapshots[self.active_snapshot].tree_state = state;
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.save(storage);
     }
 
 
@@ -631,6 +1827,26 @@ fn event_summary(event: &egui::Event, ui_elements: &Vec<UIElementInTree>) -> Str
     }
 }
 
+fn breadcrumb_label(ui_element: &UIElementProps) -> String {
+    const MAX_NAME_LEN: usize = 30;
+    let name = if ui_element.name.chars().count() > MAX_NAME_LEN {
+        format!("{}…", ui_element.name.chars().take(MAX_NAME_LEN).collect::<String>())
+    } else {
+        ui_element.name.clone()
+    };
+    if name.is_empty() {
+        ui_element.control_type.clone()
+    } else {
+        format!("{} '{}'", ui_element.control_type, name)
+    }
+}
+
+fn set_header_open(ctx: &egui::Context, header_id: egui::Id, open: bool) {
+    let mut header_state = egui::collapsing_header::CollapsingState::load_with_default_open(ctx, header_id, false);
+    header_state.set_open(open);
+    header_state.store(ctx);
+}
+
 fn is_in_path_to_active_element(active_element: Option<usize>, path_to_active_element: &Option<Vec<usize>>) -> bool {
     
     if active_element.is_none() || path_to_active_element.is_none() {
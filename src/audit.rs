@@ -0,0 +1,607 @@
+//! Accessibility audit of a captured ui tree. The rules are plain functions over the tree,
+//! so they run the same way in the findings panel and headless without the GUI.
+#![allow(dead_code)]
+
+use chrono::{DateTime, Local};
+use eframe::egui;
+
+use crate::{UIHashMap, UITree};
+use crate::uiexplore::UIElementProps;
+
+// control types the user interacts with, which need an accessible name
+const INTERACTIVE_CONTROL_TYPES: [&str; 16] = [
+    "Button",
+    "CheckBox",
+    "ComboBox",
+    "Edit",
+    "Hyperlink",
+    "ListItem",
+    "MenuItem",
+    "RadioButton",
+    "ScrollBar",
+    "Slider",
+    "Spinner",
+    "SplitButton",
+    "TabItem",
+    "TreeItem",
+    "DataItem",
+    "Thumb",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+            Severity::Info => "Info",
+        }
+    }
+}
+
+/// A problem found by a rule, `index` is the tree index of the offending element
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub rule_id: &'static str,
+    pub severity: Severity,
+    pub index: usize,
+    pub message: String,
+}
+
+/// A rule returns the tree index of each offending element together with a message
+pub struct AuditRule {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub severity: Severity,
+    pub check: fn(&UITree) -> Vec<(usize, String)>,
+}
+
+pub const RULES: [AuditRule; 8] = [
+    AuditRule {
+        id: "interactive-without-name",
+        title: "Interactive elements must have a name",
+        severity: Severity::Error,
+        check: check_interactive_without_name,
+    },
+    AuditRule {
+        id: "duplicate-sibling-name",
+        title: "Interactive siblings should have distinct names",
+        severity: Severity::Warning,
+        check: check_duplicate_sibling_name,
+    },
+    AuditRule {
+        id: "duplicate-automation-id",
+        title: "Automation ids should be unique among siblings",
+        severity: Severity::Warning,
+        check: check_duplicate_automation_id,
+    },
+    AuditRule {
+        id: "missing-automation-id",
+        title: "Interactive elements should have an automation id",
+        severity: Severity::Info,
+        check: check_missing_automation_id,
+    },
+    AuditRule {
+        id: "focusable-empty-bounds",
+        title: "Focusable elements must have a non-empty bounding rectangle",
+        severity: Severity::Error,
+        check: check_focusable_empty_bounds,
+    },
+    AuditRule {
+        id: "focusable-offscreen",
+        title: "Focusable elements should not be offscreen",
+        severity: Severity::Warning,
+        check: check_focusable_offscreen,
+    },
+    AuditRule {
+        id: "image-without-description",
+        title: "Images must have a name or help text",
+        severity: Severity::Warning,
+        check: check_image_without_description,
+    },
+    AuditRule {
+        id: "child-outside-parent",
+        title: "Elements should be within the bounds of their parent",
+        severity: Severity::Warning,
+        check: check_child_outside_parent,
+    },
+];
+
+/// Runs the rules over the tree, the findings are ordered by severity and tree position
+pub fn run_audit<'a>(ui_tree: &UITree, rules: impl IntoIterator<Item = &'a AuditRule>) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    for rule in rules {
+        for (index, message) in (rule.check)(ui_tree) {
+            findings.push(Finding { rule_id: rule.id, severity: rule.severity, index, message });
+        }
+    }
+    findings.sort_by_key(|finding| (finding.severity, finding.index));
+    findings
+}
+
+pub fn find_rule(id: &str) -> Option<&'static AuditRule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+/// Returns the labels of the element and its ancestors below the desktop, top level window first
+pub fn element_path(ui_tree: &UITree, index: usize) -> Vec<String> {
+    ui_tree.get_tree().get_path_to_element(index)
+        .into_iter()
+        .filter(|&path_index| ui_tree.node(path_index).1.level > 0)
+        .map(|path_index| element_label(ui_tree.node(path_index).1))
+        .collect()
+}
+
+// a short label identifying an element in messages and paths
+pub fn element_label(ui_elem_props: &UIElementProps) -> String {
+    if ui_elem_props.automation_id.is_empty() {
+        format!("{} '{}'", ui_elem_props.control_type, ui_elem_props.name)
+    } else {
+        format!("{} '{}' #{}", ui_elem_props.control_type, ui_elem_props.name, ui_elem_props.automation_id)
+    }
+}
+
+fn is_interactive(ui_elem_props: &UIElementProps) -> bool {
+    INTERACTIVE_CONTROL_TYPES.contains(&ui_elem_props.control_type.as_str())
+}
+
+fn is_empty_rect(rect: &uiautomation::types::Rect) -> bool {
+    rect.get_right() <= rect.get_left() || rect.get_bottom() <= rect.get_top()
+}
+
+fn contains_rect(outer: &uiautomation::types::Rect, inner: &uiautomation::types::Rect) -> bool {
+    inner.get_left() >= outer.get_left()
+        && inner.get_top() >= outer.get_top()
+        && inner.get_right() <= outer.get_right()
+        && inner.get_bottom() <= outer.get_bottom()
+}
+
+// all elements below the desktop, the desktop itself is not part of any application
+fn audited_elements(ui_tree: &UITree) -> Vec<(usize, &UIElementProps)> {
+    descendants(ui_tree, ui_tree.root())
+        .into_iter()
+        .map(|index| (index, ui_tree.node(index).1))
+        .filter(|(_, ui_elem_props)| ui_elem_props.level > 0)
+        .collect()
+}
+
+// the indices of all nodes below `index` in depth first order
+fn descendants(ui_tree: &UITree, index: usize) -> Vec<usize> {
+    let mut result: Vec<usize> = Vec::new();
+    let mut stack: Vec<usize> = ui_tree.children(index).iter().rev().copied().collect();
+    while let Some(current) = stack.pop() {
+        result.push(current);
+        stack.extend(ui_tree.children(current).iter().rev().copied());
+    }
+    result
+}
+
+// the parents whose children are compared with each other, siblings directly below
+// the desktop belong to different applications
+fn audited_parents(ui_tree: &UITree) -> Vec<usize> {
+    audited_elements(ui_tree).into_iter().map(|(index, _)| index).filter(|&index| !ui_tree.children(index).is_empty()).collect()
+}
+
+fn check_interactive_without_name(ui_tree: &UITree) -> Vec<(usize, String)> {
+    audited_elements(ui_tree)
+        .into_iter()
+        .filter(|(_, props)| is_interactive(props) && props.name.trim().is_empty())
+        .map(|(index, props)| (index, format!("{} has no name", props.control_type)))
+        .collect()
+}
+
+fn check_duplicate_sibling_name(ui_tree: &UITree) -> Vec<(usize, String)> {
+    let mut findings: Vec<(usize, String)> = Vec::new();
+    for parent in audited_parents(ui_tree) {
+        let mut siblings: UIHashMap<(&str, &str), Vec<usize>> = UIHashMap::new();
+        for &child in ui_tree.children(parent) {
+            let props = ui_tree.node(child).1;
+            if is_interactive(props) && !props.name.trim().is_empty() {
+                siblings.entry((props.control_type.as_str(), props.name.as_str())).or_default().push(child);
+            }
+        }
+        for ((control_type, name), indices) in siblings {
+            if indices.len() > 1 {
+                for &index in &indices {
+                    findings.push((index, format!("{} '{}' is shared by {} siblings", control_type, name, indices.len())));
+                }
+            }
+        }
+    }
+    findings
+}
+
+fn check_duplicate_automation_id(ui_tree: &UITree) -> Vec<(usize, String)> {
+    let mut findings: Vec<(usize, String)> = Vec::new();
+    for parent in audited_parents(ui_tree) {
+        let mut siblings: UIHashMap<&str, Vec<usize>> = UIHashMap::new();
+        for &child in ui_tree.children(parent) {
+            let props = ui_tree.node(child).1;
+            if !props.automation_id.is_empty() {
+                siblings.entry(props.automation_id.as_str()).or_default().push(child);
+            }
+        }
+        for (automation_id, indices) in siblings {
+            if indices.len() > 1 {
+                for &index in &indices {
+                    findings.push((index, format!("automation id '{}' is shared by {} siblings", automation_id, indices.len())));
+                }
+            }
+        }
+    }
+    findings
+}
+
+fn check_missing_automation_id(ui_tree: &UITree) -> Vec<(usize, String)> {
+    audited_elements(ui_tree)
+        .into_iter()
+        .filter(|(_, props)| is_interactive(props) && props.automation_id.is_empty())
+        .map(|(index, props)| (index, format!("{} has no automation id", element_label(props))))
+        .collect()
+}
+
+fn check_focusable_empty_bounds(ui_tree: &UITree) -> Vec<(usize, String)> {
+    // offscreen elements legitimately have no bounds, they are reported by focusable-offscreen
+    audited_elements(ui_tree)
+        .into_iter()
+        .filter(|(_, props)| props.is_keyboard_focusable && !props.is_offscreen && is_empty_rect(&props.bounding_rect))
+        .map(|(index, props)| (index, format!("{} is focusable but its bounding rectangle {:?} is empty", element_label(props), props.bounding_rect)))
+        .collect()
+}
+
+fn check_focusable_offscreen(ui_tree: &UITree) -> Vec<(usize, String)> {
+    audited_elements(ui_tree)
+        .into_iter()
+        .filter(|(_, props)| props.is_keyboard_focusable && props.is_enabled && props.is_offscreen)
+        .map(|(index, props)| (index, format!("{} is focusable but offscreen", element_label(props))))
+        .collect()
+}
+
+fn check_image_without_description(ui_tree: &UITree) -> Vec<(usize, String)> {
+    audited_elements(ui_tree)
+        .into_iter()
+        .filter(|(_, props)| props.control_type == "Image" && props.name.trim().is_empty() && props.help_text.trim().is_empty())
+        .map(|(index, _)| (index, "image has neither a name nor a help text".to_string()))
+        .collect()
+}
+
+fn check_child_outside_parent(ui_tree: &UITree) -> Vec<(usize, String)> {
+    let mut findings: Vec<(usize, String)> = Vec::new();
+    for parent in audited_parents(ui_tree) {
+        let parent_props = ui_tree.node(parent).1;
+        if parent_props.is_offscreen || is_empty_rect(&parent_props.bounding_rect) {
+            continue;
+        }
+        for &child in ui_tree.children(parent) {
+            let props = ui_tree.node(child).1;
+            // scrolled out content is offscreen and outside its container by design
+            if props.is_offscreen || is_empty_rect(&props.bounding_rect) {
+                continue;
+            }
+            if !contains_rect(&parent_props.bounding_rect, &props.bounding_rect) {
+                findings.push((child, format!("{} {:?} extends beyond its parent {} {:?}",
+                    element_label(props), props.bounding_rect, element_label(parent_props), parent_props.bounding_rect)));
+            }
+        }
+    }
+    findings
+}
+
+/// Formats the findings as plain text, one line per finding
+pub fn format_report(ui_tree: &UITree, findings: &[Finding]) -> String {
+    let mut report = format!("Accessibility audit of {} captured at {}\n", ui_tree.get_scope().label(), ui_tree.get_captured_at().format("%Y-%m-%d %H:%M:%S"));
+    for severity in [Severity::Error, Severity::Warning, Severity::Info] {
+        report.push_str(&format!("{}: {}\n", severity.label(), findings.iter().filter(|finding| finding.severity == severity).count()));
+    }
+    report.push('\n');
+    for finding in findings {
+        report.push_str(&format!("{:<7} {:<26} {}: {}\n", finding.severity.label(), finding.rule_id, element_path(ui_tree, finding.index).join(" > "), finding.message));
+    }
+    report
+}
+
+/// Shows the findings of the last audit, clicking a finding selects its element in the tree
+pub struct AuditPanel {
+    enabled_rules: Vec<&'static str>,
+    min_severity: Severity,
+    findings: Vec<Finding>,
+    // the snapshot the findings belong to, identified by its capture time
+    audited_at: Option<DateTime<Local>>,
+    report: String,
+}
+
+impl AuditPanel {
+    pub fn new() -> Self {
+        AuditPanel {
+            enabled_rules: RULES.iter().map(|rule| rule.id).collect(),
+            min_severity: Severity::Info,
+            findings: Vec::new(),
+            audited_at: None,
+            report: String::new(),
+        }
+    }
+
+    pub fn run(&mut self, ui_tree: &UITree) {
+        let findings = run_audit(ui_tree, RULES.iter().filter(|rule| self.enabled_rules.contains(&rule.id)));
+        log::info!("audit found {} problems", findings.len());
+        self.report = format_report(ui_tree, &findings);
+        self.findings = findings;
+        self.audited_at = Some(ui_tree.get_captured_at());
+    }
+
+    /// Renders the panel for the active snapshot, returns the tree index of a clicked finding
+    pub fn ui(&mut self, ui: &mut egui::Ui, ui_tree: &UITree) -> Option<usize> {
+        let mut selected: Option<usize> = None;
+
+        egui::CollapsingHeader::new("Rules").show(ui, |ui| {
+            for rule in &RULES {
+                let mut enabled = self.enabled_rules.contains(&rule.id);
+                if ui.checkbox(&mut enabled, format!("{} ({})", rule.title, rule.severity.label())).on_hover_text(rule.id).changed() {
+                    if enabled {
+                        self.enabled_rules.push(rule.id);
+                    } else {
+                        self.enabled_rules.retain(|&id| id != rule.id);
+                    }
+                }
+            }
+        });
+
+        let is_current = self.audited_at == Some(ui_tree.get_captured_at());
+        ui.horizontal(|ui| {
+            if ui.add_enabled(ui_tree.is_complete(), egui::Button::new("▶ Run Audit"))
+                .on_disabled_hover_text("Wait until the capture is complete")
+                .clicked() {
+                self.run(ui_tree);
+            }
+            ui.label("Show:");
+            egui::ComboBox::from_id_salt("audit_severity")
+                .selected_text(self.min_severity.label())
+                .show_ui(ui, |ui| {
+                    for severity in [Severity::Error, Severity::Warning, Severity::Info] {
+                        ui.selectable_value(&mut self.min_severity, severity, severity.label());
+                    }
+                });
+            if ui.add_enabled(!self.report.is_empty(), egui::Button::new("📋 Copy Report")).clicked() {
+                ui.ctx().copy_text(self.report.clone());
+            }
+        });
+
+        if self.audited_at.is_some() {
+            let count = |severity: Severity| self.findings.iter().filter(|finding| finding.severity == severity).count();
+            ui.label(format!("{} errors, {} warnings, {} infos", count(Severity::Error), count(Severity::Warning), count(Severity::Info)));
+            if !is_current {
+                ui.colored_label(ui.visuals().warn_fg_color, "The findings belong to another snapshot, run the audit again");
+            }
+        }
+        ui.separator();
+
+        let findings: Vec<&Finding> = self.findings.iter().filter(|finding| finding.severity <= self.min_severity).collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, findings.len(), |ui, rows| {
+                for finding in &findings[rows] {
+                    let color = match finding.severity {
+                        Severity::Error => ui.visuals().error_fg_color,
+                        Severity::Warning => ui.visuals().warn_fg_color,
+                        Severity::Info => ui.visuals().text_color(),
+                    };
+                    let text = egui::RichText::new(format!("{:<7}  {}", finding.severity.label(), finding.message)).color(color);
+                    let response = ui.add_enabled(is_current, egui::SelectableLabel::new(false, text));
+                    if is_current {
+                        let path = element_path(ui_tree, finding.index).join(" > ");
+                        if response.on_hover_text(format!("{}\n{}", finding.rule_id, path)).clicked() {
+                            selected = Some(finding.index);
+                        }
+                    }
+                }
+            });
+
+        selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{self, with_rect, TreeBuilder};
+
+    fn element(control_type: &str, name: &str, automation_id: &str) -> UIElementProps {
+        let mut ui_elem_props = with_rect(test_tree::element(control_type, name), 10, 10, 110, 40);
+        ui_elem_props.automation_id = automation_id.to_string();
+        ui_elem_props
+    }
+
+    // a desktop with a window at (0, 0, 800, 600), `build` adds the content of the window
+    fn tree_with(build: impl FnOnce(&mut TreeBuilder, usize)) -> UITree {
+        let mut tree = TreeBuilder::new(with_rect(element("Pane", "Desktop 1", ""), 0, 0, 1920, 1080));
+        let window = tree.add(tree.desktop(), with_rect(element("Window", "Editor", ""), 0, 0, 800, 600));
+        build(&mut tree, window);
+        tree.build()
+    }
+
+    // the elements reported by a rule, in tree order
+    fn flagged(rule_id: &str, ui_tree: &UITree) -> Vec<usize> {
+        let mut indices: Vec<usize> = (find_rule(rule_id).unwrap().check)(ui_tree).into_iter().map(|(index, _)| index).collect();
+        indices.sort();
+        indices
+    }
+
+    #[test]
+    fn interactive_element_without_name() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            expected.push(tree.add(window, element("Button", "", "ok")));
+            expected.push(tree.add(window, element("Edit", "  ", "search")));
+            tree.add(window, element("Button", "OK", "ok"));
+            tree.add(window, element("Text", "", ""));
+            tree.add(window, element("Pane", "", ""));
+        });
+        assert_eq!(flagged("interactive-without-name", &ui_tree), expected);
+    }
+
+    #[test]
+    fn duplicate_sibling_names() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            expected.push(tree.add(window, element("Button", "OK", "")));
+            expected.push(tree.add(window, element("Button", "OK", "")));
+            // another control type, or no siblings of each other
+            tree.add(window, element("CheckBox", "OK", ""));
+            let group = tree.add(window, element("Group", "Options", ""));
+            tree.add(group, element("Button", "OK", ""));
+            tree.add(window, element("Text", "Label", ""));
+            tree.add(window, element("Text", "Label", ""));
+        });
+        assert_eq!(flagged("duplicate-sibling-name", &ui_tree), expected);
+    }
+
+    #[test]
+    fn windows_of_different_applications_are_no_siblings() {
+        let ui_tree = tree_with(|tree, window| {
+            // two instances of the same application
+            let mut window_props = tree.props(window).clone();
+            window_props.automation_id = "main".to_string();
+            tree.update(window, window_props.clone());
+            let desktop = tree.parent(window);
+            let other = tree.add(desktop, window_props);
+            tree.add(window, element("Button", "OK", "ok"));
+            tree.add(other, element("Button", "OK", "ok"));
+        });
+        assert!(flagged("duplicate-sibling-name", &ui_tree).is_empty());
+        assert!(flagged("duplicate-automation-id", &ui_tree).is_empty());
+    }
+
+    #[test]
+    fn duplicate_automation_ids() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            expected.push(tree.add(window, element("Button", "Save", "save")));
+            expected.push(tree.add(window, element("MenuItem", "Save", "save")));
+            tree.add(window, element("Button", "Open", "open"));
+            tree.add(window, element("Text", "", ""));
+            tree.add(window, element("Text", "", ""));
+        });
+        assert_eq!(flagged("duplicate-automation-id", &ui_tree), expected);
+    }
+
+    #[test]
+    fn interactive_element_without_automation_id() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            expected.push(tree.add(window, element("Button", "OK", "")));
+            tree.add(window, element("Button", "Cancel", "cancel"));
+            tree.add(window, element("Text", "Label", ""));
+        });
+        assert_eq!(flagged("missing-automation-id", &ui_tree), expected);
+        assert_eq!(find_rule("missing-automation-id").unwrap().severity, Severity::Info);
+    }
+
+    #[test]
+    fn focusable_element_with_empty_bounds() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            let mut focusable = with_rect(element("Button", "OK", "ok"), 10, 10, 10, 40);
+            focusable.is_keyboard_focusable = true;
+            expected.push(tree.add(window, focusable.clone()));
+            expected.push(tree.add(window, with_rect(focusable.clone(), 10, 10, 110, 5)));
+            // reported as offscreen instead
+            focusable.is_offscreen = true;
+            tree.add(window, focusable);
+            tree.add(window, with_rect(element("Button", "Hidden", "hidden"), 0, 0, 0, 0));
+            let mut visible = element("Button", "Visible", "visible");
+            visible.is_keyboard_focusable = true;
+            tree.add(window, visible);
+        });
+        assert_eq!(flagged("focusable-empty-bounds", &ui_tree), expected);
+    }
+
+    #[test]
+    fn focusable_element_offscreen() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            let mut offscreen = element("Button", "OK", "ok");
+            offscreen.is_keyboard_focusable = true;
+            offscreen.is_offscreen = true;
+            expected.push(tree.add(window, offscreen.clone()));
+            offscreen.is_enabled = false;
+            tree.add(window, offscreen.clone());
+            offscreen.is_enabled = true;
+            offscreen.is_keyboard_focusable = false;
+            tree.add(window, offscreen);
+        });
+        assert_eq!(flagged("focusable-offscreen", &ui_tree), expected);
+    }
+
+    #[test]
+    fn image_without_name_or_help_text() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            expected.push(tree.add(window, element("Image", " ", "")));
+            tree.add(window, element("Image", "Logo", ""));
+            let mut described = element("Image", "", "");
+            described.help_text = "Company logo".to_string();
+            tree.add(window, described);
+            tree.add(window, element("Text", "", ""));
+        });
+        assert_eq!(flagged("image-without-description", &ui_tree), expected);
+    }
+
+    #[test]
+    fn child_outside_its_parent() {
+        let mut expected: Vec<usize> = Vec::new();
+        let ui_tree = tree_with(|tree, window| {
+            expected.push(tree.add(window, with_rect(element("Button", "OK", "ok"), 700, 500, 900, 700)));
+            tree.add(window, with_rect(element("Button", "Cancel", "cancel"), 0, 0, 800, 600));
+            // scrolled out or without bounds
+            let mut scrolled_out = with_rect(element("ListItem", "Item", "item"), 0, 900, 800, 930);
+            scrolled_out.is_offscreen = true;
+            tree.add(window, scrolled_out);
+            tree.add(window, with_rect(element("Text", "Empty", ""), 900, 900, 900, 900));
+            // nested containers are checked against their own bounds
+            let group = tree.add(window, with_rect(element("Group", "Options", ""), 100, 100, 300, 300));
+            expected.push(tree.add(group, with_rect(element("CheckBox", "Bold", "bold"), 250, 250, 350, 280)));
+            tree.add(group, with_rect(element("CheckBox", "Italic", "italic"), 110, 110, 200, 140));
+            // children of an offscreen parent are not compared
+            let mut hidden_group = with_rect(element("Group", "Hidden", ""), 100, 100, 300, 300);
+            hidden_group.is_offscreen = true;
+            let hidden_group = tree.add(window, hidden_group);
+            tree.add(hidden_group, with_rect(element("Button", "Far", "far"), 1000, 1000, 1100, 1030));
+        });
+        assert_eq!(flagged("child-outside-parent", &ui_tree), expected);
+    }
+
+    #[test]
+    fn desktop_and_windows_outside_the_screen_are_not_audited() {
+        let ui_tree = tree_with(|tree, window| {
+            // top level windows are not compared with the desktop
+            let desktop = tree.parent(window);
+            tree.add(desktop, with_rect(element("Window", "", ""), -32000, -32000, -31840, -31972));
+        });
+        assert!(run_audit(&ui_tree, RULES.iter()).is_empty());
+    }
+
+    #[test]
+    fn findings_are_ordered_by_severity_and_position() {
+        let ui_tree = tree_with(|tree, window| {
+            tree.add(window, element("Button", "OK", ""));
+            tree.add(window, element("Button", "", "unnamed"));
+            tree.add(window, element("Button", "OK", "ok"));
+        });
+        let findings = run_audit(&ui_tree, RULES.iter());
+        let order: Vec<(&str, Severity)> = findings.iter().map(|finding| (finding.rule_id, finding.severity)).collect();
+        assert_eq!(order, [
+            ("interactive-without-name", Severity::Error),
+            ("duplicate-sibling-name", Severity::Warning),
+            ("duplicate-sibling-name", Severity::Warning),
+            ("missing-automation-id", Severity::Info),
+        ]);
+        assert!(findings.windows(2).all(|pair| (pair[0].severity, pair[0].index) <= (pair[1].severity, pair[1].index)));
+    }
+}
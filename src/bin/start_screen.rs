@@ -1,8 +1,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+use std::sync::mpsc::Receiver;
+
 use eframe::egui;
 
-use uiexplore::signal_file;
+use uiexplore::splash::{self, SplashMessage};
 
 fn main() -> eframe::Result {
     // env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
@@ -17,42 +19,82 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "UI Explore",
         options,
-        Box::new(|_cc| {
-            Ok(Box::new(MyApp::new()))
+        Box::new(|cc| {
+            Ok(Box::new(MyApp::new(&cc.egui_ctx)))
         }),
     )
 }
 
-struct MyApp {}
+struct MyApp {
+    rx: Receiver<SplashMessage>,
+    progress: Option<SplashMessage>,
+    error: Option<String>,
+    cancelling: bool,
+}
 
 impl MyApp {
-    fn new() -> Self {  
-        Self {}
+    fn new(ctx: &egui::Context) -> Self {
+        // repaint as soon as the parent process sent a message
+        let ctx = ctx.clone();
+        let rx = splash::listen(move || ctx.request_repaint());
+        Self { rx, progress: None, error: None, cancelling: false }
     }
 }
 
 impl eframe::App for MyApp {
-    
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
 
         ctx.send_viewport_cmd(egui::viewport::ViewportCommand::center_on_screen(ctx).unwrap());
 
+        // process the messages of the parent process
+        let mut close = false;
+        for msg in self.rx.try_iter() {
+            match msg {
+                SplashMessage::Progress { .. } => self.progress = Some(msg),
+                SplashMessage::Error(error) => self.error = Some(error),
+                SplashMessage::Close => close = true,
+                // the parent process is gone, keep an error visible until the user closes it
+                SplashMessage::Disconnected => close |= self.error.is_none(),
+                // only sent to the parent process
+                SplashMessage::Cancel => (),
+            }
+        }
+
         let layout = egui::Layout::top_down(egui::Align::Center);
 
-        egui::CentralPanel::default().show(ctx, |ui| {            
-            ui.with_layout(layout, |ui| {                
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.with_layout(layout, |ui| {
                 ui.add_space(70.0);
                 ui.heading("UI Explore");
                 ui.add_space(20.0);
-                ui.label("Please wait while we prepare the data...");
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, "Failed to capture the ui tree:");
+                    ui.label(error);
+                    ui.add_space(10.0);
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                } else {
+                    ui.label("Please wait while we prepare the data...");
+                    if let Some(SplashMessage::Progress { nodes_visited, windows_done, windows_total, current_window }) = &self.progress {
+                        ui.add_space(10.0);
+                        ui.label(format!("{} elements, {} of {} windows", nodes_visited, windows_done, windows_total));
+                        ui.label(egui::RichText::new(current_window).weak());
+                    }
+                    ui.add_space(10.0);
+                    // the parent process closes the start screen once the capture stopped
+                    if ui.add_enabled(!self.cancelling, egui::Button::new(if self.cancelling { "Cancelling..." } else { "Cancel" })).clicked() {
+                        splash::request_cancel();
+                        self.cancelling = true;
+                    }
+                }
             });
         });
 
-        
-        // check if parent process is done and if yes, close the app
-        if signal_file::termination_signal() {
+        if close {
             ctx.send_viewport_cmd(egui::viewport::ViewportCommand::Close);
         }
 
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,237 @@
+//! Flat CSV / TSV export of the elements, one row per element, e.g. for spreadsheets
+#![allow(dead_code)]
+
+use crate::{UIHashSet, UITree};
+use crate::audit;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvColumn {
+    TreeIndex,
+    ParentIndex,
+    Level,
+    ZOrder,
+    Path,
+    Name,
+    ControlType,
+    LocalizedControlType,
+    ClassName,
+    FrameworkId,
+    AutomationId,
+    HelpText,
+    RuntimeId,
+    ProcessId,
+    NativeWindowHandle,
+    Left,
+    Top,
+    Width,
+    Height,
+    IsKeyboardFocusable,
+    IsOffscreen,
+    IsEnabled,
+    Errors,
+}
+
+impl CsvColumn {
+    pub const ALL: [CsvColumn; 23] = [
+        CsvColumn::TreeIndex,
+        CsvColumn::ParentIndex,
+        CsvColumn::Level,
+        CsvColumn::ZOrder,
+        CsvColumn::Path,
+        CsvColumn::Name,
+        CsvColumn::ControlType,
+        CsvColumn::LocalizedControlType,
+        CsvColumn::ClassName,
+        CsvColumn::FrameworkId,
+        CsvColumn::AutomationId,
+        CsvColumn::HelpText,
+        CsvColumn::RuntimeId,
+        CsvColumn::ProcessId,
+        CsvColumn::NativeWindowHandle,
+        CsvColumn::Left,
+        CsvColumn::Top,
+        CsvColumn::Width,
+        CsvColumn::Height,
+        CsvColumn::IsKeyboardFocusable,
+        CsvColumn::IsOffscreen,
+        CsvColumn::IsEnabled,
+        CsvColumn::Errors,
+    ];
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            CsvColumn::TreeIndex => "Tree Index",
+            CsvColumn::ParentIndex => "Parent Index",
+            CsvColumn::Level => "Level",
+            CsvColumn::ZOrder => "Z-Order",
+            CsvColumn::Path => "Path",
+            CsvColumn::Name => "Name",
+            CsvColumn::ControlType => "Control Type",
+            CsvColumn::LocalizedControlType => "Localized Control Type",
+            CsvColumn::ClassName => "Class Name",
+            CsvColumn::FrameworkId => "Framework ID",
+            CsvColumn::AutomationId => "Automation ID",
+            CsvColumn::HelpText => "Help Text",
+            CsvColumn::RuntimeId => "Runtime ID",
+            CsvColumn::ProcessId => "Process ID",
+            CsvColumn::NativeWindowHandle => "Native Window Handle",
+            CsvColumn::Left => "Left",
+            CsvColumn::Top => "Top",
+            CsvColumn::Width => "Width",
+            CsvColumn::Height => "Height",
+            CsvColumn::IsKeyboardFocusable => "Is Keyboard Focusable",
+            CsvColumn::IsOffscreen => "Is Offscreen",
+            CsvColumn::IsEnabled => "Is Enabled",
+            CsvColumn::Errors => "Errors",
+        }
+    }
+
+    fn value(&self, ui_tree: &UITree, index: usize) -> String {
+        let node = ui_tree.get_tree().node(index);
+        let ui_elem_props = &node.data;
+        let rect = &ui_elem_props.bounding_rect;
+        match self {
+            CsvColumn::TreeIndex => index.to_string(),
+            // the root node is its own parent
+            CsvColumn::ParentIndex => if index == ui_tree.root() { String::new() } else { node.parent.to_string() },
+            CsvColumn::Level => ui_elem_props.level.to_string(),
+            CsvColumn::ZOrder => ui_elem_props.z_order.to_string(),
+            CsvColumn::Path => audit::element_path(ui_tree, index).join(" > "),
+            CsvColumn::Name => ui_elem_props.name.clone(),
+            CsvColumn::ControlType => ui_elem_props.control_type.clone(),
+            CsvColumn::LocalizedControlType => ui_elem_props.localized_control_type.clone(),
+            CsvColumn::ClassName => ui_elem_props.classname.clone(),
+            CsvColumn::FrameworkId => ui_elem_props.framework_id.clone(),
+            CsvColumn::AutomationId => ui_elem_props.automation_id.clone(),
+            CsvColumn::HelpText => ui_elem_props.help_text.clone(),
+            CsvColumn::RuntimeId => ui_elem_props.runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-"),
+            CsvColumn::ProcessId => ui_elem_props.process_id.to_string(),
+            CsvColumn::NativeWindowHandle => format!("{:#x}", ui_elem_props.handle),
+            CsvColumn::Left => rect.get_left().to_string(),
+            CsvColumn::Top => rect.get_top().to_string(),
+            CsvColumn::Width => (rect.get_right() - rect.get_left()).to_string(),
+            CsvColumn::Height => (rect.get_bottom() - rect.get_top()).to_string(),
+            CsvColumn::IsKeyboardFocusable => ui_elem_props.is_keyboard_focusable.to_string(),
+            CsvColumn::IsOffscreen => ui_elem_props.is_offscreen.to_string(),
+            CsvColumn::IsEnabled => ui_elem_props.is_enabled.to_string(),
+            CsvColumn::Errors => ui_elem_props.errors.join("; "),
+        }
+    }
+}
+
+/// Formats the elements below (and including) the node at `start` in the order of
+/// UITree::get_elements, with the given columns separated by `delimiter` (e.g. ',' or '\t')
+pub fn to_csv(ui_tree: &UITree, start: usize, columns: &[CsvColumn], delimiter: char) -> String {
+    let mut subtree: UIHashSet<usize> = UIHashSet::new();
+    let mut stack: Vec<usize> = vec![start];
+    while let Some(index) = stack.pop() {
+        subtree.insert(index);
+        stack.extend_from_slice(ui_tree.children(index));
+    }
+
+    let mut csv = String::new();
+    let header: Vec<String> = columns.iter().map(|column| quote(column.header(), delimiter)).collect();
+    csv.push_str(&header.join(&delimiter.to_string()));
+    csv.push_str("\r\n");
+
+    for element in ui_tree.get_elements() {
+        let index = element.get_tree_index();
+        if !subtree.contains(&index) {
+            continue;
+        }
+        let row: Vec<String> = columns.iter().map(|column| quote(&column.value(ui_tree, index), delimiter)).collect();
+        csv.push_str(&row.join(&delimiter.to_string()));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Quotes a field as in RFC 4180 if it contains the delimiter, quotes or line breaks. Text
+/// which a spreadsheet would evaluate as a formula gets a leading apostrophe.
+pub fn quote(field: &str, delimiter: char) -> String {
+    let field = if is_formula(field) { format!("'{}", field) } else { field.to_string() };
+    if field.contains(delimiter) || field.contains(['"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+// names and help texts are set by the inspected application, e.g. "=HYPERLINK(...)" would become 
+// a live link in the spreadsheet. Negative numbers like the coordinates of off screen windows are kept.
+fn is_formula(field: &str) -> bool {
+    field.starts_with(['=', '+', '-', '@', '\t', '\r']) && field.parse::<f64>().is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{element, with_rect, TreeBuilder};
+
+    // desktop > [window > [button], other window], returns the tree and the first window
+    fn sample_tree() -> (UITree, usize) {
+        let mut tree = TreeBuilder::new(with_rect(element("Pane", "Desktop"), 0, 0, 10, 10));
+        let window = tree.add(tree.desktop(), with_rect(element("Window", "Save as, \"Report\""), -8, 0, 2, 10));
+        tree.add(window, with_rect(element("Button", "=HYPERLINK(\"http://example.com\")"), 20, 0, 30, 10));
+        tree.add(tree.desktop(), with_rect(element("Window", "Other"), 100, 0, 110, 10));
+        (tree.build(), window)
+    }
+
+    #[test]
+    fn plain_fields_are_not_quoted() {
+        assert_eq!(quote("Calculator", ','), "Calculator");
+        assert_eq!(quote("", ','), "");
+        assert_eq!(quote("42", ','), "42");
+    }
+
+    #[test]
+    fn fields_with_delimiter_quotes_or_line_breaks_are_quoted() {
+        assert_eq!(quote("Save as, Report", ','), "\"Save as, Report\"");
+        assert_eq!(quote("the \"Report\"", ','), "\"the \"\"Report\"\"\"");
+        assert_eq!(quote("line\r\nbreak", ','), "\"line\r\nbreak\"");
+        assert_eq!(quote("line\nbreak", ','), "\"line\nbreak\"");
+    }
+
+    #[test]
+    fn only_the_delimiter_in_use_is_quoted() {
+        assert_eq!(quote("tab\tseparated", '\t'), "\"tab\tseparated\"");
+        assert_eq!(quote("tab\tseparated", ','), "tab\tseparated");
+        assert_eq!(quote("Save as, Report", '\t'), "Save as, Report");
+    }
+
+    #[test]
+    fn formulas_are_neutralised() {
+        assert_eq!(quote("=1+2", ','), "'=1+2");
+        assert_eq!(quote("+1 555 0100", ','), "'+1 555 0100");
+        assert_eq!(quote("-2+3", ','), "'-2+3");
+        assert_eq!(quote("@SUM(A1)", ','), "'@SUM(A1)");
+        assert_eq!(quote("=SUM(A1,A2)", ','), "\"'=SUM(A1,A2)\"");
+        // not at the start, or a plain number
+        assert_eq!(quote("1=1", ','), "1=1");
+        assert_eq!(quote("-8", ','), "-8");
+        assert_eq!(quote("-0.5", ','), "-0.5");
+    }
+
+    #[test]
+    fn csv_has_a_header_and_a_row_per_element_of_the_subtree() {
+        let (ui_tree, window) = sample_tree();
+        let csv = to_csv(&ui_tree, window, &[CsvColumn::Name, CsvColumn::ControlType, CsvColumn::Left], ',');
+        let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], "Name,Control Type,Left");
+        assert!(rows.contains(&"\"Save as, \"\"Report\"\"\",Window,-8"));
+        assert!(rows.contains(&"\"'=HYPERLINK(\"\"http://example.com\"\")\",Button,20"));
+        assert!(!csv.contains("Other"));
+    }
+
+    #[test]
+    fn tsv_quotes_only_fields_with_tabs_quotes_or_line_breaks() {
+        let (ui_tree, _) = sample_tree();
+        let tsv = to_csv(&ui_tree, ui_tree.desktop(), &[CsvColumn::Name, CsvColumn::Width], '\t');
+        let rows: Vec<&str> = tsv.split_terminator("\r\n").collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0], "Name\tWidth");
+        assert!(rows.contains(&"Other\t10"));
+        assert!(rows.contains(&"\"Save as, \"\"Report\"\"\"\t10"));
+    }
+}
@@ -0,0 +1,346 @@
+//! Graphviz DOT and Mermaid flowchart export of the element hierarchy below a node,
+//! e.g. to show the structure of a window in a design review
+#![allow(dead_code)]
+
+use crate::UITreeMap;
+use crate::uiexplore::UIElementProps;
+
+// fill colors of common control types, others get a color derived from their name
+const CONTROL_TYPE_COLORS: [(&str, &str); 14] = [
+    ("Window", "#dbe9f6"),
+    ("Pane", "#eeeeee"),
+    ("Group", "#f3eddc"),
+    ("Button", "#cde8ff"),
+    ("SplitButton", "#cde8ff"),
+    ("Edit", "#d9f2d9"),
+    ("Text", "#ffffff"),
+    ("List", "#fbe5c8"),
+    ("ListItem", "#fdf1e0"),
+    ("Tree", "#e6dcf5"),
+    ("TreeItem", "#f1ebfa"),
+    ("Menu", "#f9d9d9"),
+    ("MenuItem", "#fcebeb"),
+    ("CheckBox", "#d5f0ee"),
+];
+const FALLBACK_COLORS: [&str; 6] = ["#fff5cc", "#e0f7fa", "#fce4ec", "#e8f5e9", "#ede7f6", "#f1f8e9"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramOptions {
+    /// levels below the start node, None for all
+    pub max_depth: Option<usize>,
+    /// placeholders: {name} {control_type} {localized_control_type} {classname} {automation_id}
+    /// {framework_id} {runtime_id} {index}, \n starts a new line
+    pub label_template: String,
+    pub color_by_control_type: bool,
+    /// children beyond this number are collapsed into a summary node
+    pub max_children: usize,
+}
+
+impl Default for DiagramOptions {
+    fn default() -> Self {
+        DiagramOptions {
+            max_depth: Some(4),
+            label_template: "{control_type}\\n{name}".to_string(),
+            color_by_control_type: true,
+            max_children: 15,
+        }
+    }
+}
+
+/// A node of the diagram, either an element or a summary of elements left out
+enum DiagramNode {
+    Element { id: String, lines: Vec<String>, control_type: String },
+    Summary { id: String, lines: Vec<String> },
+}
+
+struct Diagram {
+    nodes: Vec<DiagramNode>,
+    edges: Vec<(String, String)>,
+}
+
+// collects the nodes and edges, which are then written in the syntax of the target format
+fn build_diagram(tree: &UITreeMap<UIElementProps>, start: usize, options: &DiagramOptions) -> Diagram {
+    let mut diagram = Diagram { nodes: Vec::new(), edges: Vec::new() };
+    add_node(tree, start, 0, options, &mut diagram);
+    diagram
+}
+
+fn add_node(tree: &UITreeMap<UIElementProps>, index: usize, depth: usize, options: &DiagramOptions, diagram: &mut Diagram) -> String {
+    let ui_elem_props = &tree.node(index).data;
+    let id = format!("n{}", index);
+    diagram.nodes.push(DiagramNode::Element {
+        id: id.clone(),
+        lines: format_label(&options.label_template, index, ui_elem_props),
+        control_type: ui_elem_props.control_type.clone(),
+    });
+
+    let children = tree.children(index);
+    if children.is_empty() {
+        return id;
+    }
+
+    // below the max depth only the number of descendants is shown
+    if options.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+        let summary_id = format!("{}_descendants", id);
+        let count = count_descendants(tree, index);
+        diagram.nodes.push(DiagramNode::Summary { id: summary_id.clone(), lines: vec![format!("… {} descendants", count)] });
+        diagram.edges.push((id.clone(), summary_id));
+        return id;
+    }
+
+    let shown = children.len().min(options.max_children.max(1));
+    for &child in &children[..shown] {
+        let child_id = add_node(tree, child, depth + 1, options, diagram);
+        diagram.edges.push((id.clone(), child_id));
+    }
+
+    // the remaining children are summarized by control type
+    if children.len() > shown {
+        let collapsed = &children[shown..];
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for &child in collapsed {
+            let control_type = &tree.node(child).data.control_type;
+            match counts.iter_mut().find(|(known, _)| known == control_type) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((control_type.clone(), 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        let summary_id = format!("{}_more", id);
+        let mut lines = vec![format!("+ {} more", collapsed.len())];
+        lines.push(counts.iter().map(|(control_type, count)| format!("{} {}", count, control_type)).collect::<Vec<String>>().join(", "));
+        diagram.nodes.push(DiagramNode::Summary { id: summary_id.clone(), lines });
+        diagram.edges.push((id.clone(), summary_id));
+    }
+    id
+}
+
+fn count_descendants(tree: &UITreeMap<UIElementProps>, index: usize) -> usize {
+    tree.children(index).iter().map(|&child| 1 + count_descendants(tree, child)).sum()
+}
+
+/// Fills the placeholders of the template, the label is returned line by line
+pub fn format_label(template: &str, index: usize, ui_elem_props: &UIElementProps) -> Vec<String> {
+    let runtime_id = ui_elem_props.runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-");
+    let label = template
+        .replace("{name}", &ui_elem_props.name)
+        .replace("{control_type}", &ui_elem_props.control_type)
+        .replace("{localized_control_type}", &ui_elem_props.localized_control_type)
+        .replace("{classname}", &ui_elem_props.classname)
+        .replace("{automation_id}", &ui_elem_props.automation_id)
+        .replace("{framework_id}", &ui_elem_props.framework_id)
+        .replace("{runtime_id}", &runtime_id)
+        .replace("{index}", &index.to_string());
+    label.split("\\n")
+        .flat_map(|line| line.lines())
+        .map(|line| line.to_string())
+        .filter(|line| !line.trim().is_empty())
+        .collect()
+}
+
+/// The fill color of a control type
+pub fn control_type_color(control_type: &str) -> &'static str {
+    if let Some((_, color)) = CONTROL_TYPE_COLORS.iter().find(|(known, _)| *known == control_type) {
+        return color;
+    }
+    let hash = control_type.bytes().fold(0usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as usize));
+    FALLBACK_COLORS[hash % FALLBACK_COLORS.len()]
+}
+
+/// Formats the hierarchy below `start` as a Graphviz digraph
+pub fn to_dot(tree: &UITreeMap<UIElementProps>, start: usize, options: &DiagramOptions) -> String {
+    let diagram = build_diagram(tree, start, options);
+    let mut dot = String::from("digraph uitree {\n");
+    dot.push_str("  node [shape=box, style=\"rounded,filled\", fillcolor=\"#ffffff\", fontname=\"Segoe UI\", fontsize=10];\n");
+    dot.push_str("  edge [arrowsize=0.6];\n\n");
+
+    for node in &diagram.nodes {
+        match node {
+            DiagramNode::Element { id, lines, control_type } => {
+                let fill = if options.color_by_control_type { format!(", fillcolor=\"{}\"", control_type_color(control_type)) } else { String::new() };
+                dot.push_str(&format!("  {} [label=\"{}\"{}];\n", id, dot_label(lines), fill));
+            },
+            DiagramNode::Summary { id, lines } => {
+                dot.push_str(&format!("  {} [label=\"{}\", shape=note, style=dashed];\n", id, dot_label(lines)));
+            },
+        }
+    }
+    dot.push('\n');
+    for (from, to) in &diagram.edges {
+        dot.push_str(&format!("  {} -> {};\n", from, to));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn dot_label(lines: &[String]) -> String {
+    lines.iter()
+        .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+        .collect::<Vec<String>>()
+        .join("\\n")
+}
+
+/// Formats the hierarchy below `start` as a Mermaid flowchart
+pub fn to_mermaid(tree: &UITreeMap<UIElementProps>, start: usize, options: &DiagramOptions) -> String {
+    let diagram = build_diagram(tree, start, options);
+    let mut mermaid = String::from("flowchart TD\n");
+    let mut classes: Vec<(String, Vec<String>)> = Vec::new();
+
+    for node in &diagram.nodes {
+        match node {
+            DiagramNode::Element { id, lines, control_type } => {
+                mermaid.push_str(&format!("  {}[\"{}\"]\n", id, mermaid_label(lines)));
+                if options.color_by_control_type {
+                    let class = mermaid_class(control_type);
+                    match classes.iter_mut().find(|(known, _)| *known == class) {
+                        Some((_, ids)) => ids.push(id.clone()),
+                        None => classes.push((class, vec![id.clone()])),
+                    }
+                }
+            },
+            DiagramNode::Summary { id, lines } => {
+                mermaid.push_str(&format!("  {}[[\"{}\"]]\n", id, mermaid_label(lines)));
+            },
+        }
+    }
+    for (from, to) in &diagram.edges {
+        mermaid.push_str(&format!("  {} --> {}\n", from, to));
+    }
+
+    // one class per control type, assigned to all its nodes
+    for (class, ids) in &classes {
+        let control_type = class.trim_start_matches("ct_");
+        mermaid.push_str(&format!("  classDef {} fill:{},stroke:#555\n", class, control_type_color(control_type)));
+        mermaid.push_str(&format!("  class {} {}\n", ids.join(","), class));
+    }
+    mermaid
+}
+
+fn mermaid_label(lines: &[String]) -> String {
+    lines.iter()
+        .map(|line| line.replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;"))
+        .collect::<Vec<String>>()
+        .join("<br/>")
+}
+
+// class names may only contain letters, digits, - and _
+fn mermaid_class(control_type: &str) -> String {
+    let name: String = control_type.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    if name.is_empty() { "ct_Unknown".to_string() } else { format!("ct_{}", name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{element, TreeBuilder};
+
+    fn options(max_depth: Option<usize>, max_children: usize) -> DiagramOptions {
+        DiagramOptions { max_depth, label_template: "{name}".to_string(), color_by_control_type: false, max_children }
+    }
+
+    #[test]
+    fn label_placeholders_are_filled() {
+        let mut button = element("Button", "OK");
+        button.localized_control_type = "Schaltfläche".to_string();
+        button.classname = "Button".to_string();
+        button.automation_id = "okButton".to_string();
+        button.framework_id = "Win32".to_string();
+        button.runtime_id = vec![42, 1234];
+        let template = "{control_type}: {name}\\n#{automation_id} {classname} {framework_id}\\n{runtime_id} @{index} {localized_control_type}";
+        assert_eq!(format_label(template, 7, &button), ["Button: OK", "#okButton Button Win32", "42-1234 @7 Schaltfläche"]);
+    }
+
+    #[test]
+    fn label_lines_are_split_and_empty_lines_dropped() {
+        let button = element("Button", "first line\r\nsecond line");
+        assert_eq!(format_label("{name}\\n{automation_id}\\n{control_type}", 0, &button), ["first line", "second line", "Button"]);
+        assert_eq!(format_label("{automation_id}", 0, &button), Vec::<String>::new());
+        // unknown placeholders are kept
+        assert_eq!(format_label("{value}", 0, &button), ["{value}"]);
+    }
+
+    #[test]
+    fn dot_labels_are_quoted() {
+        let lines = vec!["Say \"hi\"".to_string(), "C:\\temp".to_string()];
+        assert_eq!(dot_label(&lines), "Say \\\"hi\\\"\\nC:\\\\temp");
+    }
+
+    #[test]
+    fn mermaid_labels_are_quoted() {
+        let lines = vec!["<b>\"bold\"</b>".to_string(), "second".to_string()];
+        assert_eq!(mermaid_label(&lines), "#lt;b#gt;#quot;bold#quot;#lt;/b#gt;<br/>second");
+        assert_eq!(mermaid_class("Split Button"), "ct_SplitButton");
+        assert_eq!(mermaid_class("…"), "ct_Unknown");
+    }
+
+    #[test]
+    fn dot_contains_the_nodes_and_edges() {
+        let mut tree = TreeBuilder::new(element("Pane", "Desktop"));
+        let window = tree.add(tree.desktop(), element("Window", "Say \"hi\""));
+        let button = tree.add(window, element("Button", "OK"));
+        let ui_tree = tree.build();
+
+        let dot = to_dot(ui_tree.get_tree(), window, &options(None, 15));
+        assert!(dot.starts_with("digraph uitree {\n"));
+        assert!(dot.contains(&format!("  n{} [label=\"Say \\\"hi\\\"\"];\n", window)));
+        assert!(dot.contains(&format!("  n{} [label=\"OK\"];\n", button)));
+        assert!(dot.contains(&format!("  n{} -> n{};\n", window, button)));
+        assert!(dot.ends_with("}\n"));
+
+        let colored = to_dot(ui_tree.get_tree(), window, &DiagramOptions { color_by_control_type: true, ..options(None, 15) });
+        assert!(colored.contains(&format!("  n{} [label=\"OK\", fillcolor=\"#cde8ff\"];\n", button)));
+    }
+
+    #[test]
+    fn levels_below_the_max_depth_are_summarized() {
+        let mut tree = TreeBuilder::new(element("Pane", "Desktop"));
+        let window = tree.add(tree.desktop(), element("Window", "Window"));
+        let group = tree.add(window, element("Group", "Group"));
+        tree.add(group, element("Button", "Leaf"));
+        let nested = tree.add(group, element("Group", "Nested"));
+        tree.add(nested, element("Button", "Nested leaf"));
+        let ui_tree = tree.build();
+
+        let dot = to_dot(ui_tree.get_tree(), window, &options(Some(1), 15));
+        assert!(dot.contains(&format!("  n{}_descendants [label=\"… 3 descendants\", shape=note, style=dashed];\n", group)));
+        assert!(dot.contains(&format!("  n{} -> n{}_descendants;\n", group, group)));
+        assert!(!dot.contains("Leaf"));
+        assert!(!dot.contains("Nested"));
+
+        let all = to_dot(ui_tree.get_tree(), window, &options(None, 15));
+        assert!(all.contains("Nested leaf"));
+        assert!(!all.contains("descendants"));
+    }
+
+    #[test]
+    fn children_beyond_the_limit_are_summarized_by_control_type() {
+        let mut tree = TreeBuilder::new(element("Pane", "Desktop"));
+        let window = tree.add(tree.desktop(), element("Window", "Window"));
+        for (control_type, name) in [("Button", "One"), ("Edit", "Two"), ("Button", "Three"), ("Edit", "Four"), ("Button", "Five")] {
+            tree.add(window, element(control_type, name));
+        }
+        let ui_tree = tree.build();
+
+        let mermaid = to_mermaid(ui_tree.get_tree(), window, &options(None, 2));
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("[\"One\"]"));
+        assert!(mermaid.contains("[\"Two\"]"));
+        assert!(!mermaid.contains("Three"));
+        assert!(mermaid.contains(&format!("  n{}_more[[\"+ 3 more<br/>2 Button, 1 Edit\"]]\n", window)));
+        assert!(mermaid.contains(&format!("  n{} --> n{}_more\n", window, window)));
+    }
+
+    #[test]
+    fn mermaid_assigns_one_class_per_control_type() {
+        let mut tree = TreeBuilder::new(element("Pane", "Desktop"));
+        let window = tree.add(tree.desktop(), element("Window", "Window"));
+        let ok = tree.add(window, element("Button", "OK"));
+        let cancel = tree.add(window, element("Button", "Cancel"));
+        let ui_tree = tree.build();
+
+        let mermaid = to_mermaid(ui_tree.get_tree(), window, &DiagramOptions { color_by_control_type: true, ..options(None, 15) });
+        assert!(mermaid.contains("  classDef ct_Button fill:#cde8ff,stroke:#555\n"));
+        assert!(mermaid.contains(&format!("  class n{},n{} ct_Button\n", ok, cancel)));
+        assert!(mermaid.contains(&format!("  class n{} ct_Window\n", window)));
+    }
+}
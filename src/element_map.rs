@@ -0,0 +1,244 @@
+//! Scaled wireframe of the bounding rectangles below a window, drawn from the snapshot,
+//! so elements can be located by their position even after the window has moved or closed
+#![allow(dead_code)]
+
+use std::cmp::Reverse;
+
+use eframe::egui;
+use uiautomation::types::Rect;
+
+use crate::UITree;
+use crate::diagram_export::control_type_color;
+use crate::rectangle;
+use crate::screenshot::ScreenshotTexture;
+use crate::uiexplore;
+
+// space around the window
+const MAP_MARGIN: f32 = 8.0;
+
+/// What happened on the map in this frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ElementMapOutcome {
+    /// the element under the pointer
+    pub hovered: Option<usize>,
+    pub selected: Option<usize>,
+}
+
+pub struct ElementMapPanel {
+    show_screenshot: bool,
+    show_legend: bool,
+    texture: ScreenshotTexture,
+}
+
+impl ElementMapPanel {
+    pub fn new() -> Self {
+        ElementMapPanel { show_screenshot: true, show_legend: false, texture: ScreenshotTexture::new() }
+    }
+
+    /// Draws the elements below `window`, the active element is outlined with `active_color`
+    pub fn ui(&mut self, ui: &mut egui::Ui, ui_tree: &UITree, window: usize, active_ui_element: Option<usize>, active_color: [u8; 3]) -> ElementMapOutcome {
+        let mut outcome = ElementMapOutcome::default();
+
+        let area = ui_tree.node(window).1.bounding_rect;
+        let elements = map_elements(ui_tree, window);
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{} ({} elements)", uiexplore::format_element_label(ui_tree.node(window).1), elements.len()));
+            ui.add_enabled(ui_tree.get_screenshot().is_some(), egui::Checkbox::new(&mut self.show_screenshot, "Screenshot"))
+                .on_disabled_hover_text("No screenshot was taken with this snapshot");
+            ui.checkbox(&mut self.show_legend, "Legend");
+        });
+
+        if self.show_legend {
+            let mut control_types: Vec<&str> = elements.iter().map(|&index| ui_tree.node(index).1.control_type.as_str()).collect();
+            control_types.sort();
+            control_types.dedup();
+            ui.horizontal_wrapped(|ui| {
+                for control_type in control_types {
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2.0, hex_color(control_type_color(control_type)));
+                    ui.label(control_type);
+                }
+            });
+        }
+        ui.separator();
+
+        let width = (area.get_right() - area.get_left()) as f32;
+        let height = (area.get_bottom() - area.get_top()) as f32;
+        if width <= 0.0 || height <= 0.0 {
+            ui.label("The window has no size, e.g. because it is minimized");
+            return outcome;
+        }
+
+        // scaled to fit the available space, keeping the aspect ratio
+        let available = ui.available_size() - egui::vec2(2.0 * MAP_MARGIN, 2.0 * MAP_MARGIN);
+        let scale = (available.x / width).min(available.y / height).clamp(0.01, 1.0);
+        let (response, painter) = ui.allocate_painter(egui::vec2(width * scale, height * scale) + egui::vec2(2.0 * MAP_MARGIN, 2.0 * MAP_MARGIN), egui::Sense::click());
+        let view = response.rect.shrink(MAP_MARGIN);
+        let painter = painter.with_clip_rect(response.rect);
+
+        if self.show_screenshot {
+            if let Some(screenshot) = ui_tree.get_screenshot() {
+                let texture = self.texture.get(ui.ctx(), screenshot);
+                let full = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                painter.image(texture.id(), map_rect(&screenshot.screen_rect(), &area, view), full, egui::Color32::from_white_alpha(160));
+            }
+        }
+
+        // the element under the pointer is the smallest one containing it
+        outcome.hovered = response.hover_pos()
+            .map(|pos| screen_point(pos, &area, view))
+            .and_then(|(x, y)| element_at(ui_tree, &elements, x, y));
+
+        // parents first, so children are drawn on top of them
+        for &index in &elements {
+            let ui_elem_props = ui_tree.node(index).1;
+            let rect = map_rect(&ui_elem_props.bounding_rect, &area, view);
+            let color = hex_color(control_type_color(&ui_elem_props.control_type));
+            painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, darken(color)));
+        }
+
+        if let Some(index) = outcome.hovered {
+            let rect = map_rect(&ui_tree.node(index).1.bounding_rect, &area, view);
+            let fill = ui.visuals().selection.bg_fill.gamma_multiply(0.4);
+            painter.rect(rect, 0.0, fill, ui.visuals().selection.stroke);
+        }
+        if let Some(index) = active_ui_element {
+            if elements.contains(&index) {
+                let rect = map_rect(&ui_tree.node(index).1.bounding_rect, &area, view);
+                painter.rect_stroke(rect, 0.0, egui::Stroke::new(2.0, egui::Color32::from_rgb(active_color[0], active_color[1], active_color[2])));
+            }
+        }
+
+        if let Some(index) = outcome.hovered {
+            let response = response.on_hover_text(uiexplore::format_element_label(ui_tree.node(index).1));
+            if response.clicked() {
+                outcome.selected = Some(index);
+            }
+        }
+
+        outcome
+    }
+}
+
+/// The element at `index` and all its descendants with a bounding rectangle, parents before children
+fn map_elements(ui_tree: &UITree, index: usize) -> Vec<usize> {
+    let mut elements: Vec<usize> = Vec::new();
+    let mut stack: Vec<usize> = vec![index];
+    while let Some(index) = stack.pop() {
+        let rect = &ui_tree.node(index).1.bounding_rect;
+        if rect.get_right() > rect.get_left() && rect.get_bottom() > rect.get_top() {
+            elements.push(index);
+        }
+        stack.extend(ui_tree.children(index).iter().rev());
+    }
+    elements
+}
+
+fn element_at(ui_tree: &UITree, elements: &[usize], x: i32, y: i32) -> Option<usize> {
+    elements.iter().copied()
+        .filter(|&index| rectangle::is_inside_rectancle(&ui_tree.node(index).1.bounding_rect, x, y))
+        // of equally sized elements the last, i.e. the deepest one
+        .max_by_key(|&index| {
+            let rect = &ui_tree.node(index).1.bounding_rect;
+            Reverse((rect.get_right() - rect.get_left()) as i64 * (rect.get_bottom() - rect.get_top()) as i64)
+        })
+}
+
+/// Maps a screen rectangle into `view`, which shows the screen area `area`
+pub fn map_rect(rect: &Rect, area: &Rect, view: egui::Rect) -> egui::Rect {
+    let scale_x = view.width() / (area.get_right() - area.get_left()) as f32;
+    let scale_y = view.height() / (area.get_bottom() - area.get_top()) as f32;
+    egui::Rect::from_min_max(
+        egui::pos2(view.min.x + (rect.get_left() - area.get_left()) as f32 * scale_x, view.min.y + (rect.get_top() - area.get_top()) as f32 * scale_y),
+        egui::pos2(view.min.x + (rect.get_right() - area.get_left()) as f32 * scale_x, view.min.y + (rect.get_bottom() - area.get_top()) as f32 * scale_y),
+    )
+}
+
+/// The screen position of a point of `view`, the inverse of map_rect
+pub fn screen_point(pos: egui::Pos2, area: &Rect, view: egui::Rect) -> (i32, i32) {
+    let x = area.get_left() as f32 + (pos.x - view.min.x) / view.width() * (area.get_right() - area.get_left()) as f32;
+    let y = area.get_top() as f32 + (pos.y - view.min.y) / view.height() * (area.get_bottom() - area.get_top()) as f32;
+    (x.floor() as i32, y.floor() as i32)
+}
+
+// "#rrggbb" as used in the diagrams
+fn hex_color(hex: &str) -> egui::Color32 {
+    let value = u32::from_str_radix(hex.trim_start_matches('#'), 16).unwrap_or(0x808080);
+    egui::Color32::from_rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)
+}
+
+// the diagram colors are pale fills, outlines need more contrast
+fn darken(color: egui::Color32) -> egui::Color32 {
+    egui::Color32::from_rgb(color.r() / 2, color.g() / 2, color.b() / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{element, with_rect, TreeBuilder};
+
+    // window > [group > [button, pane of the same size as the group], zero sized element > [label]]
+    fn sample_tree() -> (UITree, [usize; 6]) {
+        let mut tree = TreeBuilder::new(with_rect(element("Pane", "Desktop"), 0, 0, 1920, 1080));
+        let window = tree.add(tree.desktop(), with_rect(element("Window", "Window"), 0, 0, 800, 600));
+        let group = tree.add(window, with_rect(element("Group", "Group"), 100, 100, 300, 300));
+        let button = tree.add(group, with_rect(element("Button", "Button"), 110, 110, 200, 140));
+        let pane = tree.add(group, with_rect(element("Pane", "Pane"), 100, 100, 300, 300));
+        let zero_sized = tree.add(window, with_rect(element("Pane", "Zero sized"), 400, 400, 400, 400));
+        let label = tree.add(zero_sized, with_rect(element("Text", "Label"), 400, 400, 500, 420));
+        (tree.build(), [window, group, button, pane, zero_sized, label])
+    }
+
+    #[test]
+    fn map_rect_scales_the_area_into_the_view() {
+        let area = Rect::new(100, 200, 1100, 700);
+        let view = egui::Rect::from_min_max(egui::pos2(10.0, 20.0), egui::pos2(510.0, 270.0));
+        assert_eq!(map_rect(&area, &area, view), view);
+        assert_eq!(map_rect(&Rect::new(300, 400, 500, 500), &area, view), egui::Rect::from_min_max(egui::pos2(110.0, 120.0), egui::pos2(210.0, 170.0)));
+        // parts outside the area are mapped outside the view
+        assert_eq!(map_rect(&Rect::new(0, 100, 200, 300), &area, view), egui::Rect::from_min_max(egui::pos2(-40.0, -30.0), egui::pos2(60.0, 70.0)));
+    }
+
+    #[test]
+    fn screen_point_inverts_map_rect() {
+        let area = Rect::new(-8, -8, 1912, 1072);
+        for view in [
+            egui::Rect::from_min_max(egui::pos2(10.0, 20.0), egui::pos2(970.0, 560.0)),
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(3840.0, 2160.0)),
+            egui::Rect::from_min_max(egui::pos2(33.3, 7.7), egui::pos2(733.3, 411.1)),
+        ] {
+            for (left, top) in [(-8, -8), (0, 0), (117, 431), (1000, 999), (1911, 1071)] {
+                // the center of the mapped pixel belongs to the pixel, whatever the scale
+                let mapped = map_rect(&Rect::new(left, top, left + 1, top + 1), &area, view);
+                assert_eq!(screen_point(mapped.center(), &area, view), (left, top), "view {:?}", view);
+            }
+        }
+    }
+
+    #[test]
+    fn map_elements_skips_elements_without_bounds() {
+        let (ui_tree, [window, group, button, pane, _, label]) = sample_tree();
+        assert_eq!(map_elements(&ui_tree, window), vec![window, group, button, pane, label]);
+        assert_eq!(map_elements(&ui_tree, group), vec![group, button, pane]);
+    }
+
+    #[test]
+    fn element_at_picks_the_smallest_containing_element() {
+        let (ui_tree, [window, _, button, _, _, label]) = sample_tree();
+        let elements = map_elements(&ui_tree, window);
+        assert_eq!(element_at(&ui_tree, &elements, 150, 120), Some(button));
+        assert_eq!(element_at(&ui_tree, &elements, 450, 410), Some(label));
+        assert_eq!(element_at(&ui_tree, &elements, 700, 500), Some(window));
+        assert_eq!(element_at(&ui_tree, &elements, 900, 500), None);
+    }
+
+    #[test]
+    fn element_at_picks_the_deepest_of_equally_sized_elements() {
+        let (ui_tree, [window, _, _, pane, _, _]) = sample_tree();
+        let elements = map_elements(&ui_tree, window);
+        assert_eq!(element_at(&ui_tree, &elements, 250, 250), Some(pane));
+        // the edges belong to the element
+        assert_eq!(element_at(&ui_tree, &elements, 100, 300), Some(pane));
+    }
+}
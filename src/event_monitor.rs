@@ -0,0 +1,391 @@
+//! Monitors ui automation events of the target applications and lists them in a panel
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::thread;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use chrono::{DateTime, Local};
+use eframe::egui;
+
+use uiautomation::core::UIAutomation;
+use uiautomation::events::{CustomEventHandler, CustomFocusChangedEventHandler, CustomPropertyChangedEventHandler, CustomStructureChangedEventHandler};
+use uiautomation::events::{UIEventHandler, UIEventType, UIFocusChangedEventHandler, UIPropertyChangedEventHandler, UIStructureChangeEventHandler};
+use uiautomation::types::{Handle, StructureChangeType, TreeScope, UIProperty};
+use uiautomation::variants::Variant;
+use uiautomation::UIElement;
+
+use crate::uiexplore::{self, CaptureScope, UIElementProps};
+
+// number of events kept in the list
+const MAX_EVENTS: usize = 10000;
+
+// properties monitored for changes
+const MONITORED_PROPERTIES: [UIProperty; 8] = [
+    UIProperty::Name,
+    UIProperty::IsEnabled,
+    UIProperty::IsOffscreen,
+    UIProperty::HasKeyboardFocus,
+    UIProperty::ValueValue,
+    UIProperty::ToggleToggleState,
+    UIProperty::ExpandCollapseExpandCollapseState,
+    UIProperty::SelectionItemIsSelected,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MonitorEventKind {
+    FocusChanged,
+    PropertyChanged,
+    StructureChanged,
+    Invoked,
+    Selection,
+    TextChanged,
+    WindowOpened,
+    WindowClosed,
+}
+
+impl MonitorEventKind {
+    pub const ALL: [MonitorEventKind; 8] = [
+        MonitorEventKind::FocusChanged,
+        MonitorEventKind::PropertyChanged,
+        MonitorEventKind::StructureChanged,
+        MonitorEventKind::Invoked,
+        MonitorEventKind::Selection,
+        MonitorEventKind::TextChanged,
+        MonitorEventKind::WindowOpened,
+        MonitorEventKind::WindowClosed,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MonitorEventKind::FocusChanged => "Focus Changed",
+            MonitorEventKind::PropertyChanged => "Property Changed",
+            MonitorEventKind::StructureChanged => "Structure Changed",
+            MonitorEventKind::Invoked => "Invoked",
+            MonitorEventKind::Selection => "Selection",
+            MonitorEventKind::TextChanged => "Text Changed",
+            MonitorEventKind::WindowOpened => "Window Opened",
+            MonitorEventKind::WindowClosed => "Window Closed",
+        }
+    }
+
+    // the automation events belonging to this kind, focus, property and
+    // structure changes have dedicated handlers
+    fn event_types(&self) -> &'static [UIEventType] {
+        match self {
+            MonitorEventKind::Invoked => &[UIEventType::Invoke_Invoked],
+            MonitorEventKind::Selection => &[
+                UIEventType::SelectionItem_ElementSelected,
+                UIEventType::SelectionItem_ElementAddedToSelection,
+                UIEventType::SelectionItem_ElementRemovedFromSelection,
+                UIEventType::Selection_Invalidated,
+            ],
+            MonitorEventKind::TextChanged => &[UIEventType::Text_TextChanged],
+            MonitorEventKind::WindowOpened => &[UIEventType::Window_WindowOpened],
+            MonitorEventKind::WindowClosed => &[UIEventType::Window_WindowClosed],
+            MonitorEventKind::FocusChanged | MonitorEventKind::PropertyChanged | MonitorEventKind::StructureChanged => &[],
+        }
+    }
+
+    fn from_event_type(event_type: UIEventType) -> Option<Self> {
+        MonitorEventKind::ALL.into_iter().find(|kind| kind.event_types().contains(&event_type))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorEvent {
+    pub timestamp: DateTime<Local>,
+    pub kind: MonitorEventKind,
+    pub source: UIElementProps,
+    pub details: String,
+}
+
+impl MonitorEvent {
+    fn new(kind: MonitorEventKind, sender: &UIElement, details: String) -> Self {
+        MonitorEvent {
+            timestamp: Local::now(),
+            kind,
+            source: UIElementProps::from_cached(sender),
+            details,
+        }
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        filter.is_empty()
+            || self.kind.label().to_lowercase().contains(&filter)
+            || self.source.name.to_lowercase().contains(&filter)
+            || self.source.control_type.to_lowercase().contains(&filter)
+            || self.details.to_lowercase().contains(&filter)
+    }
+}
+
+// forwards the events of all handlers to the monitor panel
+#[derive(Clone)]
+struct EventForwarder {
+    tx: Sender<MonitorEvent>,
+    ctx: egui::Context,
+    process_id: Option<i32>, // only events of this process are forwarded
+}
+
+impl EventForwarder {
+    fn forward(&self, kind: MonitorEventKind, sender: &UIElement, details: String) {
+        // the handlers are called for the events of all processes, the others are dropped
+        // before the remaining properties are read
+        if self.process_id.is_some_and(|process_id| sender.get_cached_process_id().ok() != Some(process_id)) {
+            return;
+        }
+        let event = MonitorEvent::new(kind, sender, details);
+        if self.tx.send(event).is_ok() {
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+impl CustomEventHandler for EventForwarder {
+    fn handle(&self, sender: &UIElement, event_type: UIEventType) -> uiautomation::Result<()> {
+        if let Some(kind) = MonitorEventKind::from_event_type(event_type) {
+            self.forward(kind, sender, format!("{:?}", event_type));
+        }
+        Ok(())
+    }
+}
+
+impl CustomFocusChangedEventHandler for EventForwarder {
+    fn handle(&self, sender: &UIElement) -> uiautomation::Result<()> {
+        self.forward(MonitorEventKind::FocusChanged, sender, String::new());
+        Ok(())
+    }
+}
+
+impl CustomPropertyChangedEventHandler for EventForwarder {
+    fn handle(&self, sender: &UIElement, property: UIProperty, new_value: Variant) -> uiautomation::Result<()> {
+        self.forward(MonitorEventKind::PropertyChanged, sender, format!("{:?} = {}", property, new_value));
+        Ok(())
+    }
+}
+
+impl CustomStructureChangedEventHandler for EventForwarder {
+    fn handle(&self, sender: &UIElement, change_type: StructureChangeType, runtime_id: Option<&[i32]>) -> uiautomation::Result<()> {
+        let details = match runtime_id {
+            Some(runtime_id) => format!("{:?} ({})", change_type, runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-")),
+            None => format!("{:?}", change_type),
+        };
+        self.forward(MonitorEventKind::StructureChanged, sender, details);
+        Ok(())
+    }
+}
+
+/// The event handlers registered on a dedicated thread, they are removed when the monitor is dropped
+pub struct EventMonitor {
+    rx: Receiver<MonitorEvent>,
+    stop: Sender<()>,
+}
+
+impl EventMonitor {
+    pub fn start(scope: CaptureScope, kinds: Vec<MonitorEventKind>, ctx: egui::Context) -> Result<Self, String> {
+        let (tx, rx): (Sender<MonitorEvent>, Receiver<MonitorEvent>) = channel();
+        let (stop, stop_rx): (Sender<()>, Receiver<()>) = channel();
+        let (started_tx, started_rx): (Sender<Result<(), String>>, Receiver<Result<(), String>>) = channel();
+
+        thread::spawn(move || {
+            let automation = match UIAutomation::new() {
+                Ok(automation) => automation,
+                Err(e) => {
+                    let _res = started_tx.send(Err(e.to_string()));
+                    return;
+                },
+            };
+            let res = register_handlers(&automation, &scope, &kinds, EventForwarder { tx, ctx, process_id: None });
+            let registered = res.is_ok();
+            let _res = started_tx.send(res);
+            if registered {
+                // the handlers are called by ui automation until they are removed
+                let _res = stop_rx.recv();
+            }
+            let _res = automation.remove_all_event_handlers();
+            log::debug!("event monitor stopped");
+        });
+
+        started_rx.recv().map_err(|e| e.to_string())??;
+        Ok(EventMonitor { rx, stop })
+    }
+
+    pub fn events(&self) -> Vec<MonitorEvent> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for EventMonitor {
+    fn drop(&mut self) {
+        let _res = self.stop.send(());
+    }
+}
+
+fn register_handlers(automation: &UIAutomation, scope: &CaptureScope, kinds: &[MonitorEventKind], mut forwarder: EventForwarder) -> Result<(), String> {
+    let root = automation.get_root_element().map_err(|e| e.to_string())?;
+    let element = match scope {
+        CaptureScope::Desktop => root,
+        CaptureScope::Process(process_id) => {
+            forwarder.process_id = Some(*process_id);
+            root
+        },
+        CaptureScope::Window(handle) => automation.element_from_handle(Handle::from(*handle)).map_err(|e| e.to_string())?,
+        CaptureScope::Subtree { handle, runtime_id } => {
+            let walker = automation.get_raw_view_walker().map_err(|e| e.to_string())?;
+            let start = automation.element_from_handle(Handle::from(*handle)).map_err(|e| e.to_string())?;
+            uiexplore::find_by_runtime_id(&walker, &start, runtime_id).ok_or("element no longer available".to_string())?
+        },
+    };
+    // focus changes are global, restrict them to the process of the scope
+    if !matches!(scope, CaptureScope::Desktop) && forwarder.process_id.is_none() {
+        forwarder.process_id = element.get_process_id().ok();
+    }
+    log::info!("monitoring {:?} for {}", kinds, scope.label());
+
+    // the properties of the sender are delivered with the event, the callbacks
+    // don't need to call back into the application
+    let cache_request = uiexplore::create_element_cache_request(automation).map_err(|e| e.to_string())?;
+
+    for kind in kinds {
+        let res = match kind {
+            MonitorEventKind::FocusChanged => {
+                let handler = UIFocusChangedEventHandler::from(forwarder.clone());
+                automation.add_focus_changed_event_handler(Some(&cache_request), &handler)
+            },
+            MonitorEventKind::PropertyChanged => {
+                let handler = UIPropertyChangedEventHandler::from(forwarder.clone());
+                automation.add_property_changed_event_handler(&element, TreeScope::Subtree, Some(&cache_request), &handler, &MONITORED_PROPERTIES)
+            },
+            MonitorEventKind::StructureChanged => {
+                let handler = UIStructureChangeEventHandler::from(forwarder.clone());
+                automation.add_structure_changed_event_handler(&element, TreeScope::Subtree, Some(&cache_request), &handler)
+            },
+            _ => kind.event_types().iter().try_for_each(|&event_type| {
+                let handler = UIEventHandler::from(forwarder.clone());
+                automation.add_automation_event_handler(event_type, &element, TreeScope::Subtree, Some(&cache_request), &handler)
+            }),
+        };
+        if let Err(e) = res {
+            log::warn!("failed to subscribe to {}: {}", kind.label(), e);
+        }
+    }
+    Ok(())
+}
+
+pub enum MonitorAction {
+    Start,
+    Stop,
+    /// select the source element with this runtime id in the tree
+    Select(Vec<i32>),
+}
+
+/// The state of the event monitor panel
+pub struct EventMonitorPanel {
+    kinds: Vec<MonitorEventKind>,
+    monitor: Option<EventMonitor>,
+    scope_label: String,
+    events: VecDeque<MonitorEvent>,
+    paused: bool,
+    filter: String,
+}
+
+impl EventMonitorPanel {
+    pub fn new() -> Self {
+        EventMonitorPanel {
+            kinds: MonitorEventKind::ALL.to_vec(),
+            monitor: None,
+            scope_label: String::new(),
+            events: VecDeque::new(),
+            paused: false,
+            filter: String::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.monitor.is_some()
+    }
+
+    pub fn start(&mut self, scope: CaptureScope, ctx: &egui::Context) -> Result<(), String> {
+        // the handlers of the previous scope are removed first
+        self.monitor = None;
+        self.monitor = Some(EventMonitor::start(scope.clone(), self.kinds.clone(), ctx.clone())?);
+        self.scope_label = scope.label();
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.monitor = None;
+    }
+
+    /// Moves the received events to the list, events received while paused are dropped
+    pub fn poll(&mut self) {
+        let Some(monitor) = &self.monitor else {
+            return;
+        };
+        for event in monitor.events() {
+            if self.paused {
+                continue;
+            }
+            if self.events.len() >= MAX_EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(event);
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<MonitorAction> {
+        let mut action: Option<MonitorAction> = None;
+
+        ui.horizontal_wrapped(|ui| {
+            for kind in MonitorEventKind::ALL {
+                let mut enabled = self.kinds.contains(&kind);
+                if ui.checkbox(&mut enabled, kind.label()).changed() {
+                    if enabled {
+                        self.kinds.push(kind);
+                    } else {
+                        self.kinds.retain(|&k| k != kind);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if self.monitor.is_some() {
+                if ui.button("⏹ Stop").clicked() {
+                    action = Some(MonitorAction::Stop);
+                }
+                ui.label(format!("Monitoring {}", self.scope_label));
+            } else if ui.button("▶ Start").on_hover_text("Monitor the scope selected for captures").clicked() {
+                action = Some(MonitorAction::Start);
+            }
+            ui.toggle_value(&mut self.paused, "⏸ Pause");
+            if ui.button("Clear").clicked() {
+                self.events.clear();
+            }
+            ui.label("Filter:");
+            ui.text_edit_singleline(&mut self.filter);
+        });
+        ui.separator();
+
+        let events: Vec<&MonitorEvent> = self.events.iter().filter(|event| event.matches(&self.filter)).collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, events.len(), |ui, rows| {
+                for event in &events[rows] {
+                    let text = format!("{}  {:<17}  {}  {}",
+                        event.timestamp.format("%H:%M:%S%.3f"),
+                        event.kind.label(),
+                        uiexplore::format_element_label(&event.source),
+                        event.details);
+                    if ui.selectable_label(false, text).on_hover_text("Select the source element in the tree").clicked() {
+                        action = Some(MonitorAction::Select(event.source.runtime_id.clone()));
+                    }
+                }
+            });
+
+        action
+    }
+}
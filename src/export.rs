@@ -0,0 +1,261 @@
+//! Export of a snapshot to a file for other tools, and import of trees exported before
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::UITree;
+use crate::csv_export::{self, CsvColumn};
+use crate::diagram_export::{self, DiagramOptions};
+use crate::html_report::{self, ReportScreenshot};
+use crate::xml_export;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Xml,
+    Dot,
+    Mermaid,
+    Html,
+    Csv,
+    Tsv,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [ExportFormat::Xml, ExportFormat::Dot, ExportFormat::Mermaid, ExportFormat::Html, ExportFormat::Csv, ExportFormat::Tsv];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Xml => "XML (UIA inspector layout)",
+            ExportFormat::Dot => "Graphviz DOT",
+            ExportFormat::Mermaid => "Mermaid Flowchart",
+            ExportFormat::Html => "HTML Report",
+            ExportFormat::Csv => "CSV (one row per element)",
+            ExportFormat::Tsv => "TSV (one row per element)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Xml => "xml",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Mermaid => "mmd",
+            ExportFormat::Html => "html",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+
+    pub fn can_import(&self) -> bool {
+        matches!(self, ExportFormat::Xml)
+    }
+
+    pub fn is_diagram(&self) -> bool {
+        matches!(self, ExportFormat::Dot | ExportFormat::Mermaid)
+    }
+
+    pub fn is_table(&self) -> bool {
+        matches!(self, ExportFormat::Csv | ExportFormat::Tsv)
+    }
+
+    /// The format of a file to import, based on its extension
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        ExportFormat::ALL.into_iter().find(|format| format.can_import() && format.extension() == extension)
+    }
+}
+
+/// Options of the export formats, each format uses the ones that apply to it
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub diagram: DiagramOptions,
+    /// the columns of the csv and tsv exports, in this order
+    pub columns: Vec<CsvColumn>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions { diagram: DiagramOptions::default(), columns: CsvColumn::ALL.to_vec() }
+    }
+}
+
+/// Formats the tree below (and including) the node at `start` in the given format
+pub fn export(ui_tree: &UITree, format: ExportFormat, start: usize, options: &ExportOptions) -> Result<String, String> {
+    match format {
+        ExportFormat::Xml => xml_export::to_xml(ui_tree, start),
+        ExportFormat::Dot => Ok(diagram_export::to_dot(ui_tree.get_tree(), start, &options.diagram)),
+        ExportFormat::Mermaid => Ok(diagram_export::to_mermaid(ui_tree.get_tree(), start, &options.diagram)),
+        ExportFormat::Html => {
+            // the screenshot is cropped to the exported element
+            let (_, ui_elem_props) = ui_tree.node(start);
+            let cropped = ui_tree.get_screenshot().and_then(|screenshot| screenshot.crop(&ui_elem_props.bounding_rect));
+            match cropped {
+                Some(cropped) => {
+                    let png = cropped.to_png().map_err(|e| format!("failed to encode the screenshot: {}", e))?;
+                    let screenshot = ReportScreenshot { png: &png, left: cropped.left, top: cropped.top };
+                    Ok(html_report::to_html(ui_tree, start, Some(&screenshot)))
+                },
+                None => Ok(html_report::to_html(ui_tree, start, None)),
+            }
+        },
+        ExportFormat::Csv => Ok(csv_export::to_csv(ui_tree, start, &options.columns, ',')),
+        ExportFormat::Tsv => Ok(csv_export::to_csv(ui_tree, start, &options.columns, '\t')),
+    }
+}
+
+pub fn export_to_file(ui_tree: &UITree, format: ExportFormat, start: usize, options: &ExportOptions, path: &Path) -> Result<(), String> {
+    let mut content = export(ui_tree, format, start, options)?;
+    if format.is_table() {
+        // the byte order mark makes spreadsheets read the file as utf-8
+        content.insert(0, '\u{feff}');
+    }
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+/// Reads a tree from a file exported before
+pub fn import_from_file(path: &Path) -> Result<UITree, String> {
+    let format = ExportFormat::from_path(path).ok_or(format!("{} can't be imported, only xml files are supported", path.display()))?;
+    let content = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    match format {
+        ExportFormat::Xml => xml_export::from_xml(&content),
+        _ => Err(format!("{} files can't be imported", format.label())),
+    }
+}
+
+pub enum ExportOutcome {
+    Status(String),
+    Imported(UITree),
+}
+
+pub struct ExportPanel {
+    format: ExportFormat,
+    options: ExportOptions,
+    // export the subtree of the active element instead of the whole tree
+    subtree_only: bool,
+}
+
+impl ExportPanel {
+    pub fn new() -> Self {
+        ExportPanel { format: ExportFormat::Xml, options: ExportOptions::default(), subtree_only: false }
+    }
+
+    /// Renders the export options for the active snapshot, `path` is the file to export to or import from
+    pub fn ui(&mut self, ui: &mut egui::Ui, ui_tree: &UITree, active_ui_element: Option<usize>, path: &mut String) -> Option<ExportOutcome> {
+        let mut outcome: Option<ExportOutcome> = None;
+
+        egui::Grid::new("export_grid").num_columns(2).spacing([20.0, 6.0]).show(ui, |ui| {
+            ui.label("Format:");
+            let prev_format = self.format;
+            egui::ComboBox::from_id_salt("export_format")
+                .selected_text(self.format.label())
+                .show_ui(ui, |ui| {
+                    for format in ExportFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.label());
+                    }
+                });
+            if self.format != prev_format {
+                *path = PathBuf::from(path.as_str()).with_extension(self.format.extension()).display().to_string();
+            }
+            ui.end_row();
+
+            ui.label("File:");
+            ui.text_edit_singleline(path);
+            ui.end_row();
+
+            ui.label("Elements:");
+            ui.add_enabled(active_ui_element.is_some(), egui::Checkbox::new(&mut self.subtree_only, "Subtree of active element only"));
+            ui.end_row();
+
+            if self.format.is_diagram() {
+                let diagram = &mut self.options.diagram;
+
+                ui.label("Max Depth:").on_hover_text("Levels below the first element, 0 = no limit");
+                let mut max_depth = diagram.max_depth.unwrap_or(0);
+                if ui.add(egui::DragValue::new(&mut max_depth).range(0..=50)).changed() {
+                    diagram.max_depth = if max_depth == 0 { None } else { Some(max_depth) };
+                }
+                ui.end_row();
+
+                ui.label("Node Label:").on_hover_text("{name} {control_type} {localized_control_type} {classname} {automation_id} {framework_id} {runtime_id} {index}, \\n starts a new line");
+                ui.text_edit_singleline(&mut diagram.label_template);
+                ui.end_row();
+
+                ui.label("Color by Control Type:");
+                ui.checkbox(&mut diagram.color_by_control_type, "");
+                ui.end_row();
+
+                ui.label("Collapse Children after:").on_hover_text("Further children are shown as a single summary node");
+                ui.add(egui::DragValue::new(&mut diagram.max_children).range(1..=500));
+                ui.end_row();
+            }
+
+            if self.format.is_table() {
+                ui.label("Columns:");
+                ui.horizontal(|ui| {
+                    if ui.small_button("All").clicked() {
+                        self.options.columns = CsvColumn::ALL.to_vec();
+                    }
+                    if ui.small_button("None").clicked() {
+                        self.options.columns.clear();
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        if self.format.is_table() {
+            // the columns keep the order of CsvColumn::ALL, whichever order they were checked in
+            egui::ScrollArea::vertical().id_salt("export_columns").max_height(160.0).show(ui, |ui| {
+                ui.columns(3, |columns| {
+                    for (i, column) in CsvColumn::ALL.into_iter().enumerate() {
+                        let mut checked = self.options.columns.contains(&column);
+                        if columns[i % 3].checkbox(&mut checked, column.header()).changed() {
+                            self.options.columns = CsvColumn::ALL.into_iter()
+                                .filter(|&known| if known == column { checked } else { self.options.columns.contains(&known) })
+                                .collect();
+                        }
+                    }
+                });
+            });
+        }
+
+        let desktop = ui_tree.desktop();
+        let start = if self.subtree_only { active_ui_element.unwrap_or(desktop) } else { desktop };
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            let has_columns = !self.format.is_table() || !self.options.columns.is_empty();
+            if ui.add_enabled(ui_tree.is_complete() && has_columns, egui::Button::new("💾 Export"))
+                .on_disabled_hover_text("Wait until the capture is complete and select at least one column")
+                .clicked() {
+                let msg = match export_to_file(ui_tree, self.format, start, &self.options, Path::new(path.as_str())) {
+                    Ok(()) => format!("Exported to {}", path),
+                    Err(msg) => format!("Export failed: {}", msg),
+                };
+                outcome = Some(ExportOutcome::Status(msg));
+            }
+            if ui.add_enabled(ui_tree.is_complete() && has_columns, egui::Button::new("📋 Copy"))
+                .on_hover_text("Copy the export to the clipboard, e.g. to paste a diagram into a document")
+                .clicked() {
+                match export(ui_tree, self.format, start, &self.options) {
+                    Ok(content) => {
+                        ui.ctx().copy_text(content);
+                        outcome = Some(ExportOutcome::Status("Copied the export to the clipboard".to_string()));
+                    },
+                    Err(msg) => outcome = Some(ExportOutcome::Status(format!("Export failed: {}", msg))),
+                }
+            }
+            if ui.add_enabled(self.format.can_import(), egui::Button::new("📂 Import"))
+                .on_hover_text("Open the file as a new snapshot, xml files can also be dropped onto the window")
+                .clicked() {
+                outcome = Some(match import_from_file(Path::new(path.as_str())) {
+                    Ok(ui_tree) => ExportOutcome::Imported(ui_tree),
+                    Err(msg) => ExportOutcome::Status(format!("Import failed: {}", msg)),
+                });
+            }
+        });
+
+        outcome
+    }
+}
@@ -0,0 +1,109 @@
+//! System wide hotkeys, registered on a dedicated thread running its own message loop
+#![allow(dead_code)]
+
+use std::thread;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use eframe::egui;
+
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::{RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_CONTROL, MOD_SHIFT, MOD_NOREPEAT, VK_E, VK_F};
+use windows::Win32::UI::WindowsAndMessaging::{GetMessageW, PostThreadMessageW, MSG, WM_HOTKEY, WM_QUIT};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    TogglePin,
+    CaptureUnderCursor,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hotkey {
+    pub modifiers: HOT_KEY_MODIFIERS,
+    pub virtual_key: u16,
+    pub action: HotkeyAction,
+}
+
+impl Hotkey {
+    pub fn new(modifiers: HOT_KEY_MODIFIERS, virtual_key: u16, action: HotkeyAction) -> Self {
+        Hotkey { modifiers, virtual_key, action }
+    }
+}
+
+pub fn default_hotkeys() -> Vec<Hotkey> {
+    vec![
+        // Ctrl+Shift+F
+        Hotkey::new(MOD_CONTROL | MOD_SHIFT, VK_F.0, HotkeyAction::TogglePin),
+        // Ctrl+Shift+E
+        Hotkey::new(MOD_CONTROL | MOD_SHIFT, VK_E.0, HotkeyAction::CaptureUnderCursor),
+    ]
+}
+
+pub struct HotkeyListener {
+    rx: Receiver<HotkeyAction>,
+    thread_id: u32,
+    registered: Vec<HotkeyAction>, // hotkeys already taken by another application are missing
+}
+
+impl HotkeyListener {
+    /// Registers the hotkeys on a separate thread and requests a repaint of the
+    /// app whenever one of them is pressed, so the action is processed even
+    /// if the explorer window does not have the focus.
+    pub fn start(hotkeys: Vec<Hotkey>, ctx: egui::Context) -> Self {
+        let (tx, rx): (Sender<HotkeyAction>, Receiver<HotkeyAction>) = channel();
+        let (tx_started, rx_started): (Sender<(u32, Vec<HotkeyAction>)>, Receiver<(u32, Vec<HotkeyAction>)>) = channel();
+
+        thread::spawn(move || {
+            // hotkeys are bound to the thread registering them, so the thread
+            // id is needed to stop the message loop later on
+            let thread_id = unsafe { GetCurrentThreadId() };
+
+            let mut registered: Vec<HotkeyAction> = Vec::new();
+            for (id, hotkey) in hotkeys.iter().enumerate() {
+                let res = unsafe { RegisterHotKey(HWND(std::ptr::null_mut()), id as i32, hotkey.modifiers | MOD_NOREPEAT, hotkey.virtual_key as u32) };
+                match res {
+                    Ok(()) => registered.push(hotkey.action),
+                    Err(e) => log::warn!("Failed to register hotkey {:?}: {}", hotkey.action, e),
+                }
+            }
+            tx_started.send((thread_id, registered)).unwrap();
+
+            let mut msg = MSG::default();
+            while unsafe { GetMessageW(&mut msg, HWND(std::ptr::null_mut()), 0, 0) }.as_bool() {
+                if msg.message == WM_HOTKEY {
+                    if let Some(hotkey) = hotkeys.get(msg.wParam.0) {
+                        if tx.send(hotkey.action).is_err() {
+                            break;
+                        }
+                        ctx.request_repaint();
+                    }
+                }
+            }
+
+            for id in 0..hotkeys.len() {
+                let _res = unsafe { UnregisterHotKey(HWND(std::ptr::null_mut()), id as i32) };
+            }
+        });
+
+        let (thread_id, registered) = rx_started.recv().unwrap();
+        HotkeyListener { rx, thread_id, registered }
+    }
+
+    /// Whether the hotkey of the action works, i.e. it wasn't taken by another application
+    pub fn is_registered(&self, action: HotkeyAction) -> bool {
+        self.registered.contains(&action)
+    }
+
+    /// Returns all hotkey actions triggered since the last call.
+    pub fn triggered(&self) -> Vec<HotkeyAction> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for HotkeyListener {
+    fn drop(&mut self) {
+        // end the message loop of the hotkey thread
+        let _res = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
+    }
+}
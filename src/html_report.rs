@@ -0,0 +1,303 @@
+//! Self-contained HTML report of a snapshot, for people without UI Explore: a collapsible,
+//! searchable tree, the properties of the clicked element and, if available, a screenshot
+//! with the bounding rectangles drawn over it. Everything is embedded in the single file.
+#![allow(dead_code)]
+
+use crate::UITree;
+use crate::escape::{json_string, xml_escape};
+use crate::uiexplore;
+
+/// A screenshot embedded in the report, `left` and `top` are the screen coordinates of its
+/// top left pixel, so the bounding rectangles of the elements can be drawn over it
+pub struct ReportScreenshot<'a> {
+    pub png: &'a [u8],
+    pub left: i32,
+    pub top: i32,
+}
+
+/// Generates the report for the element at `start` and everything below it
+pub fn to_html(ui_tree: &UITree, start: usize, screenshot: Option<&ReportScreenshot>) -> String {
+    let title = format!("UI Explore snapshot {} ({})", ui_tree.get_captured_at().format("%Y-%m-%d %H:%M:%S"), ui_tree.get_scope().label());
+
+    // the nodes in depth first order, parents refer to the position of their parent in the list
+    let mut nodes: Vec<String> = Vec::new();
+    let mut stack: Vec<(usize, i64)> = vec![(start, -1)];
+    while let Some((index, parent)) = stack.pop() {
+        let (_, ui_elem_props) = ui_tree.node(index);
+        let rect = &ui_elem_props.bounding_rect;
+        let props: Vec<String> = ui_elem_props.property_list().into_iter()
+            .map(|(name, value)| format!("[{},{}]", json_string(name), json_string(&value)))
+            .collect();
+        let position = nodes.len() as i64;
+        nodes.push(format!("{{\"p\":{},\"l\":{},\"r\":[{},{},{},{}],\"props\":[{}]}}",
+            parent,
+            json_string(&uiexplore::format_element_label(ui_elem_props)),
+            rect.get_left(), rect.get_top(), rect.get_right(), rect.get_bottom(),
+            props.join(",")));
+        for &child in ui_tree.children(index).iter().rev() {
+            stack.push((child, position));
+        }
+    }
+
+    let screenshot_json = match screenshot {
+        Some(screenshot) => format!("{{\"src\":\"data:image/png;base64,{}\",\"left\":{},\"top\":{}}}", base64_encode(screenshot.png), screenshot.left, screenshot.top),
+        None => "null".to_string(),
+    };
+    let data = escape_script_data(&format!("{{\"nodes\":[\n{}\n],\"screenshot\":{}}}", nodes.join(",\n"), screenshot_json));
+
+    REPORT_TEMPLATE
+        .replace("{{title}}", &xml_escape(&title))
+        .replace("{{data}}", &data)
+}
+
+// the names are set by the inspected application, "</script" would end the script element early and
+// "<!--" switches the parser into the escaped state. "<" only occurs inside the json strings, where 
+// the escape sequence stands for the same character.
+fn escape_script_data(json: &str) -> String {
+    json.replace('<', "\\u003c")
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let triple = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for pos in 0..4 {
+            if pos <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(triple >> (18 - 6 * pos) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+const REPORT_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+  body { font-family: "Segoe UI", sans-serif; font-size: 13px; margin: 0; display: flex; height: 100vh; }
+  #left { width: 45%; display: flex; flex-direction: column; border-right: 1px solid #ccc; }
+  #right { flex: 1; overflow: auto; padding: 8px 12px; }
+  #search { margin: 8px; padding: 4px; }
+  #results { max-height: 25%; overflow: auto; margin: 0 8px; }
+  #tree { flex: 1; overflow: auto; padding: 0 8px 8px 8px; }
+  ul { list-style: none; margin: 0; padding-left: 16px; }
+  #tree > ul { padding-left: 0; }
+  .toggle { display: inline-block; width: 14px; cursor: pointer; color: #666; }
+  .label { cursor: pointer; white-space: nowrap; padding: 0 2px; }
+  .label:hover { background: #eef; }
+  .selected { background: #cde8ff !important; }
+  .match { font-weight: bold; }
+  .result { cursor: pointer; white-space: nowrap; }
+  .result:hover { background: #eef; }
+  table { border-collapse: collapse; margin-bottom: 12px; }
+  td { border: 1px solid #ddd; padding: 2px 6px; vertical-align: top; }
+  td:first-child { color: #555; white-space: nowrap; }
+  #shot { position: relative; display: inline-block; }
+  #shot img { max-width: 100%; display: block; cursor: crosshair; }
+  .box { position: absolute; border: 1px solid rgba(0, 120, 215, 0.6); pointer-events: none; }
+  .box.active { border: 2px solid #e81123; }
+</style>
+</head>
+<body>
+<div id="left">
+  <input id="search" type="search" placeholder="Search names, control types, class names...">
+  <div id="results"></div>
+  <div id="tree"></div>
+</div>
+<div id="right">
+  <h3 id="heading">{{title}}</h3>
+  <table id="props"></table>
+  <div id="shot"></div>
+</div>
+<script>
+const DATA = {{data}};
+const nodes = DATA.nodes;
+const children = nodes.map(() => []);
+nodes.forEach((node, i) => { if (node.p >= 0) children[node.p].push(i); });
+const items = new Map(); // rendered list items by node position
+let selected = -1;
+
+function renderNode(i) {
+  const li = document.createElement("li");
+  const toggle = document.createElement("span");
+  toggle.className = "toggle";
+  toggle.textContent = children[i].length ? "▸" : "";
+  toggle.onclick = () => setOpen(i, !li.open);
+  const label = document.createElement("span");
+  label.className = "label";
+  label.textContent = nodes[i].l;
+  label.onclick = () => select(i);
+  li.append(toggle, label);
+  li.open = false;
+  li.toggle = toggle;
+  li.label = label;
+  items.set(i, li);
+  return li;
+}
+
+// children are rendered when their parent is opened for the first time
+function setOpen(i, open) {
+  const li = items.get(i);
+  if (!children[i].length) return;
+  if (open && !li.list) {
+    li.list = document.createElement("ul");
+    children[i].forEach(child => li.list.append(renderNode(child)));
+    li.append(li.list);
+  }
+  if (li.list) li.list.style.display = open ? "" : "none";
+  li.open = open;
+  li.toggle.textContent = open ? "▾" : "▸";
+}
+
+function reveal(i) {
+  const path = [];
+  for (let p = nodes[i].p; p >= 0; p = nodes[p].p) path.unshift(p);
+  path.forEach(p => setOpen(p, true));
+}
+
+function select(i) {
+  if (selected >= 0 && items.has(selected)) items.get(selected).label.classList.remove("selected");
+  selected = i;
+  reveal(i);
+  const label = items.get(i).label;
+  label.classList.add("selected");
+  label.scrollIntoView({ block: "nearest" });
+  const table = document.getElementById("props");
+  table.innerHTML = "";
+  nodes[i].props.forEach(([name, value]) => {
+    const row = table.insertRow();
+    row.insertCell().textContent = name;
+    row.insertCell().textContent = value;
+  });
+  drawBoxes();
+}
+
+document.getElementById("search").oninput = (event) => {
+  const text = event.target.value.trim().toLowerCase();
+  const results = document.getElementById("results");
+  results.innerHTML = "";
+  if (!text) return;
+  const matches = [];
+  for (let i = 0; i < nodes.length && matches.length < 200; i++) {
+    if (nodes[i].l.toLowerCase().includes(text)) matches.push(i);
+  }
+  matches.forEach(i => {
+    const div = document.createElement("div");
+    div.className = "result";
+    div.textContent = nodes[i].l;
+    div.onclick = () => select(i);
+    results.append(div);
+  });
+  if (matches.length === 200) results.append("…");
+};
+
+// the selected element and its children are outlined on the screenshot
+function drawBoxes() {
+  const shot = DATA.screenshot;
+  const container = document.getElementById("shot");
+  const img = container.querySelector("img");
+  if (!shot || !img || !img.naturalWidth) return;
+  container.querySelectorAll(".box").forEach(box => box.remove());
+  if (selected < 0) return;
+  const scale = img.clientWidth / img.naturalWidth;
+  [selected].concat(children[selected]).forEach((i, pos) => {
+    const [l, t, r, b] = nodes[i].r;
+    if (r <= l || b <= t) return;
+    const box = document.createElement("div");
+    box.className = pos === 0 ? "box active" : "box";
+    box.style.left = (l - shot.left) * scale + "px";
+    box.style.top = (t - shot.top) * scale + "px";
+    box.style.width = (r - l) * scale + "px";
+    box.style.height = (b - t) * scale + "px";
+    container.append(box);
+  });
+}
+
+// clicking the screenshot selects the smallest element at that point
+function selectAt(event) {
+  const shot = DATA.screenshot;
+  const img = event.target;
+  const scale = img.naturalWidth / img.clientWidth;
+  const x = shot.left + event.offsetX * scale;
+  const y = shot.top + event.offsetY * scale;
+  let best = -1, bestArea = Infinity;
+  nodes.forEach((node, i) => {
+    const [l, t, r, b] = node.r;
+    const area = (r - l) * (b - t);
+    if (x >= l && x <= r && y >= t && y <= b && area > 0 && area < bestArea) { best = i; bestArea = area; }
+  });
+  if (best >= 0) select(best);
+}
+
+const tree = document.createElement("ul");
+tree.append(renderNode(0));
+document.getElementById("tree").append(tree);
+setOpen(0, true);
+
+if (DATA.screenshot) {
+  const img = document.createElement("img");
+  img.src = DATA.screenshot.src;
+  img.onload = drawBoxes;
+  img.onclick = selectAt;
+  document.getElementById("shot").append(img);
+  window.onresize = drawBoxes;
+}
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{element, with_rect, TreeBuilder};
+
+    #[test]
+    fn base64_pads_to_full_quads() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(base64_encode(b"fooba"), "Zm9vYmE=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn base64_uses_the_whole_alphabet() {
+        assert_eq!(base64_encode(&[0xff, 0xfe]), "//4=");
+        assert_eq!(base64_encode(&[0xfb, 0xef, 0xbe]), "++++");
+        assert_eq!(base64_encode(&[0x00, 0x10, 0x83, 0x10, 0x51, 0x87]), "ABCDEFGH");
+        // the png signature
+        assert_eq!(base64_encode(&[0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]), "iVBORw0KGgo=");
+    }
+
+    #[test]
+    fn script_data_contains_no_markup() {
+        assert_eq!(escape_script_data("[\"</script>\"]"), "[\"\\u003c/script>\"]");
+        assert_eq!(escape_script_data("[\"<!--\",\"<script>\"]"), "[\"\\u003c!--\",\"\\u003cscript>\"]");
+        assert_eq!(escape_script_data("{\"r\":[0,0,10,10]}"), "{\"r\":[0,0,10,10]}");
+    }
+
+    #[test]
+    fn names_cannot_break_out_of_the_script() {
+        let mut tree = TreeBuilder::new(with_rect(element("Pane", "Desktop"), 0, 0, 1920, 1080));
+        let window = tree.add(tree.desktop(), with_rect(element("Window", "<!--<script>"), 0, 0, 800, 600));
+        tree.add(window, with_rect(element("Button", "</script><b>bold</b>"), 10, 10, 110, 40));
+        let ui_tree = tree.build();
+
+        let html = to_html(&ui_tree, ui_tree.desktop(), None);
+        for markup in ["<script", "</script>", "<!--", "<b>"] {
+            assert_eq!(html.matches(markup).count(), REPORT_TEMPLATE.matches(markup).count(), "{}", markup);
+        }
+        assert!(html.contains("\\u003c!--\\u003cscript>"));
+        assert!(html.contains("\\u003c/script>\\u003cb>bold\\u003c/b>"));
+    }
+}
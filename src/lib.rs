@@ -1 +1 @@
-pub mod signal_file;
+pub mod splash;
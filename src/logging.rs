@@ -0,0 +1,313 @@
+//! Leveled logging to a rotating log file in the user's data dir and to an in-memory buffer
+//! shown in the log panel. Log records carry the spans (e.g. capture, window, refresh) that
+//! were entered on the logging thread.
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const LOG_FILE_NAME: &str = "uiexplore.log";
+// size after which the log file is rotated
+const MAX_LOG_FILE_SIZE: u64 = 5 * 1024 * 1024;
+// number of rotated log files kept, uiexplore.log.1 being the most recent one
+const MAX_ROTATED_FILES: usize = 5;
+// number of records kept for the log panel
+const BUFFER_CAPACITY: usize = 5000;
+// environment variable overriding the log level, e.g. UIEXPLORE_LOG=trace
+const LOG_LEVEL_ENV: &str = "UIEXPLORE_LOG";
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub spans: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    fn format(&self) -> String {
+        let spans = if self.spans.is_empty() { String::new() } else { format!("{} ", self.spans) };
+        format!("{} {:<5} {}{}: {}", self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"), self.level, spans, self.target, self.message)
+    }
+}
+
+/// The most recent log records, shared between the logger and the log panel
+#[derive(Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<LogEntry>>>);
+
+impl LogBuffer {
+    fn push(&self, entry: LogEntry) {
+        let mut entries = self.0.lock().unwrap();
+        if entries.len() >= BUFFER_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Returns the number of records with at least the given level
+    pub fn count(&self, level: LevelFilter) -> usize {
+        self.0.lock().unwrap().iter().filter(|entry| entry.level <= level).count()
+    }
+
+    /// Returns the records with at least the given level at the positions in `rows`,
+    /// e.g. the rows visible in the log panel
+    pub fn entries(&self, level: LevelFilter, rows: Range<usize>) -> Vec<LogEntry> {
+        self.0.lock().unwrap().iter()
+            .filter(|entry| entry.level <= level)
+            .skip(rows.start)
+            .take(rows.len())
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+struct RotatingFile {
+    dir: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: PathBuf) -> Self {
+        let path = dir.join(LOG_FILE_NAME);
+        let file = fs::create_dir_all(&dir)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+            .ok();
+        let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        RotatingFile { dir, file, size }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size + line.len() as u64 > MAX_LOG_FILE_SIZE {
+            self.rotate();
+        }
+        if let Some(file) = &mut self.file {
+            if writeln!(file, "{}", line).is_ok() {
+                self.size += line.len() as u64 + 1;
+            }
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        let path = |n: usize| if n == 0 { self.dir.join(LOG_FILE_NAME) } else { self.dir.join(format!("{}.{}", LOG_FILE_NAME, n)) };
+        let _res = fs::remove_file(path(MAX_ROTATED_FILES));
+        for n in (0..MAX_ROTATED_FILES).rev() {
+            let _res = fs::rename(path(n), path(n + 1));
+        }
+        self.file = File::create(path(0)).ok();
+        self.size = 0;
+    }
+}
+
+struct Logger {
+    level: LevelFilter,
+    file: Mutex<RotatingFile>,
+    buffer: LogBuffer,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // only our own records, dependencies like eframe log a lot on debug level
+        metadata.level() <= self.level && (metadata.target().starts_with("uiexplore") || metadata.level() <= Level::Warn)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let entry = LogEntry {
+            timestamp: Local::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            spans: current_spans().join(":"),
+            message: record.args().to_string(),
+        };
+        self.file.lock().unwrap().write_line(&entry.format());
+        self.buffer.push(entry);
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &mut self.file.lock().unwrap().file {
+            let _res = file.flush();
+        }
+    }
+}
+
+static LOG_BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Installs the logger, the level is Info in release and Debug in debug builds
+/// unless set with the UIEXPLORE_LOG environment variable.
+pub fn init() {
+    let default_level = if cfg!(debug_assertions) { LevelFilter::Debug } else { LevelFilter::Info };
+    let level = std::env::var(LOG_LEVEL_ENV).ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .unwrap_or(default_level);
+
+    let dir = log_dir();
+    let buffer = LOG_BUFFER.get_or_init(LogBuffer::default).clone();
+    let logger = Logger { level, file: Mutex::new(RotatingFile::open(dir.clone())), buffer };
+    let _res = LOG_DIR.set(dir);
+
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// The records shown in the log panel
+pub fn buffer() -> LogBuffer {
+    LOG_BUFFER.get_or_init(LogBuffer::default).clone()
+}
+
+/// %LOCALAPPDATA%\uiexplore\logs, or the temp dir if the data dir is unknown
+pub fn log_dir() -> PathBuf {
+    if let Some(dir) = LOG_DIR.get() {
+        return dir.clone();
+    }
+    std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("uiexplore")
+        .join("logs")
+}
+
+thread_local! {
+    static SPANS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the spans entered on the current thread, outermost first
+pub fn current_spans() -> Vec<String> {
+    SPANS.with(|spans| spans.borrow().clone())
+}
+
+/// Continues the spans of another thread on the current one, e.g. in a worker thread
+pub fn inherit_spans(parent: Vec<String>) {
+    SPANS.with(|spans| *spans.borrow_mut() = parent);
+}
+
+/// A span is active until the guard is dropped, its duration is logged on exit
+pub struct SpanGuard {
+    label: String,
+    started: Instant,
+}
+
+/// Enters a span, `fields` are shown in braces after the name, e.g. window{name='Calculator'}
+pub fn span(name: &str, fields: &str) -> SpanGuard {
+    let label = if fields.is_empty() { name.to_string() } else { format!("{}{{{}}}", name, fields) };
+    SPANS.with(|spans| spans.borrow_mut().push(label.clone()));
+    log::debug!("enter");
+    SpanGuard { label, started: Instant::now() }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        log::debug!("exit after {:?}", self.started.elapsed());
+        SPANS.with(|spans| {
+            let mut spans = spans.borrow_mut();
+            if let Some(pos) = spans.iter().rposition(|label| *label == self.label) {
+                spans.truncate(pos);
+            }
+        });
+    }
+}
+
+/// Renders the log panel with a level filter
+pub fn ui(ui: &mut eframe::egui::Ui, level: &mut LevelFilter) {
+    use eframe::egui;
+
+    let buffer = buffer();
+    // records above the level of the logger are never recorded, e.g. debug in release builds
+    let max_level = log::max_level();
+    *level = (*level).min(max_level);
+    ui.horizontal(|ui| {
+        ui.label("Level:");
+        egui::ComboBox::from_id_salt("log_level")
+            .selected_text(level.to_string())
+            .show_ui(ui, |ui| {
+                for candidate in [LevelFilter::Error, LevelFilter::Warn, LevelFilter::Info, LevelFilter::Debug, LevelFilter::Trace] {
+                    if candidate <= max_level {
+                        ui.selectable_value(level, candidate, candidate.to_string());
+                    }
+                }
+            })
+            .response
+            .on_hover_text(format!("More detailed levels are logged if set with {}, e.g. {}=trace", LOG_LEVEL_ENV, LOG_LEVEL_ENV));
+        if ui.button("Clear").clicked() {
+            buffer.clear();
+        }
+        ui.label(egui::RichText::new(format!("Log file: {}", log_dir().join(LOG_FILE_NAME).display())).weak());
+    });
+    ui.separator();
+
+    // only the visible rows are copied out of the buffer
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    egui::ScrollArea::both()
+        .auto_shrink([false, false])
+        .stick_to_bottom(true)
+        .show_rows(ui, row_height, buffer.count(*level), |ui, rows| {
+            for entry in buffer.entries(*level, rows) {
+                let color = match entry.level {
+                    Level::Error => ui.visuals().error_fg_color,
+                    Level::Warn => ui.visuals().warn_fg_color,
+                    Level::Info => ui.visuals().text_color(),
+                    Level::Debug | Level::Trace => ui.visuals().weak_text_color(),
+                };
+                ui.label(egui::RichText::new(entry.format()).monospace().color(color));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: Level, message: &str) -> LogEntry {
+        LogEntry { timestamp: Local::now(), level, target: "uiexplore".to_string(), spans: String::new(), message: message.to_string() }
+    }
+
+    fn messages(entries: Vec<LogEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.message).collect()
+    }
+
+    #[test]
+    fn entries_are_filtered_by_level_and_row() {
+        let buffer = LogBuffer::default();
+        buffer.push(entry(Level::Info, "started"));
+        buffer.push(entry(Level::Debug, "details"));
+        buffer.push(entry(Level::Warn, "slow"));
+        buffer.push(entry(Level::Error, "failed"));
+        buffer.push(entry(Level::Info, "done"));
+
+        assert_eq!(buffer.count(LevelFilter::Trace), 5);
+        assert_eq!(buffer.count(LevelFilter::Info), 4);
+        assert_eq!(buffer.count(LevelFilter::Off), 0);
+        assert_eq!(messages(buffer.entries(LevelFilter::Info, 0..4)), ["started", "slow", "failed", "done"]);
+        assert_eq!(messages(buffer.entries(LevelFilter::Info, 1..3)), ["slow", "failed"]);
+        assert_eq!(messages(buffer.entries(LevelFilter::Warn, 1..5)), ["failed"]);
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped() {
+        let buffer = LogBuffer::default();
+        for n in 0..BUFFER_CAPACITY + 2 {
+            buffer.push(entry(Level::Info, &n.to_string()));
+        }
+        assert_eq!(buffer.count(LevelFilter::Info), BUFFER_CAPACITY);
+        assert_eq!(messages(buffer.entries(LevelFilter::Info, 0..1)), ["2"]);
+    }
+}
@@ -1,19 +1,3 @@
-// use chrono::offset::Local;
-
-//println with formatted time stamp included
-#[macro_export]
-macro_rules! printfmt {
-    // take a token tree as input
-    ($($arg:tt)*) => {
-        // Print the timestamp
-        print!("{}: ", chrono::offset::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"));
-        // Print the arguments passed
-        println!($($arg)*);
-
-    };
-
-}
-
 #[macro_export]
 macro_rules! sendmsg {
     // take an expression as input
@@ -12,12 +12,31 @@ use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_
 use windows::Win32::Graphics::Gdi::{MONITOR_FROM_FLAGS, MonitorFromPoint};
 use windows::Win32::UI::HiDpi::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE, DPI_AWARENESS_PER_MONITOR_AWARE, MONITOR_DPI_TYPE, GetDpiForMonitor, SetProcessDpiAwarenessContext, GetDpiAwarenessContextForProcess, GetAwarenessFromDpiAwarenessContext}; //DPI_AWARENESS, DPI_AWARENESS_CONTEXT, GetThreadDpiAwarenessContext
 use windows::Win32::Foundation::{POINT, HANDLE};
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
 
 
 mod rectangle;
 mod winevent;
-
-use ::uiexplore::signal_file;
+mod hotkey;
+mod settings;
+mod tree_view;
+mod logging;
+mod event_monitor;
+mod audit;
+mod a11y_gate;
+mod escape;
+mod tab_order;
+mod xml_export;
+mod diagram_export;
+mod html_report;
+mod csv_export;
+mod screenshot;
+mod element_map;
+mod overlay;
+mod export;
+mod parallel_capture;
+
+use ::uiexplore::splash::{SplashMessage, SplashScreen};
 
 mod uiexplore;
 use uiexplore::{UITree, UIElementProps, UIElementInTree};
@@ -37,26 +56,100 @@ mod tree_map;
 use tree_map::UITreeMap;
 
 
+// interval in which the initial capture is checked for being cancelled on the start screen
+const START_SCREEN_POLL_INTERVAL_MS: u64 = 100;
+
+// exit code of the explorer if the command line can't be parsed
+const EXIT_INVALID_ARGS: i32 = 2;
+
 fn main() -> eframe::Result {
 
-    printfmt!("Getting the ui tree");
+    logging::init();
+
+    // headless accessibility check, e.g. `uiexplore audit --process 1234 --sarif a11y.sarif`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "audit") {
+        attach_parent_console();
+        std::process::exit(a11y_gate::run(&args[1..]));
+    }
+
+    // the initial capture can be scoped, e.g. `uiexplore --process 1234`
+    let scope = match parse_scope_args(&args) {
+        Ok(scope) => scope,
+        Err(msg) => {
+            attach_parent_console();
+            log::error!("{}", msg);
+            eprintln!("{}\nUsage: uiexplore {}", msg, uiexplore::ScopeArgs::USAGE);
+            std::process::exit(EXIT_INVALID_ARGS);
+        },
+    };
+
+    log::info!("Getting the ui tree of {}", scope.label());
 
     // get the ui tree in a separate thread
-    let (tx, rx): (Sender<_>, Receiver<uiexplore::UITree>) = channel();
-    thread::spawn(|| {
-        uiexplore::get_all_elements(tx, None);
+    let (tx, rx): (Sender<_>, Receiver<uiexplore::CaptureMessage>) = channel();
+    let cancel = uiexplore::CancelToken::new();
+    let worker_cancel = cancel.clone();
+    thread::spawn(move || {
+        uiexplore::get_all_elements(tx, uiexplore::CaptureOptions { scope, ..Default::default() }, worker_cancel);
     });
-    printfmt!("Spawned separate thread to get ui tree");
+    log::debug!("Spawned separate thread to get ui tree");
 
-    printfmt!("displaying start screen now");
-    launch_start_screen();
+    log::debug!("displaying start screen now");
+    let splash_cancel = cancel.clone();
+    let mut splash = launch_start_screen(move || splash_cancel.cancel());
     
-    let ui_tree = rx.recv().unwrap();
+    // wait until the top level windows are known, their content is streamed into the 
+    // explorer while it is already running
+    let (ui_tree, streaming) = loop {
+        // a capture cancelled on the start screen ends the explorer right away,
+        // even if the capture thread is stuck in a call into a hung application
+        if cancel.is_cancelled() {
+            log::info!("Getting the ui tree was cancelled on the start screen");
+            if let Some(splash) = splash {
+                splash.close();
+            }
+            return Ok(());
+        }
+        match rx.recv_timeout(std::time::Duration::from_millis(START_SCREEN_POLL_INTERVAL_MS)) {
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => (),
+            Ok(uiexplore::CaptureMessage::Progress(progress)) => {
+                log::debug!("Captured {} elements, window {}/{}: {}", progress.nodes_visited, progress.windows_done + 1, progress.windows_total, progress.current_window);
+                if let Some(splash) = &mut splash {
+                    splash.send(&SplashMessage::Progress { 
+                        nodes_visited: progress.nodes_visited, 
+                        windows_done: progress.windows_done, 
+                        windows_total: progress.windows_total, 
+                        current_window: progress.current_window,
+                    });
+                }
+            },
+            Ok(uiexplore::CaptureMessage::Skeleton(ui_tree)) => break (ui_tree, true),
+            Ok(uiexplore::CaptureMessage::Subtree { .. }) => (),
+            Ok(uiexplore::CaptureMessage::Done(ui_tree)) => break (ui_tree, false),
+            Ok(uiexplore::CaptureMessage::Failed(msg)) => {
+                log::error!("Failed to get the ui tree: {}", msg);
+                if let Some(splash) = splash {
+                    splash.fail(&msg);
+                }
+                return Ok(());
+            },
+            Ok(uiexplore::CaptureMessage::Cancelled) | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                log::warn!("Getting the ui tree was aborted");
+                if let Some(splash) = splash {
+                    splash.fail("The capture was aborted");
+                }
+                return Ok(());
+            },
+        }
+    };
     
-    signal_file::create_signal_file().unwrap();
-    printfmt!("UI Tree retrieved, setting up UIExplorer app...");
+    if let Some(splash) = splash {
+        splash.close();
+    }
+    log::info!("UI Tree retrieved, setting up UIExplorer app...");
+    let pending_capture = if streaming { Some((rx, cancel)) } else { None };
 
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).    
 
     let app_size_pos = AppContext::new_from_screen(0.4, 0.8);
 
@@ -71,10 +164,10 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "UI Explore",
         options,
-        Box::new(|_cc| {
+        Box::new(move |cc| {
             // This gives us image support:
             // egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(UIExplorer::new_with_state(app_size_pos, ui_tree)))
+            Ok(Box::new(UIExplorer::new_with_state(app_size_pos, ui_tree, pending_capture, cc)))
         }),
 
     )
@@ -89,7 +182,9 @@ struct ScreenSize {
 #[derive(Debug)]
 #[repr(C)]
 struct AppContext {
+    #[allow(dead_code)]
     screen_width: i32,
+    #[allow(dead_code)]
     screen_height: i32,
     screen_scale: f32,
     app_width: f32,
@@ -165,7 +260,7 @@ fn get_screen_scale_factor() -> f32 {
         let scale_x = dpi_x as f32 / 96.0;
         let scale_y = dpi_y as f32 / 96.0;
         let scale = (scale_x + scale_y) / 2.0;
-        println!("Screen size: {}x{}, DPI: {}x{}, Awareness Process: {}, Scale: {}", x, y, dpi_x, dpi_y, awareness, scale);
+        log::info!("Screen size: {}x{}, DPI: {}x{}, Awareness Process: {}, Scale: {}", x, y, dpi_x, dpi_y, awareness, scale);
 
         scale
     }
@@ -173,17 +268,41 @@ fn get_screen_scale_factor() -> f32 {
 
 }
 
-fn launch_start_screen() {
-
-    let msg: &str;
-
-    let cmd = std::process::Command::new("start_screen.exe").spawn();
+// release builds have no console of their own, the output of the headless 
+// check goes to the console of the calling shell or build agent
+fn attach_parent_console() {
+    unsafe {
+        let _res = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
 
-    match cmd {
-        Ok(_) => { msg = "Start Screen successfully launched"; }
-        Err(_) => { msg = "Failed to launch Start Screen"; }
+// the explorer takes the same scope flags as the headless check, the whole desktop without any
+fn parse_scope_args(args: &[String]) -> Result<uiexplore::CaptureScope, String> {
+    let mut scope_args = uiexplore::ScopeArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("missing value for {}", arg));
+        if !scope_args.parse_arg(arg, &mut value)? {
+            return Err(format!("unknown argument '{}'", arg));
+        }
     }
+    scope_args.resolve()
+}
 
-    printfmt!("{}", msg);
+fn launch_start_screen<F>(on_cancel: F) -> Option<SplashScreen>
+where
+    F: Fn() + Send + 'static,
+{
+
+    match SplashScreen::launch(on_cancel) {
+        Ok(splash) => {
+            log::debug!("Start Screen successfully launched");
+            Some(splash)
+        },
+        Err(e) => {
+            log::warn!("Failed to launch Start Screen: {}", e);
+            None
+        },
+    }
 }
 
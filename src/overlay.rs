@@ -0,0 +1,390 @@
+//! Highlights shown in topmost, click-through layered windows owned by a dedicated thread,
+//! instead of being painted onto the desktop where they leave artefacts behind and are
+//! wiped as soon as the highlighted window repaints
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::thread;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, RECT, SIZE, WPARAM};
+use windows::Win32::Graphics::Gdi::{DEFAULT_GUI_FONT, PAINTSTRUCT, TRANSPARENT, BeginPaint, CreateSolidBrush, DeleteObject, EndPaint, FillRect, GetDC, GetStockObject, GetTextExtentPoint32W, ReleaseDC, SelectObject, SetBkMode, SetTextColor, TextOutW};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW, GetSystemMetrics, KillTimer, PeekMessageW, PostThreadMessageW, RegisterClassExW,
+    SetLayeredWindowAttributes, SetTimer, SetWindowDisplayAffinity, ShowWindow, TranslateMessage,
+    HMENU, HTTRANSPARENT, LWA_ALPHA, LWA_COLORKEY, MSG, PM_NOREMOVE, SM_YVIRTUALSCREEN, SW_SHOWNOACTIVATE, WDA_EXCLUDEFROMCAPTURE, WNDCLASSEXW,
+    WM_APP, WM_NCHITTEST, WM_PAINT, WM_QUIT, WM_TIMER, WM_USER, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+use crate::UIHashMap;
+
+/// Class name of the overlay windows, so captures can leave them out
+pub const OVERLAY_CLASS_NAME: &str = "UIExploreOverlay";
+
+// pixels of this color are transparent, highlights in exactly this color are drawn in black
+const TRANSPARENT_KEY: [u8; 3] = [1, 0, 1];
+// interval in which fading highlights are updated
+const FADE_INTERVAL_MS: u32 = 30;
+// space around the label text
+const LABEL_PADDING_X: i32 = 3;
+const LABEL_PADDING_Y: i32 = 1;
+const MAX_LABEL_CHARS: usize = 80;
+
+/// Independent sets of highlights, setting the highlights of a layer keeps the other layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverlayLayer {
+    ActiveElement,
+    Hovered,
+    TabOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelPosition {
+    /// on top of the frame, or inside of it at the top of the screen
+    Above,
+    /// at the top left corner inside the frame
+    Inside,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    /// screen coordinates of the highlighted area
+    pub rect: RECT,
+    pub color: [u8; 3],
+    /// width of the frame drawn inside of `rect`, 0 to show the label only
+    pub width: i32,
+    pub label: Option<String>,
+    pub label_position: LabelPosition,
+    /// the highlight fades out over this time and is then removed, None to keep it
+    pub fade: Option<Duration>,
+}
+
+enum OverlayCommand {
+    Set(OverlayLayer, Vec<Highlight>),
+}
+
+/// Handle of the overlay thread, the windows are removed when it is dropped
+pub struct Overlay {
+    tx: Sender<OverlayCommand>,
+    thread_id: u32,
+    shown: UIHashMap<OverlayLayer, Vec<Highlight>>,
+}
+
+impl Overlay {
+    pub fn start() -> Self {
+        let (tx, rx): (Sender<OverlayCommand>, Receiver<OverlayCommand>) = channel();
+        let (tx_id, rx_id): (Sender<u32>, Receiver<u32>) = channel();
+
+        thread::spawn(move || {
+            // the message queue has to exist before the thread id is handed out,
+            // otherwise the first wake up message could get lost
+            let mut msg = MSG::default();
+            unsafe {
+                let _res = PeekMessageW(&mut msg, HWND(std::ptr::null_mut()), WM_USER, WM_USER, PM_NOREMOVE);
+            }
+            tx_id.send(unsafe { GetCurrentThreadId() }).unwrap();
+            run_overlay_thread(rx);
+        });
+
+        let thread_id = rx_id.recv().unwrap();
+        Overlay { tx, thread_id, shown: UIHashMap::default() }
+    }
+
+    /// Replaces the highlights of the layer, nothing happens if they are the ones shown already,
+    /// so calling this in every frame neither flickers nor restarts fading highlights
+    pub fn set(&mut self, layer: OverlayLayer, highlights: Vec<Highlight>) {
+        let shown = self.shown.get(&layer).map(|shown| shown.as_slice()).unwrap_or(&[]);
+        if shown == highlights.as_slice() {
+            return;
+        }
+        self.shown.insert(layer, highlights.clone());
+        if self.tx.send(OverlayCommand::Set(layer, highlights)).is_ok() {
+            // wake up the message loop of the overlay thread
+            let _res = unsafe { PostThreadMessageW(self.thread_id, WM_APP, WPARAM(0), LPARAM(0)) };
+        }
+    }
+
+    pub fn clear(&mut self, layer: OverlayLayer) {
+        self.set(layer, Vec::new());
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        // end the message loop of the overlay thread, which destroys its windows
+        let _res = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
+    }
+}
+
+/// A highlight shown in its own window, the frame and the label are in client coordinates
+struct OverlayWindow {
+    hwnd: HWND,
+    layer: OverlayLayer,
+    highlight: Highlight,
+    frame: RECT,
+    label: Option<RECT>,
+    shown_at: Instant,
+}
+
+thread_local! {
+    // the windows of the overlay thread, the window procedure paints them from here
+    static OVERLAY_WINDOWS: RefCell<Vec<OverlayWindow>> = const { RefCell::new(Vec::new()) };
+}
+
+fn run_overlay_thread(rx: Receiver<OverlayCommand>) {
+    let class_name: Vec<u16> = OVERLAY_CLASS_NAME.encode_utf16().chain(std::iter::once(0)).collect();
+    let instance: HINSTANCE = unsafe { GetModuleHandleW(PCWSTR::null()) }.map(HINSTANCE::from).unwrap_or_default();
+    let class = WNDCLASSEXW {
+        cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+        lpfnWndProc: Some(overlay_window_proc),
+        hInstance: instance,
+        lpszClassName: PCWSTR(class_name.as_ptr()),
+        ..Default::default()
+    };
+    if unsafe { RegisterClassExW(&class) } == 0 {
+        log::warn!("failed to register the overlay window class, highlights are not shown");
+        return;
+    }
+
+    let mut fade_timer: usize = 0;
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, HWND(std::ptr::null_mut()), 0, 0) }.as_bool() {
+        // thread messages have no window
+        if msg.hwnd.0.is_null() {
+            match msg.message {
+                WM_APP => {
+                    for command in rx.try_iter() {
+                        match command {
+                            OverlayCommand::Set(layer, highlights) => set_layer(layer, highlights, instance, &class_name),
+                        }
+                    }
+                },
+                WM_TIMER => fade_windows(),
+                _ => (),
+            }
+
+            // the timer only runs while there are fading highlights
+            let fading = OVERLAY_WINDOWS.with_borrow(|windows| windows.iter().any(|window| window.highlight.fade.is_some()));
+            if fading && fade_timer == 0 {
+                fade_timer = unsafe { SetTimer(HWND(std::ptr::null_mut()), 0, FADE_INTERVAL_MS, None) };
+            } else if !fading && fade_timer != 0 {
+                let _res = unsafe { KillTimer(HWND(std::ptr::null_mut()), fade_timer) };
+                fade_timer = 0;
+            }
+            continue;
+        }
+        unsafe {
+            let _res = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+
+    let windows: Vec<HWND> = OVERLAY_WINDOWS.with_borrow_mut(|windows| windows.drain(..).map(|window| window.hwnd).collect());
+    for hwnd in windows {
+        let _res = unsafe { DestroyWindow(hwnd) };
+    }
+}
+
+fn set_layer(layer: OverlayLayer, highlights: Vec<Highlight>, instance: HINSTANCE, class_name: &[u16]) {
+    // the borrow is released before calling into windows, which may send messages to the window procedure
+    let removed: Vec<HWND> = OVERLAY_WINDOWS.with_borrow_mut(|windows| {
+        let (removed, kept): (Vec<OverlayWindow>, Vec<OverlayWindow>) = windows.drain(..).partition(|window| window.layer == layer);
+        *windows = kept;
+        removed.into_iter().map(|window| window.hwnd).collect()
+    });
+    for hwnd in removed {
+        let _res = unsafe { DestroyWindow(hwnd) };
+    }
+
+    let screen_top = unsafe { GetSystemMetrics(SM_YVIRTUALSCREEN) };
+    for highlight in highlights {
+        let label_size = highlight.label.as_deref().map(label_size);
+        let (bounds, frame, label) = layout(&highlight, label_size, screen_top);
+        let hwnd = match create_overlay_window(&bounds, instance, class_name) {
+            Ok(hwnd) => hwnd,
+            Err(e) => {
+                log::debug!("failed to create an overlay window: {}", e);
+                continue;
+            },
+        };
+        OVERLAY_WINDOWS.with_borrow_mut(|windows| windows.push(OverlayWindow { hwnd, layer, highlight, frame, label, shown_at: Instant::now() }));
+        // painted as soon as the window is shown
+        unsafe {
+            let _res = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+        }
+    }
+}
+
+fn create_overlay_window(bounds: &RECT, instance: HINSTANCE, class_name: &[u16]) -> windows::core::Result<HWND> {
+    unsafe {
+        let hwnd = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            PCWSTR(class_name.as_ptr()),
+            w!(""),
+            WS_POPUP,
+            bounds.left,
+            bounds.top,
+            bounds.right - bounds.left,
+            bounds.bottom - bounds.top,
+            HWND(std::ptr::null_mut()),
+            HMENU(std::ptr::null_mut()),
+            instance,
+            None,
+        )?;
+        SetLayeredWindowAttributes(hwnd, colorref(TRANSPARENT_KEY), 255, LWA_COLORKEY | LWA_ALPHA)?;
+        // keep the highlights out of the screenshots taken with captures
+        let _res = SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE);
+        Ok(hwnd)
+    }
+}
+
+/// The bounds of the window on the screen and the frame and the label in client coordinates
+fn layout(highlight: &Highlight, label_size: Option<SIZE>, screen_top: i32) -> (RECT, RECT, Option<RECT>) {
+    let rect = highlight.rect;
+    let label = label_size.map(|size| {
+        let above = highlight.label_position == LabelPosition::Above && rect.top - size.cy >= screen_top;
+        let top = if above { rect.top - size.cy } else { rect.top };
+        RECT { left: rect.left, top, right: rect.left + size.cx, bottom: top + size.cy }
+    });
+
+    let bounds = match &label {
+        // without a frame the window only needs to cover the label
+        Some(label) if highlight.width <= 0 => *label,
+        Some(label) => RECT {
+            left: rect.left.min(label.left),
+            top: rect.top.min(label.top),
+            right: rect.right.max(label.right),
+            bottom: rect.bottom.max(label.bottom),
+        },
+        None => rect,
+    };
+    let to_client = |r: &RECT| RECT { left: r.left - bounds.left, top: r.top - bounds.top, right: r.right - bounds.left, bottom: r.bottom - bounds.top };
+    (bounds, to_client(&rect), label.as_ref().map(to_client))
+}
+
+fn label_text(label: &str) -> Vec<u16> {
+    let mut text: String = label.chars().take(MAX_LABEL_CHARS).collect();
+    if label.chars().count() > MAX_LABEL_CHARS {
+        text.push('…');
+    }
+    text.encode_utf16().collect()
+}
+
+// size of the label including the padding, measured in the font it is drawn with
+fn label_size(label: &str) -> SIZE {
+    let text = label_text(label);
+    let mut size = SIZE::default();
+    unsafe {
+        let hdc = GetDC(HWND(std::ptr::null_mut()));
+        let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+        let _res = GetTextExtentPoint32W(hdc, &text, &mut size);
+        SelectObject(hdc, old_font);
+        ReleaseDC(HWND(std::ptr::null_mut()), hdc);
+    }
+    SIZE { cx: size.cx + 2 * LABEL_PADDING_X, cy: size.cy + 2 * LABEL_PADDING_Y }
+}
+
+// fading highlights become more transparent until they are removed
+fn fade_windows() {
+    let mut updates: Vec<(HWND, Option<u8>)> = Vec::new();
+    OVERLAY_WINDOWS.with_borrow_mut(|windows| {
+        windows.retain(|window| {
+            let Some(fade) = window.highlight.fade else {
+                return true;
+            };
+            let elapsed = window.shown_at.elapsed();
+            if elapsed >= fade {
+                updates.push((window.hwnd, None));
+                return false;
+            }
+            let alpha = 255.0 * (1.0 - elapsed.as_secs_f32() / fade.as_secs_f32().max(f32::EPSILON));
+            updates.push((window.hwnd, Some(alpha as u8)));
+            true
+        });
+    });
+
+    for (hwnd, alpha) in updates {
+        unsafe {
+            match alpha {
+                Some(alpha) => { let _res = SetLayeredWindowAttributes(hwnd, colorref(TRANSPARENT_KEY), alpha, LWA_COLORKEY | LWA_ALPHA); },
+                None => { let _res = DestroyWindow(hwnd); },
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn overlay_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_PAINT => {
+            paint(hwnd);
+            LRESULT(0)
+        },
+        // the overlay never takes any mouse input
+        WM_NCHITTEST => LRESULT(HTTRANSPARENT as isize),
+        _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    }
+}
+
+fn paint(hwnd: HWND) {
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = unsafe { BeginPaint(hwnd, &mut ps) };
+
+    OVERLAY_WINDOWS.with_borrow(|windows| {
+        let Some(window) = windows.iter().find(|window| window.hwnd == hwnd) else {
+            return;
+        };
+        let highlight = &window.highlight;
+        let color = if highlight.color == TRANSPARENT_KEY { [0, 0, 0] } else { highlight.color };
+
+        unsafe {
+            // everything not covered by the frame or the label is transparent
+            let key_brush = CreateSolidBrush(colorref(TRANSPARENT_KEY));
+            FillRect(hdc, &ps.rcPaint, key_brush);
+            let _del_res = DeleteObject(key_brush);
+
+            let brush = CreateSolidBrush(colorref(color));
+            let frame = window.frame;
+            if highlight.width > 0 {
+                let width = highlight.width;
+                let bands = [
+                    RECT { left: frame.left, top: frame.top, right: frame.right, bottom: (frame.top + width).min(frame.bottom) },
+                    RECT { left: frame.left, top: (frame.bottom - width).max(frame.top), right: frame.right, bottom: frame.bottom },
+                    RECT { left: frame.left, top: frame.top, right: (frame.left + width).min(frame.right), bottom: frame.bottom },
+                    RECT { left: (frame.right - width).max(frame.left), top: frame.top, right: frame.right, bottom: frame.bottom },
+                ];
+                for band in &bands {
+                    FillRect(hdc, band, brush);
+                }
+            }
+
+            if let (Some(label), Some(text)) = (&window.label, &highlight.label) {
+                FillRect(hdc, label, brush);
+                let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+                SetBkMode(hdc, TRANSPARENT);
+                SetTextColor(hdc, colorref(text_color(color)));
+                let _res = TextOutW(hdc, label.left + LABEL_PADDING_X, label.top + LABEL_PADDING_Y, &label_text(text));
+                SelectObject(hdc, old_font);
+            }
+            let _del_res = DeleteObject(brush);
+        }
+    });
+
+    unsafe {
+        let _res = EndPaint(hwnd, &ps);
+    }
+}
+
+// black or white, whichever is easier to read on the background
+fn text_color(background: [u8; 3]) -> [u8; 3] {
+    let luminance = 0.299 * background[0] as f32 + 0.587 * background[1] as f32 + 0.114 * background[2] as f32;
+    if luminance > 150.0 { [0, 0, 0] } else { [255, 255, 255] }
+}
+
+// COLORREF is the U32 little endian representation of the rgb value
+fn colorref(rgb: [u8; 3]) -> COLORREF {
+    COLORREF(rgb[0] as u32 | (rgb[1] as u32) << 8 | (rgb[2] as u32) << 16)
+}
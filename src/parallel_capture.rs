@@ -0,0 +1,356 @@
+//! Captures the subtrees of several elements concurrently, each on its own worker thread
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+
+use crate::{logging, UITreeMap};
+use crate::uiexplore::{format_element_label, CancelToken, CaptureProgress, UIElementProps};
+
+// interval in which the workers are checked for progress, timeouts and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Walks the elements of a tree, e.g. through ui automation cache requests
+pub trait ElementSource {
+    type Element;
+
+    fn element_props(&self, element: &Self::Element, level: usize, z_order: usize) -> UIElementProps;
+    /// Retrieves all children of an element at once
+    fn children(&self, element: &Self::Element) -> Vec<Self::Element>;
+}
+
+/// Creates an element source on a worker thread and locates the root of the subtree to capture,
+/// as ui automation instances and elements can't be shared between threads
+pub trait SourceFactory: Send + Sync + 'static {
+    type Source: ElementSource;
+
+    fn open(&self, ui_elem_props: &UIElementProps) -> Result<(Self::Source, <Self::Source as ElementSource>::Element), String>;
+}
+
+/// The root of a subtree to capture, its level and z_order are kept for all descendants
+#[derive(Debug, Clone)]
+pub struct SubtreeJob {
+    pub ui_elem_props: UIElementProps,
+    pub max_depth: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SubtreeResult {
+    Captured(UITreeMap<UIElementProps>),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolLimits {
+    pub max_workers: usize,
+    /// a subtree is skipped if no new element could be retrieved within this time
+    pub element_timeout: Option<Duration>,
+    /// subtrees not captured until then are skipped
+    pub deadline: Option<Instant>,
+}
+
+impl PoolLimits {
+    pub fn default_workers() -> usize {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+    }
+}
+
+struct RunningJob {
+    index: usize,
+    name: String,
+    nodes_visited: Arc<AtomicUsize>,
+    abort: Arc<AtomicBool>,
+    last_count: usize,
+    last_change: Instant,
+}
+
+/// Captures the subtrees on up to `max_workers` threads at the same time and passes each result
+/// with the index of its job to `on_finished` as soon as it is available.
+/// Returns false if the capture was cancelled.
+pub fn capture_subtrees<F, P, R>(factory: Arc<F>, jobs: Vec<SubtreeJob>, limits: PoolLimits, cancel: &CancelToken, mut on_progress: P, mut on_finished: R) -> bool
+where
+    F: SourceFactory,
+    P: FnMut(&CaptureProgress),
+    R: FnMut(usize, SubtreeResult),
+{
+    let (tx, rx): (Sender<(usize, Result<UITreeMap<UIElementProps>, String>)>, Receiver<_>) = channel();
+    let mut progress = CaptureProgress { windows_total: jobs.len(), ..Default::default() };
+    let mut queue: VecDeque<(usize, SubtreeJob)> = jobs.into_iter().enumerate().collect();
+    let mut running: Vec<RunningJob> = Vec::new();
+    // elements of all finished or abandoned subtrees
+    let mut nodes_done: usize = 0;
+
+    loop {
+        // start workers for the next jobs
+        while running.len() < limits.max_workers.max(1) {
+            let Some((index, job)) = queue.pop_front() else {
+                break;
+            };
+            if limits.deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                on_finished(index, SubtreeResult::Failed("Skipped: overall capture timeout exceeded".to_string()));
+                progress.windows_done += 1;
+                continue;
+            }
+            running.push(spawn_worker(factory.clone(), index, job, tx.clone()));
+        }
+
+        if running.is_empty() {
+            break;
+        }
+
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok((index, result)) => {
+                // results of abandoned workers arriving late are ignored
+                if let Some(pos) = running.iter().position(|job| job.index == index) {
+                    let job = running.remove(pos);
+                    nodes_done += job.nodes_visited.load(Ordering::Relaxed);
+                    on_finished(index, match result {
+                        Ok(subtree) => SubtreeResult::Captured(subtree),
+                        Err(msg) => {
+                            log::warn!("window '{}': capture failed: {}", job.name, msg);
+                            SubtreeResult::Failed(format!("Capture failed: {}", msg))
+                        },
+                    });
+                    progress.windows_done += 1;
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => (),
+            // can't happen as long as tx is alive
+            Err(RecvTimeoutError::Disconnected) => (),
+        }
+
+        if cancel.is_cancelled() {
+            for job in running.iter() {
+                job.abort.store(true, Ordering::Relaxed);
+            }
+            return false;
+        }
+
+        // abandon workers which stopped making progress or ran out of time,
+        // they stop as soon as their pending call returns
+        let now = Instant::now();
+        let mut pos = 0;
+        while pos < running.len() {
+            let job = &mut running[pos];
+            let count = job.nodes_visited.load(Ordering::Relaxed);
+            if count != job.last_count {
+                job.last_count = count;
+                job.last_change = now;
+            }
+            let failure = if let Some(timeout) = limits.element_timeout.filter(|&timeout| now.duration_since(job.last_change) > timeout) {
+                Some(format!("Skipped: application not responding (no element retrieved within {:?})", timeout))
+            } else if limits.deadline.is_some_and(|deadline| now > deadline) {
+                Some("Skipped: overall capture timeout exceeded".to_string())
+            } else {
+                None
+            };
+            if let Some(msg) = failure {
+                let job = running.remove(pos);
+                log::warn!("window '{}': {}", job.name, msg);
+                job.abort.store(true, Ordering::Relaxed);
+                nodes_done += job.nodes_visited.load(Ordering::Relaxed);
+                on_finished(job.index, SubtreeResult::Failed(msg));
+                progress.windows_done += 1;
+            } else {
+                pos += 1;
+            }
+        }
+
+        progress.nodes_visited = nodes_done + running.iter().map(|job| job.nodes_visited.load(Ordering::Relaxed)).sum::<usize>();
+        progress.current_window = running.first().map(|job| job.name.clone()).unwrap_or_default();
+        on_progress(&progress);
+    }
+
+    true
+}
+
+fn spawn_worker<F: SourceFactory>(factory: Arc<F>, index: usize, job: SubtreeJob, tx: Sender<(usize, Result<UITreeMap<UIElementProps>, String>)>) -> RunningJob {
+    let nodes_visited = Arc::new(AtomicUsize::new(0));
+    let abort = Arc::new(AtomicBool::new(false));
+    let name = job.ui_elem_props.name.clone();
+    let parent_spans = logging::current_spans();
+    {
+        let nodes_visited = nodes_visited.clone();
+        let abort = abort.clone();
+        thread::spawn(move || {
+            logging::inherit_spans(parent_spans);
+            let _span = logging::span("window", &format!("z_order={} name='{}'", job.ui_elem_props.z_order, job.ui_elem_props.name));
+            // a panicking worker must not leave the supervisor waiting for its result
+            let result = panic::catch_unwind(AssertUnwindSafe(|| capture_subtree(factory.as_ref(), job, &nodes_visited, &abort)))
+                .unwrap_or_else(|_| Err("worker thread panicked".to_string()));
+            let _res = tx.send((index, result));
+        });
+    }
+    RunningJob { index, name, nodes_visited, abort, last_count: 0, last_change: Instant::now() }
+}
+
+fn capture_subtree<F: SourceFactory>(factory: &F, job: SubtreeJob, nodes_visited: &AtomicUsize, abort: &AtomicBool) -> Result<UITreeMap<UIElementProps>, String> {
+    let (source, element) = factory.open(&job.ui_elem_props)?;
+
+    let level = job.ui_elem_props.level;
+    let z_order = job.ui_elem_props.z_order;
+    let mut subtree = UITreeMap::new(format_element_label(&job.ui_elem_props), job.ui_elem_props);
+    nodes_visited.fetch_add(1, Ordering::Relaxed);
+
+    let walk = Walk { source: &source, max_depth: job.max_depth, z_order, nodes_visited, abort };
+    walk.get_children(&mut subtree, 0, &element, level);
+    Ok(subtree)
+}
+
+struct Walk<'a, S: ElementSource> {
+    source: &'a S,
+    max_depth: Option<usize>,
+    z_order: usize,
+    nodes_visited: &'a AtomicUsize,
+    abort: &'a AtomicBool,
+}
+
+impl<S: ElementSource> Walk<'_, S> {
+    fn get_element(&self, tree: &mut UITreeMap<UIElementProps>, parent: usize, element: &S::Element, level: usize) {
+
+        if let Some(limit) = self.max_depth {
+            if level > limit {
+                return;
+            }
+        }
+        if self.abort.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let ui_elem_props = self.source.element_props(element, level, self.z_order);
+        let item = format_element_label(&ui_elem_props);
+        let index = tree.add_child(parent, item.as_str(), ui_elem_props);
+        self.nodes_visited.fetch_add(1, Ordering::Relaxed);
+
+        self.get_children(tree, index, element, level);
+    }
+
+    fn get_children(&self, tree: &mut UITreeMap<UIElementProps>, parent: usize, element: &S::Element, level: usize) {
+        // walking children now
+        for child in self.source.children(element) {
+            if self.abort.load(Ordering::Relaxed) {
+                return;
+            }
+            self.get_element(tree, parent, &child, level + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree;
+
+    // a synthetic tree of top level windows with a fixed number of children per element,
+    // retrieving the children is delayed like a cross process call, the properties
+    // of the children are part of the same call as with cache requests
+    struct SyntheticFactory {
+        children_per_element: usize,
+        depth: usize,
+        latency: Duration,
+    }
+
+    struct SyntheticSource {
+        children_per_element: usize,
+        depth: usize,
+        latency: Duration,
+    }
+
+    // the path of child positions from the top level window
+    type SyntheticElement = Vec<usize>;
+
+    impl SourceFactory for SyntheticFactory {
+        type Source = SyntheticSource;
+
+        fn open(&self, _ui_elem_props: &UIElementProps) -> Result<(SyntheticSource, SyntheticElement), String> {
+            let source = SyntheticSource { children_per_element: self.children_per_element, depth: self.depth, latency: self.latency };
+            Ok((source, Vec::new()))
+        }
+    }
+
+    impl ElementSource for SyntheticSource {
+        type Element = SyntheticElement;
+
+        fn element_props(&self, element: &SyntheticElement, level: usize, z_order: usize) -> UIElementProps {
+            synthetic_props(format!("element {:?}", element), level, z_order)
+        }
+
+        fn children(&self, element: &SyntheticElement) -> Vec<SyntheticElement> {
+            thread::sleep(self.latency);
+            if element.len() >= self.depth {
+                return Vec::new();
+            }
+            (0..self.children_per_element).map(|pos| [element.as_slice(), &[pos]].concat()).collect()
+        }
+    }
+
+    fn synthetic_props(name: String, level: usize, z_order: usize) -> UIElementProps {
+        UIElementProps {
+            runtime_id: vec![z_order as i32, level as i32],
+            level,
+            z_order,
+            ..test_tree::element("Pane", &name)
+        }
+    }
+
+    // returns the time taken and the labels of the captured elements per window
+    fn run(max_workers: usize, windows: usize, latency: Duration) -> (Duration, Vec<Vec<String>>) {
+        let factory = Arc::new(SyntheticFactory { children_per_element: 3, depth: 3, latency });
+        let jobs: Vec<SubtreeJob> = (0..windows)
+            .map(|z_order| SubtreeJob { ui_elem_props: synthetic_props(format!("window {}", z_order), 1, z_order), max_depth: None })
+            .collect();
+        let limits = PoolLimits { max_workers, element_timeout: None, deadline: None };
+
+        let mut results: Vec<Option<SubtreeResult>> = jobs.iter().map(|_| None).collect();
+        let start = Instant::now();
+        capture_subtrees(factory, jobs, limits, &CancelToken::new(), |_| (), |index, result| results[index] = Some(result));
+        let elapsed = start.elapsed();
+
+        // the results are assembled in the order of the jobs
+        let labels = results.into_iter().flatten().map(|result| match result {
+            SubtreeResult::Captured(subtree) => {
+                let mut labels: Vec<String> = Vec::new();
+                subtree.for_each(|_, ui_elem_props| labels.push(format!("{} {}", ui_elem_props.z_order, ui_elem_props.name)));
+                labels
+            },
+            SubtreeResult::Failed(msg) => vec![msg],
+        }).collect();
+        (elapsed, labels)
+    }
+
+    #[test]
+    fn parallel_capture_matches_serial_capture() {
+        const WINDOWS: usize = 8;
+
+        let (_, serial_labels) = run(1, WINDOWS, Duration::from_millis(1));
+        let (_, parallel_labels) = run(4, WINDOWS, Duration::from_millis(1));
+
+        // the assembled result doesn't depend on the order in which the workers finish
+        assert_eq!(serial_labels.len(), WINDOWS);
+        assert!(serial_labels.iter().all(|labels| labels.len() == 1 + 3 + 9 + 27));
+        assert_eq!(serial_labels, parallel_labels);
+    }
+
+    // benchmark of the speed-up with cross process latency, the timing depends on the machine,
+    // so it is only reported. Run with: cargo test --release parallel_capture -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn parallel_capture_speed_up() {
+        const WINDOWS: usize = 16;
+        let latency = Duration::from_millis(5);
+
+        let workers = PoolLimits::default_workers();
+        let (serial_time, serial_labels) = run(1, WINDOWS, latency);
+        let (parallel_time, parallel_labels) = run(workers, WINDOWS, latency);
+        assert_eq!(serial_labels, parallel_labels);
+
+        println!("{} windows, 1 worker: {:?}", WINDOWS, serial_time);
+        println!("{} windows, {} workers: {:?}", WINDOWS, workers, parallel_time);
+        println!("speed-up: {:.1}x", serial_time.as_secs_f64() / parallel_time.as_secs_f64());
+    }
+}
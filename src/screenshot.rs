@@ -0,0 +1,467 @@
+//! Screenshot of the captured area taken at the moment of the tree walk, so the bounding
+//! rectangles can be related to the pixels even after the target has changed or closed
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use eframe::egui;
+use uiautomation::types::Rect;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{SetWindowDisplayAffinity, WDA_EXCLUDEFROMCAPTURE, WDA_NONE};
+use windows::Win32::Graphics::Gdi::{BI_RGB, BITMAPINFO, BITMAPINFOHEADER, CAPTUREBLT, DIB_RGB_COLORS, SRCCOPY, BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits, ReleaseDC, SelectObject};
+
+use crate::UITree;
+
+/// The pixels of a screen area in rgba order, row by row. `left` and `top` are the
+/// screen coordinates of the top left pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screenshot {
+    pub left: i32,
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// An area of the image in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Screenshot {
+    pub fn new(left: i32, top: i32, width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width * height * 4, "the pixels don't match the size of the screenshot");
+        Screenshot { left, top, width, height, pixels }
+    }
+
+    /// Copies the given screen area from the screen device context
+    pub fn capture(rect: &Rect) -> Result<Screenshot, String> {
+        let width = rect.get_right() - rect.get_left();
+        let height = rect.get_bottom() - rect.get_top();
+        if width <= 0 || height <= 0 {
+            return Err(format!("nothing to capture in the empty area {}", rect));
+        }
+
+        let mut pixels: Vec<u8> = vec![0; width as usize * height as usize * 4];
+        unsafe {
+            let screen = GetDC(HWND(std::ptr::null_mut()));
+            if screen.is_invalid() {
+                return Err("failed to get the screen device context".to_string());
+            }
+            let hdc = CreateCompatibleDC(screen);
+            let bitmap = CreateCompatibleBitmap(screen, width, height);
+            let old_bitmap = SelectObject(hdc, bitmap);
+            // CAPTUREBLT includes layered windows
+            let copied = BitBlt(hdc, 0, 0, width, height, screen, rect.get_left(), rect.get_top(), SRCCOPY | CAPTUREBLT);
+            SelectObject(hdc, old_bitmap);
+
+            // a negative height returns the rows from top to bottom
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width,
+                    biHeight: -height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let lines = match copied {
+                Ok(()) => GetDIBits(hdc, bitmap, 0, height as u32, Some(pixels.as_mut_ptr() as *mut core::ffi::c_void), &mut info, DIB_RGB_COLORS),
+                Err(_) => 0,
+            };
+
+            let _del_res = DeleteObject(bitmap);
+            let _del_res = DeleteDC(hdc);
+            ReleaseDC(HWND(std::ptr::null_mut()), screen);
+
+            copied.map_err(|e| format!("failed to copy the screen: {}", e))?;
+            if lines != height {
+                return Err("failed to read the pixels of the screen".to_string());
+            }
+        }
+
+        // the bitmap is in bgra order and the alpha channel of the screen is undefined
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 255;
+        }
+        Ok(Screenshot::new(rect.get_left(), rect.get_top(), width as usize, height as usize, pixels))
+    }
+
+    pub fn screen_rect(&self) -> Rect {
+        Rect::new(self.left, self.top, self.left + self.width as i32, self.top + self.height as i32)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * self.width + x) * 4;
+        [self.pixels[offset], self.pixels[offset + 1], self.pixels[offset + 2], self.pixels[offset + 3]]
+    }
+
+    /// The part of a screen rectangle that is inside the image, None if nothing is
+    pub fn image_rect(&self, rect: &Rect) -> Option<PixelRect> {
+        let x0 = (rect.get_left() as i64 - self.left as i64).max(0);
+        let y0 = (rect.get_top() as i64 - self.top as i64).max(0);
+        let x1 = (rect.get_right() as i64 - self.left as i64).min(self.width as i64);
+        let y1 = (rect.get_bottom() as i64 - self.top as i64).min(self.height as i64);
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+        Some(PixelRect { x: x0 as usize, y: y0 as usize, width: (x1 - x0) as usize, height: (y1 - y0) as usize })
+    }
+
+    /// The part of the image showing the given screen rectangle
+    pub fn crop(&self, rect: &Rect) -> Option<Screenshot> {
+        let area = self.image_rect(rect)?;
+        let mut pixels: Vec<u8> = Vec::with_capacity(area.width * area.height * 4);
+        for y in area.y..area.y + area.height {
+            let start = (y * self.width + area.x) * 4;
+            pixels.extend_from_slice(&self.pixels[start..start + area.width * 4]);
+        }
+        Some(Screenshot::new(self.left + area.x as i32, self.top + area.y as i32, area.width, area.height, pixels))
+    }
+
+    /// Draws the outline of a screen rectangle into the image, `outline_width` pixels
+    /// wide on the inside of the rectangle like the highlight frame on the screen
+    pub fn draw_frame(&mut self, rect: &Rect, outline_width: i32, rgba: [u8; 4]) {
+        let (left, top, right, bottom) = (rect.get_left(), rect.get_top(), rect.get_right(), rect.get_bottom());
+        let outline_width = outline_width.max(1);
+        self.fill(&Rect::new(left, top, right, (top + outline_width).min(bottom)), rgba);
+        self.fill(&Rect::new(left, (bottom - outline_width).max(top), right, bottom), rgba);
+        self.fill(&Rect::new(left, top, (left + outline_width).min(right), bottom), rgba);
+        self.fill(&Rect::new((right - outline_width).max(left), top, right, bottom), rgba);
+    }
+
+    fn fill(&mut self, rect: &Rect, rgba: [u8; 4]) {
+        let Some(area) = self.image_rect(rect) else {
+            return;
+        };
+        for y in area.y..area.y + area.height {
+            let start = (y * self.width + area.x) * 4;
+            for pixel in self.pixels[start..start + area.width * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&rgba);
+            }
+        }
+    }
+
+    /// Maps a screen rectangle to the area `view` in which the part `uv` (in texture
+    /// coordinates from 0 to 1) of the image is shown
+    pub fn to_view(&self, rect: &Rect, uv: egui::Rect, view: egui::Rect) -> egui::Rect {
+        let map = |x: i32, y: i32| {
+            let u = (x - self.left) as f32 / self.width as f32;
+            let v = (y - self.top) as f32 / self.height as f32;
+            egui::pos2(
+                view.min.x + (u - uv.min.x) / uv.width() * view.width(),
+                view.min.y + (v - uv.min.y) / uv.height() * view.height(),
+            )
+        };
+        egui::Rect::from_min_max(map(rect.get_left(), rect.get_top()), map(rect.get_right(), rect.get_bottom()))
+    }
+
+    /// The part of the image around a screen rectangle in texture coordinates,
+    /// the whole image if the rectangle is outside of it
+    pub fn zoom_uv(&self, rect: &Rect, margin: i32) -> egui::Rect {
+        let expanded = Rect::new(rect.get_left() - margin, rect.get_top() - margin, rect.get_right() + margin, rect.get_bottom() + margin);
+        match self.image_rect(&expanded) {
+            Some(area) => egui::Rect::from_min_max(
+                egui::pos2(area.x as f32 / self.width as f32, area.y as f32 / self.height as f32),
+                egui::pos2((area.x + area.width) as f32 / self.width as f32, (area.y + area.height) as f32 / self.height as f32),
+            ),
+            None => egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+        }
+    }
+
+    /// The image as a texture with at most `max_side` pixels per side, larger images
+    /// (e.g. of several monitors) are scaled down by skipping pixels
+    pub fn to_color_image(&self, max_side: usize) -> egui::ColorImage {
+        let step = self.width.max(self.height).div_ceil(max_side.max(1)).max(1);
+        if step == 1 {
+            return egui::ColorImage::from_rgba_unmultiplied([self.width, self.height], &self.pixels);
+        }
+        let width = self.width.div_ceil(step);
+        let height = self.height.div_ceil(step);
+        let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 4);
+        for y in (0..self.height).step_by(step) {
+            for x in (0..self.width).step_by(step) {
+                pixels.extend_from_slice(&self.pixel(x, y));
+            }
+        }
+        egui::ColorImage::from_rgba_unmultiplied([width, height], &pixels)
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut png: Vec<u8> = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer.write_image_data(&self.pixels).map_err(|e| e.to_string())?;
+        writer.finish().map_err(|e| e.to_string())?;
+        Ok(png)
+    }
+}
+
+/// Keeps a window out of the screenshots, otherwise the explorer covers the elements of a 
+/// refreshed capture. The window is then also missing in screen recordings and shared screens.
+pub fn exclude_from_screenshots(hwnd: isize, exclude: bool) -> Result<(), String> {
+    let affinity = if exclude { WDA_EXCLUDEFROMCAPTURE } else { WDA_NONE };
+    unsafe { SetWindowDisplayAffinity(HWND(hwnd as *mut std::ffi::c_void), affinity) }.map_err(|e| e.to_string())
+}
+
+/// The texture of the screenshot shown last, uploaded again when another screenshot is shown
+pub struct ScreenshotTexture {
+    texture: Option<(Arc<Screenshot>, egui::TextureHandle)>,
+}
+
+impl ScreenshotTexture {
+    pub fn new() -> Self {
+        ScreenshotTexture { texture: None }
+    }
+
+    pub fn get(&mut self, ctx: &egui::Context, screenshot: &Arc<Screenshot>) -> &egui::TextureHandle {
+        if !self.texture.as_ref().is_some_and(|(shown, _)| Arc::ptr_eq(shown, screenshot)) {
+            let max_side = ctx.input(|i| i.max_texture_side);
+            let texture = ctx.load_texture("snapshot_screenshot", screenshot.to_color_image(max_side), egui::TextureOptions::LINEAR);
+            self.texture = Some((screenshot.clone(), texture));
+        }
+        &self.texture.as_ref().unwrap().1
+    }
+
+    pub fn clear(&mut self) {
+        self.texture = None;
+    }
+}
+
+// pixels around the active element when zooming in
+const ZOOM_MARGIN: i32 = 40;
+// small elements are shown at most this many times their size
+const MAX_ZOOM: f32 = 4.0;
+
+pub struct ScreenshotPanel {
+    zoom_to_element: bool,
+    texture: ScreenshotTexture,
+}
+
+impl ScreenshotPanel {
+    pub fn new() -> Self {
+        ScreenshotPanel { zoom_to_element: false, texture: ScreenshotTexture::new() }
+    }
+
+    /// Shows the screenshot of the snapshot with the frame of the active element drawn over it,
+    /// `path` is the file the screenshot is saved to. Returns a status message.
+    pub fn ui(&mut self, ui: &mut egui::Ui, ui_tree: &UITree, active_ui_element: Option<usize>, path: &mut String, frame_color: [u8; 3], frame_width: i32) -> Option<String> {
+        let mut status: Option<String> = None;
+
+        let Some(screenshot) = ui_tree.get_screenshot() else {
+            ui.label("No screenshot was taken with this snapshot");
+            self.texture.clear();
+            return None;
+        };
+        let active_rect = active_ui_element.map(|index| ui_tree.node(index).1.bounding_rect);
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{}×{} at ({}, {}), taken {}", screenshot.width, screenshot.height, screenshot.left, screenshot.top, ui_tree.get_captured_at().format("%H:%M:%S")));
+            ui.checkbox(&mut self.zoom_to_element, "Zoom to Element");
+        });
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(path);
+            if ui.button("💾 Save with Frame").clicked() {
+                let mut framed = screenshot.as_ref().clone();
+                if let Some(rect) = &active_rect {
+                    framed.draw_frame(rect, frame_width, [frame_color[0], frame_color[1], frame_color[2], 255]);
+                }
+                status = Some(match framed.to_png().and_then(|png| fs::write(Path::new(path.as_str()), png).map_err(|e| e.to_string())) {
+                    Ok(()) => format!("Saved the screenshot to {}", path),
+                    Err(msg) => format!("Failed to save the screenshot: {}", msg),
+                });
+            }
+        });
+        if let Some(rect) = &active_rect {
+            if screenshot.image_rect(rect).is_none() {
+                ui.weak("The active element is outside of the screenshot");
+            }
+        }
+        ui.separator();
+
+        let texture = self.texture.get(ui.ctx(), screenshot);
+        let full = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let uv = match (&active_rect, self.zoom_to_element) {
+            (Some(rect), true) => screenshot.zoom_uv(rect, ZOOM_MARGIN),
+            _ => full,
+        };
+
+        // scaled to fit the available space, keeping the aspect ratio
+        let shown = egui::vec2(uv.width() * screenshot.width as f32, uv.height() * screenshot.height as f32);
+        let available = ui.available_size();
+        let scale = (available.x / shown.x).min(available.y / shown.y).min(MAX_ZOOM).max(0.01);
+        let (response, painter) = ui.allocate_painter(shown * scale, egui::Sense::hover());
+        let painter = painter.with_clip_rect(response.rect);
+        painter.image(texture.id(), response.rect, uv, egui::Color32::WHITE);
+        if let Some(rect) = &active_rect {
+            let color = egui::Color32::from_rgb(frame_color[0], frame_color[1], frame_color[2]);
+            painter.rect_stroke(screenshot.to_view(rect, uv, response.rect), 0.0, egui::Stroke::new(frame_width.max(1) as f32, color));
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a synthetic image where each pixel encodes its own position
+    fn synthetic(left: i32, top: i32, width: usize, height: usize) -> Screenshot {
+        let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend_from_slice(&[x as u8, y as u8, 0, 255]);
+            }
+        }
+        Screenshot::new(left, top, width, height, pixels)
+    }
+
+    #[test]
+    fn image_rect_is_relative_to_the_screenshot() {
+        let screenshot = synthetic(100, 50, 40, 30);
+        assert_eq!(screenshot.image_rect(&Rect::new(110, 60, 120, 65)), Some(PixelRect { x: 10, y: 10, width: 10, height: 5 }));
+    }
+
+    #[test]
+    fn image_rect_is_clipped_to_the_screenshot() {
+        let screenshot = synthetic(100, 50, 40, 30);
+        assert_eq!(screenshot.image_rect(&Rect::new(90, 40, 110, 60)), Some(PixelRect { x: 0, y: 0, width: 10, height: 10 }));
+        assert_eq!(screenshot.image_rect(&Rect::new(130, 70, 200, 200)), Some(PixelRect { x: 30, y: 20, width: 10, height: 10 }));
+        assert_eq!(screenshot.image_rect(&Rect::new(0, 0, 1000, 1000)), Some(PixelRect { x: 0, y: 0, width: 40, height: 30 }));
+    }
+
+    #[test]
+    fn image_rect_outside_or_empty_is_none() {
+        let screenshot = synthetic(100, 50, 40, 30);
+        assert_eq!(screenshot.image_rect(&Rect::new(0, 0, 100, 50)), None);
+        assert_eq!(screenshot.image_rect(&Rect::new(140, 50, 150, 80)), None);
+        assert_eq!(screenshot.image_rect(&Rect::new(110, 60, 110, 70)), None);
+        // minimized windows are moved far off the screen
+        assert_eq!(screenshot.image_rect(&Rect::new(-32000, -32000, -31840, -31972)), None);
+    }
+
+    #[test]
+    fn crop_copies_the_pixels_of_the_area() {
+        let screenshot = synthetic(-20, 10, 40, 30);
+        let cropped = screenshot.crop(&Rect::new(-10, 15, 0, 25)).unwrap();
+        assert_eq!((cropped.left, cropped.top, cropped.width, cropped.height), (-10, 15, 10, 10));
+        assert_eq!(cropped.pixel(0, 0), [10, 5, 0, 255]);
+        assert_eq!(cropped.pixel(9, 9), [19, 14, 0, 255]);
+    }
+
+    #[test]
+    fn crop_is_clipped_to_the_screenshot() {
+        let screenshot = synthetic(0, 0, 40, 30);
+        let cropped = screenshot.crop(&Rect::new(35, -5, 60, 3)).unwrap();
+        assert_eq!((cropped.left, cropped.top, cropped.width, cropped.height), (35, 0, 5, 3));
+        assert_eq!(cropped.pixel(4, 2), [39, 2, 0, 255]);
+        assert!(screenshot.crop(&Rect::new(50, 50, 60, 60)).is_none());
+    }
+
+    #[test]
+    fn frame_is_drawn_inside_the_rectangle() {
+        let mut screenshot = synthetic(100, 100, 20, 20);
+        let red = [255, 0, 0, 255];
+        screenshot.draw_frame(&Rect::new(102, 104, 112, 114), 2, red);
+        // the outline
+        assert_eq!(screenshot.pixel(2, 4), red);
+        assert_eq!(screenshot.pixel(3, 5), red);
+        assert_eq!(screenshot.pixel(11, 13), red);
+        assert_eq!(screenshot.pixel(6, 12), red);
+        // inside and outside of the outline
+        assert_eq!(screenshot.pixel(4, 6), [4, 6, 0, 255]);
+        assert_eq!(screenshot.pixel(9, 11), [9, 11, 0, 255]);
+        assert_eq!(screenshot.pixel(1, 4), [1, 4, 0, 255]);
+        assert_eq!(screenshot.pixel(12, 14), [12, 14, 0, 255]);
+    }
+
+    #[test]
+    fn frame_partly_outside_is_clipped() {
+        let mut screenshot = synthetic(0, 0, 10, 10);
+        let red = [255, 0, 0, 255];
+        screenshot.draw_frame(&Rect::new(-5, -5, 5, 5), 1, red);
+        assert_eq!(screenshot.pixel(4, 0), red);
+        assert_eq!(screenshot.pixel(0, 4), red);
+        assert_eq!(screenshot.pixel(3, 3), [3, 3, 0, 255]);
+        assert_eq!(screenshot.pixel(5, 5), [5, 5, 0, 255]);
+        // entirely outside, nothing changes
+        let before = screenshot.clone();
+        screenshot.draw_frame(&Rect::new(20, 20, 30, 30), 3, red);
+        assert_eq!(screenshot, before);
+    }
+
+    #[test]
+    fn frame_wider_than_the_rectangle_fills_it() {
+        let mut screenshot = synthetic(0, 0, 10, 10);
+        let red = [255, 0, 0, 255];
+        screenshot.draw_frame(&Rect::new(2, 2, 5, 5), 4, red);
+        assert_eq!(screenshot.pixel(3, 3), red);
+        assert_eq!(screenshot.pixel(5, 5), [5, 5, 0, 255]);
+    }
+
+    #[test]
+    fn to_view_maps_the_whole_image() {
+        let screenshot = synthetic(100, 200, 400, 300);
+        let full = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let view = egui::Rect::from_min_size(egui::pos2(10.0, 20.0), egui::vec2(200.0, 150.0));
+        let mapped = screenshot.to_view(&Rect::new(200, 260, 300, 320), full, view);
+        assert_eq!(mapped, egui::Rect::from_min_max(egui::pos2(60.0, 50.0), egui::pos2(110.0, 80.0)));
+    }
+
+    #[test]
+    fn to_view_maps_a_zoomed_part() {
+        let screenshot = synthetic(0, 0, 400, 200);
+        // the right half of the image shown at twice its size
+        let uv = egui::Rect::from_min_max(egui::pos2(0.5, 0.0), egui::pos2(1.0, 1.0));
+        let view = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(400.0, 400.0));
+        let mapped = screenshot.to_view(&Rect::new(250, 50, 300, 100), uv, view);
+        assert_eq!(mapped, egui::Rect::from_min_max(egui::pos2(100.0, 100.0), egui::pos2(200.0, 200.0)));
+    }
+
+    #[test]
+    fn zoom_uv_adds_the_margin_and_stays_inside_the_image() {
+        let screenshot = synthetic(0, 0, 200, 100);
+        let uv = screenshot.zoom_uv(&Rect::new(50, 40, 100, 60), 10);
+        assert_eq!(uv, egui::Rect::from_min_max(egui::pos2(0.2, 0.3), egui::pos2(0.55, 0.7)));
+        let uv = screenshot.zoom_uv(&Rect::new(0, 0, 20, 20), 10);
+        assert_eq!(uv, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(0.15, 0.3)));
+        let uv = screenshot.zoom_uv(&Rect::new(500, 500, 600, 600), 10);
+        assert_eq!(uv, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)));
+    }
+
+    #[test]
+    fn color_image_is_scaled_down_to_the_max_side() {
+        let screenshot = synthetic(0, 0, 50, 20);
+        let image = screenshot.to_color_image(100);
+        assert_eq!(image.size, [50, 20]);
+        let image = screenshot.to_color_image(20);
+        assert_eq!(image.size, [17, 7]);
+        assert_eq!(image.pixels[1], egui::Color32::from_rgba_unmultiplied(3, 0, 0, 255));
+        assert_eq!(image.pixels[17], egui::Color32::from_rgba_unmultiplied(0, 3, 0, 255));
+    }
+
+    #[test]
+    fn png_keeps_the_pixels() {
+        let screenshot = synthetic(0, 0, 7, 5);
+        let png = screenshot.to_png().unwrap();
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels: Vec<u8> = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (7, 5));
+        assert_eq!(&pixels[..info.buffer_size()], screenshot.pixels.as_slice());
+    }
+}
@@ -0,0 +1,148 @@
+//! User preferences, persisted in the eframe storage between sessions
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// key under which the settings are stored in the eframe storage
+pub const SETTINGS_KEY: &str = "uiexplore_settings";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub highlighting: bool,
+    pub recording: bool,
+    pub show_history: bool,
+    pub show_log: bool,
+    pub freeze_over_explorer: bool,
+    pub hover_delay_ms: u32,
+    pub highlight_color: [u8; 3],
+    pub highlight_width: i32,
+    pub highlight_labels: bool,
+    pub highlight_fade_ms: u32, // 0 = stays
+    pub tree_panel_min_width: f32,
+    pub tree_panel_max_width: f32,
+    pub capture_element_timeout_s: u32,
+    pub capture_overall_timeout_s: u32, // 0 = no limit
+    pub capture_worker_threads: usize,  // 0 = one per core
+    pub capture_screenshot: bool,
+    pub export_path: String,
+    pub screenshot_path: String,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            highlighting: false,
+            recording: false,
+            show_history: false,
+            show_log: false,
+            freeze_over_explorer: true,
+            hover_delay_ms: 0,
+            highlight_color: [0x2c, 0xff, 0x05],
+            highlight_width: 4,
+            highlight_labels: false,
+            highlight_fade_ms: 0,
+            tree_panel_min_width: 800.0,
+            tree_panel_max_width: 1400.0,
+            capture_element_timeout_s: 10,
+            capture_overall_timeout_s: 0,
+            capture_worker_threads: 0,
+            capture_screenshot: true,
+            export_path: "uiexplore_export.xml".to_string(),
+            screenshot_path: "uiexplore_screenshot.png".to_string(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Loads the settings from the eframe storage, falling back to the defaults
+    /// if there is no storage or nothing has been stored yet.
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SETTINGS_KEY, self);
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("settings_grid").num_columns(2).spacing([20.0, 6.0]).show(ui, |ui| {
+            ui.label("Show Highlight Rectangle:");
+            ui.checkbox(&mut self.highlighting, "");
+            ui.end_row();
+
+            ui.label("Highlight Color:");
+            ui.color_edit_button_srgb(&mut self.highlight_color);
+            ui.end_row();
+
+            ui.label("Highlight Width:");
+            ui.add(egui::DragValue::new(&mut self.highlight_width).range(1..=20).suffix(" px"));
+            ui.end_row();
+
+            ui.label("Highlight Labels:")
+                .on_hover_text("Show the control type and name of the highlighted element above its frame");
+            ui.checkbox(&mut self.highlight_labels, "");
+            ui.end_row();
+
+            ui.label("Highlight Fade Out:")
+                .on_hover_text("The highlight fades out over this time, 0 = it stays");
+            ui.add(egui::DragValue::new(&mut self.highlight_fade_ms).range(0..=10000).speed(10).suffix(" ms"));
+            ui.end_row();
+
+            ui.label("Track Cursor:");
+            ui.checkbox(&mut self.recording, "");
+            ui.end_row();
+
+            ui.label("Freeze over Explorer:");
+            ui.checkbox(&mut self.freeze_over_explorer, "");
+            ui.end_row();
+
+            ui.label("Hover Delay:");
+            ui.add(egui::DragValue::new(&mut self.hover_delay_ms).range(0..=5000).speed(10).suffix(" ms"));
+            ui.end_row();
+
+            ui.label("Show Event History:");
+            ui.checkbox(&mut self.show_history, "");
+            ui.end_row();
+
+            ui.label("Show Log:");
+            ui.checkbox(&mut self.show_log, "");
+            ui.end_row();
+
+            ui.label("Tree Panel Min Width:");
+            ui.add(egui::DragValue::new(&mut self.tree_panel_min_width).range(100.0..=self.tree_panel_max_width).suffix(" px"));
+            ui.end_row();
+
+            ui.label("Tree Panel Max Width:");
+            ui.add(egui::DragValue::new(&mut self.tree_panel_max_width).range(self.tree_panel_min_width..=4000.0).suffix(" px"));
+            ui.end_row();
+
+            ui.label("Skip Unresponsive Window after:")
+                .on_hover_text("A window is skipped if no element could be retrieved within this time");
+            ui.add(egui::DragValue::new(&mut self.capture_element_timeout_s).range(1..=300).suffix(" s"));
+            ui.end_row();
+
+            ui.label("Overall Capture Timeout:")
+                .on_hover_text("Windows not captured within this time are skipped, 0 = no limit");
+            ui.add(egui::DragValue::new(&mut self.capture_overall_timeout_s).range(0..=3600).suffix(" s"));
+            ui.end_row();
+
+            ui.label("Capture Worker Threads:")
+                .on_hover_text("Number of windows captured at the same time, 0 = one per core");
+            ui.add(egui::DragValue::new(&mut self.capture_worker_threads).range(0..=64));
+            ui.end_row();
+
+            ui.label("Screenshot with Captures:")
+                .on_hover_text("Keep a screenshot of the captured windows with each snapshot");
+            ui.checkbox(&mut self.capture_screenshot, "");
+            ui.end_row();
+        });
+
+        ui.add_space(6.0);
+        if ui.button("Reset to Defaults").clicked() {
+            *self = AppSettings::default();
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Communication with the start screen, which is shown while the initial ui tree is captured.
+//! Messages are sent line by line through the stdin pipe of the start screen process, so a
+//! start screen never sees messages of another run and closes when its parent goes away.
+//! The start screen answers through its stdout pipe, e.g. when the user cancels the capture.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+const START_SCREEN_EXE: &str = "start_screen.exe";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplashMessage {
+    Progress { nodes_visited: usize, windows_done: usize, windows_total: usize, current_window: String },
+    Error(String),
+    Close,
+    /// the parent process closed the pipe, sent by the listener only
+    Disconnected,
+    /// the user cancelled the capture, sent by the start screen to its parent
+    Cancel,
+}
+
+impl SplashMessage {
+    /// Encodes the message as a single line of tab separated fields
+    pub fn encode(&self) -> String {
+        match self {
+            SplashMessage::Progress { nodes_visited, windows_done, windows_total, current_window } => {
+                format!("progress\t{}\t{}\t{}\t{}", nodes_visited, windows_done, windows_total, sanitize(current_window))
+            },
+            SplashMessage::Error(msg) => format!("error\t{}", sanitize(msg)),
+            SplashMessage::Close => "close".to_string(),
+            SplashMessage::Disconnected => "disconnected".to_string(),
+            SplashMessage::Cancel => "cancel".to_string(),
+        }
+    }
+
+    pub fn decode(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end_matches(['\r', '\n']).split('\t');
+        match fields.next()? {
+            "progress" => {
+                let nodes_visited = fields.next()?.parse().ok()?;
+                let windows_done = fields.next()?.parse().ok()?;
+                let windows_total = fields.next()?.parse().ok()?;
+                let current_window = fields.next().unwrap_or_default().to_string();
+                Some(SplashMessage::Progress { nodes_visited, windows_done, windows_total, current_window })
+            },
+            "error" => Some(SplashMessage::Error(fields.next().unwrap_or_default().to_string())),
+            "close" => Some(SplashMessage::Close),
+            "disconnected" => Some(SplashMessage::Disconnected),
+            "cancel" => Some(SplashMessage::Cancel),
+            _ => None,
+        }
+    }
+}
+
+// tabs and line breaks would break the line based protocol
+fn sanitize(text: &str) -> String {
+    text.replace(['\t', '\r', '\n'], " ")
+}
+
+/// The start screen as seen from the main process
+pub struct SplashScreen {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl SplashScreen {
+    /// Launches the start screen, which is expected next to the running executable
+    /// (falling back to the working directory). `on_cancel` is called on a separate
+    /// thread when the user cancels the capture on the start screen.
+    pub fn launch<F>(on_cancel: F) -> io::Result<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let mut child = Command::new(start_screen_path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        if let Some(stdout) = child.stdout.take() {
+            thread::spawn(move || listen_for_cancel(stdout, on_cancel));
+        }
+        Ok(SplashScreen { child, stdin })
+    }
+
+    pub fn send(&mut self, msg: &SplashMessage) {
+        if let Some(stdin) = &mut self.stdin {
+            // a start screen closed by the user is not an error
+            if writeln!(stdin, "{}", msg.encode()).and_then(|_| stdin.flush()).is_err() {
+                self.stdin = None;
+            }
+        }
+    }
+
+    pub fn close(mut self) {
+        self.send(&SplashMessage::Close);
+        self.stdin = None;
+    }
+
+    /// Shows the error on the start screen and waits until the user closed it
+    pub fn fail(mut self, msg: &str) {
+        self.send(&SplashMessage::Error(msg.to_string()));
+        self.stdin = None;
+        let _res = self.child.wait();
+    }
+}
+
+fn listen_for_cancel<F: Fn()>(stdout: ChildStdout, on_cancel: F) {
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if SplashMessage::decode(&line) == Some(SplashMessage::Cancel) {
+            on_cancel();
+        }
+    }
+}
+
+/// Tells the parent process that the user cancelled the capture, called by the start screen
+pub fn request_cancel() {
+    let mut stdout = io::stdout();
+    let _res = writeln!(stdout, "{}", SplashMessage::Cancel.encode()).and_then(|_| stdout.flush());
+}
+
+fn start_screen_path() -> PathBuf {
+    std::env::current_exe()
+        .map(|exe| exe.with_file_name(START_SCREEN_EXE))
+        .ok()
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(START_SCREEN_EXE))
+}
+
+/// Reads the messages of the main process from stdin on a separate thread,
+/// `on_message` is called after each message, e.g. to request a repaint
+pub fn listen<F>(on_message: F) -> Receiver<SplashMessage>
+where
+    F: Fn() + Send + 'static,
+{
+    let (tx, rx): (Sender<SplashMessage>, Receiver<SplashMessage>) = channel();
+    thread::spawn(move || {
+        let stdin = BufReader::new(io::stdin());
+        for line in stdin.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if let Some(msg) = SplashMessage::decode(&line) {
+                if tx.send(msg).is_err() {
+                    return;
+                }
+                on_message();
+            }
+        }
+        let _res = tx.send(SplashMessage::Disconnected);
+        on_message();
+    });
+    rx
+}
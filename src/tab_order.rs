@@ -0,0 +1,322 @@
+//! Expected keyboard navigation order of a window, derived from the captured tree: the
+//! focusable elements in the order of the tree. Stops which are focusable but can't be
+//! reached, or which jump back against the reading direction, are flagged.
+#![allow(dead_code)]
+
+use chrono::{DateTime, Local};
+use eframe::egui;
+
+use crate::UITree;
+use crate::uiexplore::{self, UIElementProps};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TabOrderIssue {
+    /// focusable, but the keyboard focus can't get there
+    Unreachable(String),
+    /// the stop is above, or in the same row left of, the previous stop
+    AgainstReadingOrder,
+}
+
+impl TabOrderIssue {
+    pub fn label(&self) -> String {
+        match self {
+            TabOrderIssue::Unreachable(reason) => format!("unreachable: {}", reason),
+            TabOrderIssue::AgainstReadingOrder => "against reading order".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TabStop {
+    pub index: usize,
+    /// 1-based position in the tab order, None for unreachable elements
+    pub position: Option<usize>,
+    pub issue: Option<TabOrderIssue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TabOrder {
+    pub window: usize,
+    /// reachable stops in navigation order, followed by the unreachable ones
+    pub stops: Vec<TabStop>,
+    captured_at: DateTime<Local>,
+}
+
+impl TabOrder {
+    /// Computes the tab order of the window at `window` (a node of the tree)
+    pub fn analyze(ui_tree: &UITree, window: usize) -> Self {
+        let mut reachable: Vec<TabStop> = Vec::new();
+        let mut unreachable: Vec<TabStop> = Vec::new();
+        let mut prev: Option<&UIElementProps> = None;
+
+        // depth first, the children of an element are navigated before its next sibling
+        let mut stack: Vec<(usize, Option<String>)> = ui_tree.children(window).iter().rev().map(|&child| (child, None)).collect();
+        while let Some((index, blocked_by)) = stack.pop() {
+            let ui_elem_props = ui_tree.node(index).1;
+
+            // elements inside a disabled or offscreen container can't get the focus either
+            let blocked_by = blocked_by.or_else(|| container_blocker(ui_elem_props));
+            for &child in ui_tree.children(index).iter().rev() {
+                stack.push((child, blocked_by.clone()));
+            }
+
+            if !ui_elem_props.is_keyboard_focusable || !ui_elem_props.is_enabled {
+                continue;
+            }
+
+            if let Some(reason) = unreachable_reason(ui_elem_props, &blocked_by) {
+                unreachable.push(TabStop { index, position: None, issue: Some(TabOrderIssue::Unreachable(reason)) });
+                continue;
+            }
+
+            let issue = match prev {
+                Some(prev) if is_against_reading_order(&prev.bounding_rect, &ui_elem_props.bounding_rect) => Some(TabOrderIssue::AgainstReadingOrder),
+                _ => None,
+            };
+            reachable.push(TabStop { index, position: Some(reachable.len() + 1), issue });
+            prev = Some(ui_elem_props);
+        }
+
+        reachable.extend(unreachable);
+        TabOrder { window, stops: reachable, captured_at: ui_tree.get_captured_at() }
+    }
+
+    /// The stops the keyboard focus moves through, in order
+    pub fn reachable(&self) -> impl Iterator<Item = &TabStop> {
+        self.stops.iter().filter(|stop| stop.position.is_some())
+    }
+
+    pub fn issue_count(&self) -> usize {
+        self.stops.iter().filter(|stop| stop.issue.is_some()).count()
+    }
+
+    pub fn belongs_to(&self, ui_tree: &UITree) -> bool {
+        self.captured_at == ui_tree.get_captured_at()
+    }
+}
+
+// the reason why the descendants of a container can't be focused
+fn container_blocker(ui_elem_props: &UIElementProps) -> Option<String> {
+    if !ui_elem_props.is_enabled {
+        Some(format!("inside disabled {} '{}'", ui_elem_props.control_type, ui_elem_props.name))
+    } else if ui_elem_props.is_offscreen {
+        Some(format!("inside offscreen {} '{}'", ui_elem_props.control_type, ui_elem_props.name))
+    } else {
+        None
+    }
+}
+
+fn unreachable_reason(ui_elem_props: &UIElementProps, blocked_by: &Option<String>) -> Option<String> {
+    let rect = &ui_elem_props.bounding_rect;
+    if let Some(blocked_by) = blocked_by {
+        Some(blocked_by.clone())
+    } else if ui_elem_props.is_offscreen {
+        Some("offscreen".to_string())
+    } else if rect.get_right() <= rect.get_left() || rect.get_bottom() <= rect.get_top() {
+        Some("empty bounding rectangle".to_string())
+    } else {
+        None
+    }
+}
+
+/// Left to right, top to bottom: a stop is out of order if it lies entirely above the
+/// previous one, or left of it while overlapping vertically (i.e. in the same row)
+pub fn is_against_reading_order(prev: &uiautomation::types::Rect, current: &uiautomation::types::Rect) -> bool {
+    if current.get_bottom() <= prev.get_top() {
+        return true;
+    }
+    let same_row = current.get_top() < prev.get_bottom() && current.get_bottom() > prev.get_top();
+    same_row && current.get_right() <= prev.get_left()
+}
+
+pub enum TabOrderAction {
+    /// analyze the top level window of the active element
+    Analyze,
+    Select(usize),
+}
+
+/// Lists the tab order of the analyzed window
+pub struct TabOrderPanel {
+    pub order: Option<TabOrder>,
+    pub show_badges: bool,
+}
+
+impl TabOrderPanel {
+    pub fn new() -> Self {
+        TabOrderPanel { order: None, show_badges: true }
+    }
+
+    /// The tab order if it was computed for the given tree
+    pub fn current_order(&self, ui_tree: &UITree) -> Option<&TabOrder> {
+        self.order.as_ref().filter(|order| order.belongs_to(ui_tree))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, ui_tree: &UITree, active_ui_element: Option<usize>) -> Option<TabOrderAction> {
+        let mut action: Option<TabOrderAction> = None;
+
+        ui.horizontal(|ui| {
+            if ui.button("⌨ Analyze").on_hover_text("Compute the tab order of the window of the active element").clicked() {
+                action = Some(TabOrderAction::Analyze);
+            }
+            ui.checkbox(&mut self.show_badges, "Show Badges");
+        });
+
+        let Some(order) = self.current_order(ui_tree) else {
+            if self.order.is_some() {
+                ui.colored_label(ui.visuals().warn_fg_color, "The tab order belongs to another snapshot, analyze again");
+            } else {
+                ui.label("Select an element and analyze its window");
+            }
+            return action;
+        };
+
+        ui.label(egui::RichText::new(uiexplore::format_element_label(ui_tree.node(order.window).1)).strong());
+        ui.label(format!("{} stops, {} issues", order.reachable().count(), order.issue_count()));
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for stop in &order.stops {
+                    let ui_elem_props = ui_tree.node(stop.index).1;
+                    let position = stop.position.map(|position| position.to_string()).unwrap_or("–".to_string());
+                    let mut text = egui::RichText::new(format!("{:>3}  {} '{}'", position, ui_elem_props.control_type, ui_elem_props.name));
+                    if stop.issue.is_some() {
+                        text = text.color(ui.visuals().warn_fg_color);
+                    }
+                    let mut response = ui.selectable_label(active_ui_element == Some(stop.index), text);
+                    if let Some(issue) = &stop.issue {
+                        response = response.on_hover_text(issue.label());
+                    }
+                    if response.clicked() {
+                        action = Some(TabOrderAction::Select(stop.index));
+                    }
+                }
+            });
+
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{element, with_rect, TreeBuilder};
+    use uiautomation::types::Rect;
+
+    fn focusable(name: &str, left: i32, top: i32, right: i32, bottom: i32) -> UIElementProps {
+        UIElementProps { is_keyboard_focusable: true, ..with_rect(element("Button", name), left, top, right, bottom) }
+    }
+
+    fn window() -> (TreeBuilder, usize) {
+        let mut tree = TreeBuilder::new(element("Pane", "Desktop"));
+        let window = tree.add(tree.desktop(), with_rect(element("Window", "Dialog"), 0, 0, 800, 600));
+        (tree, window)
+    }
+
+    // (name, position, issue) of every stop
+    fn stops(ui_tree: &UITree, order: &TabOrder) -> Vec<(String, Option<usize>, Option<TabOrderIssue>)> {
+        order.stops.iter().map(|stop| (ui_tree.node(stop.index).1.name.clone(), stop.position, stop.issue.clone())).collect()
+    }
+
+    #[test]
+    fn stops_follow_the_tree() {
+        let (mut tree, window) = window();
+        let group = tree.add(window, with_rect(element("Group", "Options"), 10, 10, 400, 100));
+        tree.add(group, focusable("First", 20, 20, 100, 40));
+        tree.add(group, focusable("Second", 120, 20, 200, 40));
+        tree.add(window, element("Text", "Label"));
+        tree.add(window, UIElementProps { is_enabled: false, ..focusable("Disabled", 20, 200, 100, 220) });
+        tree.add(window, focusable("OK", 20, 500, 100, 520));
+        let ui_tree = tree.build();
+
+        let order = TabOrder::analyze(&ui_tree, window);
+        assert_eq!(stops(&ui_tree, &order), [
+            ("First".to_string(), Some(1), None),
+            ("Second".to_string(), Some(2), None),
+            ("OK".to_string(), Some(3), None),
+        ]);
+        assert_eq!(order.issue_count(), 0);
+    }
+
+    #[test]
+    fn disabled_and_offscreen_containers_block_their_descendants() {
+        let (mut tree, window) = window();
+        let toolbar = tree.add(window, UIElementProps { is_enabled: false, ..with_rect(element("ToolBar", "Tools"), 0, 0, 800, 30) });
+        let nested = tree.add(toolbar, with_rect(element("Group", "Nested"), 0, 0, 400, 30));
+        tree.add(nested, focusable("Bold", 0, 0, 30, 30));
+        let hidden = tree.add(window, UIElementProps { is_offscreen: true, ..with_rect(element("Pane", "Hidden"), 0, 0, 0, 0) });
+        tree.add(hidden, focusable("Apply", 10, 40, 90, 60));
+        tree.add(window, UIElementProps { is_offscreen: true, ..focusable("Scrolled away", 10, 700, 90, 720) });
+        tree.add(window, focusable("OK", 20, 500, 100, 520));
+        let ui_tree = tree.build();
+
+        // the unreachable stops come after the reachable ones, in tree order
+        let order = TabOrder::analyze(&ui_tree, window);
+        assert_eq!(stops(&ui_tree, &order), [
+            ("OK".to_string(), Some(1), None),
+            ("Bold".to_string(), None, Some(TabOrderIssue::Unreachable("inside disabled ToolBar 'Tools'".to_string()))),
+            ("Apply".to_string(), None, Some(TabOrderIssue::Unreachable("inside offscreen Pane 'Hidden'".to_string()))),
+            ("Scrolled away".to_string(), None, Some(TabOrderIssue::Unreachable("offscreen".to_string()))),
+        ]);
+        assert_eq!(order.reachable().count(), 1);
+        assert_eq!(order.issue_count(), 3);
+    }
+
+    #[test]
+    fn empty_bounding_rectangles_are_unreachable() {
+        let (mut tree, window) = window();
+        tree.add(window, focusable("Zero width", 10, 10, 10, 30));
+        tree.add(window, focusable("Zero height", 10, 10, 90, 10));
+        tree.add(window, focusable("Inverted", 90, 30, 10, 10));
+        tree.add(window, focusable("OK", 20, 500, 100, 520));
+        let ui_tree = tree.build();
+
+        let order = TabOrder::analyze(&ui_tree, window);
+        let empty = Some(TabOrderIssue::Unreachable("empty bounding rectangle".to_string()));
+        assert_eq!(stops(&ui_tree, &order), [
+            ("OK".to_string(), Some(1), None),
+            ("Zero width".to_string(), None, empty.clone()),
+            ("Zero height".to_string(), None, empty.clone()),
+            ("Inverted".to_string(), None, empty),
+        ]);
+    }
+
+    #[test]
+    fn stops_against_the_reading_order_are_flagged() {
+        let (mut tree, window) = window();
+        tree.add(window, focusable("Right", 300, 100, 400, 130));
+        tree.add(window, focusable("Left", 100, 105, 200, 125));
+        tree.add(window, focusable("Below", 100, 300, 200, 330));
+        tree.add(window, focusable("Above", 500, 10, 600, 40));
+        tree.add(window, focusable("Last", 100, 500, 200, 530));
+        let ui_tree = tree.build();
+
+        let order = TabOrder::analyze(&ui_tree, window);
+        assert_eq!(stops(&ui_tree, &order), [
+            ("Right".to_string(), Some(1), None),
+            ("Left".to_string(), Some(2), Some(TabOrderIssue::AgainstReadingOrder)),
+            ("Below".to_string(), Some(3), None),
+            ("Above".to_string(), Some(4), Some(TabOrderIssue::AgainstReadingOrder)),
+            ("Last".to_string(), Some(5), None),
+        ]);
+        assert_eq!(order.issue_count(), 2);
+    }
+
+    #[test]
+    fn reading_order_of_two_rects() {
+        let prev = Rect::new(100, 100, 200, 130);
+        // same row
+        assert!(!is_against_reading_order(&prev, &Rect::new(250, 100, 350, 130)));
+        assert!(is_against_reading_order(&prev, &Rect::new(0, 110, 80, 120)));
+        // overlapping horizontally isn't right to left
+        assert!(!is_against_reading_order(&prev, &Rect::new(150, 100, 250, 130)));
+        // entirely above, also when it is to the right
+        assert!(is_against_reading_order(&prev, &Rect::new(100, 50, 200, 100)));
+        assert!(is_against_reading_order(&prev, &Rect::new(500, 0, 600, 30)));
+        // next row, also when it is to the left
+        assert!(!is_against_reading_order(&prev, &Rect::new(0, 130, 80, 160)));
+        // taller than the previous stop, starting above it but in the same row
+        assert!(!is_against_reading_order(&prev, &Rect::new(300, 80, 400, 150)));
+    }
+}
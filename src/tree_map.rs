@@ -65,6 +65,32 @@ impl<T> UITreeMap<T> {
         index
     }
 
+    /// Replaces the name and data of a node, its position in the tree is kept
+    pub fn update_node(&mut self, index: usize, name: &str, data: T) {
+        self.name_to_index.insert(name.to_string(), index);
+        self.nodes[index].name = name.to_string();
+        self.nodes[index].data = data;
+    }
+
+    /// Detaches all children of a node, their nodes stay in the map but are no longer reachable from the root
+    pub fn remove_children(&mut self, index: usize) {
+        self.nodes[index].children.clear();
+    }
+
+    /// Copies the subtree starting at `other_index` of another tree below `parent`,
+    /// returns the index of the copied subtree root
+    pub fn add_subtree(&mut self, parent: usize, other: &UITreeMap<T>, other_index: usize) -> usize
+    where
+        T: Clone,
+    {
+        let node = other.node(other_index);
+        let index = self.add_child(parent, &node.name, node.data.clone());
+        for &child in other.children(other_index) {
+            self.add_subtree(index, other, child);
+        }
+        index
+    }
+
     pub fn get_path_to_element(&self, index: usize) -> Vec<usize> {
         let mut path = Vec::new();
         let mut current_index = index;
@@ -0,0 +1,251 @@
+//! The view of the ui automation tree used for capturing: raw, control, content or a custom condition
+#![allow(dead_code)]
+
+use eframe::egui;
+
+use uiautomation::controls::ControlType;
+use uiautomation::core::{UIAutomation, UICondition};
+use uiautomation::types::UIProperty;
+use uiautomation::variants::Variant;
+use uiautomation::{Result, UITreeWalker};
+
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TreeView {
+    Raw,
+    #[default]
+    Control,
+    Content,
+    Custom(ConditionSpec),
+}
+
+impl TreeView {
+    pub fn label(&self) -> String {
+        match self {
+            TreeView::Raw => "Raw View".to_string(),
+            TreeView::Control => "Control View".to_string(),
+            TreeView::Content => "Content View".to_string(),
+            TreeView::Custom(condition) => format!("Custom View [{}]", condition),
+        }
+    }
+
+    /// Creates the walker used to traverse the tree in this view
+    pub fn create_walker(&self, automation: &UIAutomation) -> Result<UITreeWalker> {
+        match self {
+            TreeView::Raw => automation.get_raw_view_walker(),
+            TreeView::Control => automation.get_control_view_walker(),
+            TreeView::Content => automation.get_content_view_walker(),
+            TreeView::Custom(condition) => {
+                let condition = condition.build(automation)?;
+                automation.filter_tree_walker(condition)
+            },
+        }
+    }
+}
+
+/// The properties which can be used in a custom condition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConditionProperty {
+    Name,
+    ClassName,
+    AutomationId,
+    ControlType,
+    FrameworkId,
+    ProcessId,
+    IsControlElement,
+    IsContentElement,
+    IsEnabled,
+    IsOffscreen,
+    IsKeyboardFocusable,
+}
+
+impl ConditionProperty {
+    pub const ALL: [ConditionProperty; 11] = [
+        ConditionProperty::Name,
+        ConditionProperty::ClassName,
+        ConditionProperty::AutomationId,
+        ConditionProperty::ControlType,
+        ConditionProperty::FrameworkId,
+        ConditionProperty::ProcessId,
+        ConditionProperty::IsControlElement,
+        ConditionProperty::IsContentElement,
+        ConditionProperty::IsEnabled,
+        ConditionProperty::IsOffscreen,
+        ConditionProperty::IsKeyboardFocusable,
+    ];
+
+    fn ui_property(&self) -> UIProperty {
+        match self {
+            ConditionProperty::Name => UIProperty::Name,
+            ConditionProperty::ClassName => UIProperty::ClassName,
+            ConditionProperty::AutomationId => UIProperty::AutomationId,
+            ConditionProperty::ControlType => UIProperty::ControlType,
+            ConditionProperty::FrameworkId => UIProperty::FrameworkId,
+            ConditionProperty::ProcessId => UIProperty::ProcessId,
+            ConditionProperty::IsControlElement => UIProperty::IsControlElement,
+            ConditionProperty::IsContentElement => UIProperty::IsContentElement,
+            ConditionProperty::IsEnabled => UIProperty::IsEnabled,
+            ConditionProperty::IsOffscreen => UIProperty::IsOffscreen,
+            ConditionProperty::IsKeyboardFocusable => UIProperty::IsKeyboardFocusable,
+        }
+    }
+
+    /// Converts the value entered by the user into the variant type expected for the property
+    fn parse_value(&self, value: &str) -> std::result::Result<Variant, String> {
+        match self {
+            ConditionProperty::Name | ConditionProperty::ClassName | ConditionProperty::AutomationId | ConditionProperty::FrameworkId => {
+                Ok(Variant::from(value))
+            },
+            ConditionProperty::ProcessId => {
+                value.trim().parse::<i32>().map(Variant::from).map_err(|_| format!("'{}' is not a valid process id", value))
+            },
+            ConditionProperty::ControlType => {
+                parse_control_type(value).map(|ctrl_type| Variant::from(ctrl_type as i32)).ok_or(format!("'{}' is not a known control type", value))
+            },
+            ConditionProperty::IsControlElement | ConditionProperty::IsContentElement | ConditionProperty::IsEnabled
+            | ConditionProperty::IsOffscreen | ConditionProperty::IsKeyboardFocusable => {
+                value.trim().to_lowercase().parse::<bool>().map(Variant::from).map_err(|_| format!("'{}' is not true or false", value))
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ConditionProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Looks up a control type by its name, e.g. "Button"
+pub fn parse_control_type(name: &str) -> Option<ControlType> {
+    // control type ids range from 50000 (Button) to 50040 (AppBar)
+    (50000..=50040)
+        .filter_map(|id| ControlType::try_from(id).ok())
+        .find(|ctrl_type| ctrl_type.to_string().eq_ignore_ascii_case(name.trim()))
+}
+
+/// A condition as composed in the condition builder
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionSpec {
+    PropertyEquals { property: ConditionProperty, value: String },
+    And(Box<ConditionSpec>, Box<ConditionSpec>),
+    Or(Box<ConditionSpec>, Box<ConditionSpec>),
+    Not(Box<ConditionSpec>),
+}
+
+impl Default for ConditionSpec {
+    fn default() -> Self {
+        ConditionSpec::PropertyEquals { property: ConditionProperty::IsControlElement, value: "true".to_string() }
+    }
+}
+
+impl std::fmt::Display for ConditionSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConditionSpec::PropertyEquals { property, value } => write!(f, "{} = '{}'", property, value),
+            ConditionSpec::And(left, right) => write!(f, "({} AND {})", left, right),
+            ConditionSpec::Or(left, right) => write!(f, "({} OR {})", left, right),
+            ConditionSpec::Not(inner) => write!(f, "NOT {}", inner),
+        }
+    }
+}
+
+impl ConditionSpec {
+    /// Checks that all values can be converted to the type of their property
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            ConditionSpec::PropertyEquals { property, value } => property.parse_value(value).map(|_| ()),
+            ConditionSpec::And(left, right) | ConditionSpec::Or(left, right) => {
+                left.validate()?;
+                right.validate()
+            },
+            ConditionSpec::Not(inner) => inner.validate(),
+        }
+    }
+
+    pub fn build(&self, automation: &UIAutomation) -> Result<UICondition> {
+        match self {
+            ConditionSpec::PropertyEquals { property, value } => {
+                let value = property.parse_value(value)
+                    .map_err(|msg| uiautomation::Error::new(uiautomation::errors::ERR_FORMAT, &msg))?;
+                automation.create_property_condition(property.ui_property(), value, None)
+            },
+            ConditionSpec::And(left, right) => automation.create_and_condition(left.build(automation)?, right.build(automation)?),
+            ConditionSpec::Or(left, right) => automation.create_or_condition(left.build(automation)?, right.build(automation)?),
+            ConditionSpec::Not(inner) => automation.create_not_condition(inner.build(automation)?),
+        }
+    }
+
+    /// Renders the condition builder for this condition, returns true if the condition was changed
+    pub fn ui(&mut self, ui: &mut egui::Ui, id_salt: &str) -> bool {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            let kind = self.kind_label();
+            egui::ComboBox::from_id_salt(format!("{}_kind", id_salt))
+                .selected_text(kind)
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    for new_kind in ["Property", "And", "Or", "Not"] {
+                        if ui.selectable_label(kind == new_kind, new_kind).clicked() && kind != new_kind {
+                            self.change_kind(new_kind);
+                            changed = true;
+                        }
+                    }
+                });
+
+            if let ConditionSpec::PropertyEquals { property, value } = self {
+                egui::ComboBox::from_id_salt(format!("{}_property", id_salt))
+                    .selected_text(property.to_string())
+                    .show_ui(ui, |ui| {
+                        for candidate in ConditionProperty::ALL {
+                            changed |= ui.selectable_value(property, candidate, candidate.to_string()).changed();
+                        }
+                    });
+                ui.label("=");
+                changed |= ui.text_edit_singleline(value).changed();
+            }
+        });
+
+        match self {
+            ConditionSpec::And(left, right) | ConditionSpec::Or(left, right) => {
+                ui.indent(format!("{}_children", id_salt), |ui| {
+                    changed |= left.ui(ui, &format!("{}_l", id_salt));
+                    changed |= right.ui(ui, &format!("{}_r", id_salt));
+                });
+            },
+            ConditionSpec::Not(inner) => {
+                ui.indent(format!("{}_children", id_salt), |ui| {
+                    changed |= inner.ui(ui, &format!("{}_n", id_salt));
+                });
+            },
+            ConditionSpec::PropertyEquals { .. } => (),
+        }
+
+        changed
+    }
+
+    fn kind_label(&self) -> &'static str {
+        match self {
+            ConditionSpec::PropertyEquals { .. } => "Property",
+            ConditionSpec::And(..) => "And",
+            ConditionSpec::Or(..) => "Or",
+            ConditionSpec::Not(..) => "Not",
+        }
+    }
+
+    fn change_kind(&mut self, kind: &str) {
+        // the current condition becomes the first operand of a new and / or / not condition
+        let current = std::mem::take(self);
+        *self = match kind {
+            "And" => ConditionSpec::And(Box::new(current), Box::new(ConditionSpec::default())),
+            "Or" => ConditionSpec::Or(Box::new(current), Box::new(ConditionSpec::default())),
+            "Not" => ConditionSpec::Not(Box::new(current)),
+            _ => match current {
+                ConditionSpec::PropertyEquals { .. } => current,
+                ConditionSpec::And(left, _) | ConditionSpec::Or(left, _) => *left,
+                ConditionSpec::Not(inner) => *inner,
+            },
+        };
+    }
+}
@@ -1,14 +1,26 @@
 #![allow(dead_code)]
 
 
-use crate::UITreeMap;
+use crate::{logging, UITreeMap, UIHashSet};
+use crate::parallel_capture::{self, ElementSource, PoolLimits, SourceFactory, SubtreeJob, SubtreeResult};
+use crate::overlay::OVERLAY_CLASS_NAME;
+use crate::screenshot::Screenshot;
+use crate::tree_view::TreeView;
 
+use chrono::{DateTime, Local};
 
-use std::sync::mpsc::Sender;
 
-use uiautomation::core::UIAutomation;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+
+use uiautomation::core::{UIAutomation, UICacheRequest};
 use uiautomation::{UIElement, UITreeWalker};
-use uiautomation::types::Handle;
+use uiautomation::types::{Handle, Point, Rect, TreeScope, UIProperty};
+use uiautomation::variants::Value;
 
 #[derive(Debug, Clone)]
 pub struct UIElementInTree {
@@ -34,11 +46,110 @@ impl UIElementInTree {
 pub struct UITree {
     tree: UITreeMap<UIElementProps>,
     ui_elements: Vec<UIElementInTree>,
+    captured_at: DateTime<Local>,
+    options: CaptureOptions,
+    loading: UIHashSet<usize>, // placeholders of windows which are still being captured
+    screenshot: Option<Arc<Screenshot>>, // shared, as the tree is cloned while it is streamed
+    revision: usize, // incremented whenever elements are added or changed
 }
 
 impl UITree {
-    pub fn new(tree: UITreeMap<UIElementProps>, ui_elements: Vec<UIElementInTree>) -> Self {
-        UITree {tree, ui_elements} 
+    pub fn new(tree: UITreeMap<UIElementProps>, ui_elements: Vec<UIElementInTree>, options: CaptureOptions) -> Self {
+        UITree {tree, ui_elements, captured_at: Local::now(), options, loading: UIHashSet::new(), screenshot: None, revision: 0} 
+    }
+
+    /// Creates a tree with the given placeholder nodes, their content is added 
+    /// with graft_subtree as soon as it has been captured
+    pub fn new_skeleton(tree: UITreeMap<UIElementProps>, options: CaptureOptions, loading: UIHashSet<usize>) -> Self {
+        let mut ui_tree = UITree {tree, ui_elements: Vec::new(), captured_at: Local::now(), options, loading, screenshot: None, revision: 0};
+        ui_tree.rebuild_elements();
+        ui_tree
+    }
+
+    /// Creates a tree which is complete already, e.g. one read from a file
+    pub fn from_map(tree: UITreeMap<UIElementProps>, options: CaptureOptions) -> Self {
+        UITree::new_skeleton(tree, options, UIHashSet::new())
+    }
+
+    pub fn is_loading(&self, index: usize) -> bool {
+        self.loading.contains(&index)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.loading.is_empty()
+    }
+
+    /// Replaces a placeholder with the captured subtree, or marks it with the reason why 
+    /// it couldn't be captured. rebuild_elements has to be called afterwards.
+    pub fn graft_subtree(&mut self, placeholder: usize, result: SubtreeResult) {
+        if !self.loading.remove(&placeholder) {
+            return;
+        }
+        self.revision += 1;
+        match result {
+            SubtreeResult::Captured(subtree) => {
+                let subtree_root = subtree.node(subtree.root());
+                self.tree.update_node(placeholder, &subtree_root.name, subtree_root.data.clone());
+                for &child in subtree.children(subtree.root()) {
+                    self.tree.add_subtree(placeholder, &subtree, child);
+                }
+            },
+            SubtreeResult::Failed(msg) => self.mark_failed(placeholder, msg),
+        }
+    }
+
+    /// Marks all windows still being captured with the reason why they never will be
+    pub fn stop_loading(&mut self, msg: &str) {
+        let loading: Vec<usize> = self.loading.drain().collect();
+        for placeholder in loading {
+            self.mark_failed(placeholder, msg.to_string());
+        }
+    }
+
+    fn mark_failed(&mut self, index: usize, msg: String) {
+        self.revision += 1;
+        let mut ui_elem_props = self.tree.node(index).data.clone();
+        ui_elem_props.errors.push(msg);
+        self.tree.update_node(index, format_element_label(&ui_elem_props).as_str(), ui_elem_props);
+    }
+
+    /// Recreates the flat list of all elements, which is used to find the element under the cursor
+    pub fn rebuild_elements(&mut self) {
+        let mut ui_elements: Vec<UIElementInTree> = Vec::new();
+        self.tree.for_each(|index, ui_elem_props| ui_elements.push(UIElementInTree::new(ui_elem_props.clone(), index)));
+        sort_ui_elements(&mut ui_elements);
+        self.ui_elements = ui_elements;
+    }
+
+    pub fn get_capture_options(&self) -> &CaptureOptions {
+        &self.options
+    }
+
+    pub fn get_view(&self) -> &TreeView {
+        &self.options.view
+    }
+
+    pub fn get_captured_at(&self) -> DateTime<Local> {
+        self.captured_at
+    }
+
+    /// Changes whenever elements are added to the tree or changed, e.g. to know whether
+    /// a result derived from the tree is still valid
+    pub fn get_revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn get_scope(&self) -> &CaptureScope {
+        &self.options.scope
+    }
+
+    /// The screenshot of the captured area taken together with the tree, if any
+    pub fn get_screenshot(&self) -> Option<&Arc<Screenshot>> {
+        self.screenshot.as_ref()
+    }
+
+    pub fn set_screenshot(&mut self, screenshot: Screenshot) {
+        self.screenshot = Some(Arc::new(screenshot));
     }
 
     pub fn get_tree(&self) -> &UITreeMap<UIElementProps> {
@@ -60,6 +171,12 @@ impl UITree {
         self.tree.root()
     }
 
+    /// The desktop element, the parent of the top level windows. It is repeated as the
+    /// single child of the root node, trees without it use the root node instead.
+    pub fn desktop(&self) -> usize {
+        self.tree.children(self.root()).first().copied().unwrap_or(self.root())
+    }
+
     pub fn children(&self, index: usize) -> &[usize] {
         self.tree.children(index)
     }
@@ -69,6 +186,175 @@ impl UITree {
         (&node.name, &node.data)
     }
 
+    /// Merges a chain of elements, ordered from the top level window down to the
+    /// target element, into the tree. Elements already in the tree are matched by
+    /// their runtime id, missing ones are added below their closest known ancestor.
+    /// Returns the tree index of the last element of the chain.
+    pub fn merge_element_chain(&mut self, chain: Vec<UIElementProps>) -> Option<usize> {
+        
+        // top level windows are children of the desktop element
+        let mut parent = self.desktop();
+        let mut z_order: usize = 0;
+        let mut added = false;
+        let mut last: Option<usize> = None;
+
+        for mut ui_elem_props in chain {
+            let existing = self.tree.children(parent).iter().copied().find(|&child| {
+                let data = &self.tree.node(child).data;
+                !data.runtime_id.is_empty() && data.runtime_id == ui_elem_props.runtime_id
+            });
+
+            let index = match existing {
+                Some(index) => {
+                    z_order = self.tree.node(index).data.z_order;
+                    index
+                },
+                None => {
+                    // new elements inherit the z_order of their top level window
+                    ui_elem_props.z_order = z_order;
+                    ui_elem_props.level = self.tree.node(parent).data.level + 1;
+                    let item = format_element_label(&ui_elem_props);
+                    let index = self.tree.add_child(parent, item.as_str(), ui_elem_props.clone());
+                    self.ui_elements.push(UIElementInTree::new(ui_elem_props, index));
+                    added = true;
+                    index
+                },
+            };
+            parent = index;
+            last = Some(index);
+        }
+
+        if added {
+            sort_ui_elements(&mut self.ui_elements);
+            self.revision += 1;
+        }
+
+        last
+    }
+
+    /// Merges a scoped capture into this tree, e.g. a window captured again into a capture of the
+    /// whole desktop. The ancestors of the captured windows or subtree are matched by their runtime id
+    /// and added where missing, the captured elements replace their counterparts including all their
+    /// descendants. Returns the number of merged windows or subtrees.
+    pub fn merge_scoped(&mut self, scoped: &UITree) -> usize {
+        let scope_roots: Vec<usize> = match scoped.get_scope() {
+            CaptureScope::Subtree { runtime_id, .. } => scoped.find_by_runtime_id(runtime_id).into_iter().collect(),
+            // the captured windows are the children of the desktop
+            _ => scoped.children(scoped.desktop()).to_vec(),
+        };
+
+        for &scope_root in &scope_roots {
+            // the path starts with the desktop, followed by the top level window
+            let path = scoped.get_tree().get_path_to_element(scope_root);
+            if path.len() < 2 {
+                continue;
+            }
+            let mut parent = self.desktop();
+            let mut z_order: usize = 0;
+            for &ancestor in &path[1..path.len() - 1] {
+                let ui_elem_props = scoped.node(ancestor).1;
+                parent = match self.find_child(parent, &ui_elem_props.runtime_id) {
+                    Some(index) => index,
+                    None => {
+                        let mut ui_elem_props = ui_elem_props.clone();
+                        ui_elem_props.z_order = z_order;
+                        self.tree.add_child(parent, format_element_label(&ui_elem_props).as_str(), ui_elem_props)
+                    },
+                };
+                z_order = self.tree.node(parent).data.z_order;
+            }
+
+            let (name, ui_elem_props) = scoped.node(scope_root);
+            let merged = match self.find_child(parent, &ui_elem_props.runtime_id) {
+                Some(index) => {
+                    // windows keep their position in the z order of this tree
+                    if parent == self.desktop() {
+                        z_order = self.tree.node(index).data.z_order;
+                    }
+                    self.tree.update_node(index, name, ui_elem_props.clone());
+                    self.tree.remove_children(index);
+                    for &child in scoped.children(scope_root) {
+                        self.tree.add_subtree(index, scoped.get_tree(), child);
+                    }
+                    index
+                },
+                None => self.tree.add_subtree(parent, scoped.get_tree(), scope_root),
+            };
+            // the elements inherit the z order of their top level window
+            self.set_z_order(merged, z_order);
+        }
+
+        self.captured_at = self.captured_at.max(scoped.captured_at);
+        self.revision += 1;
+        self.rebuild_elements();
+        scope_roots.len()
+    }
+
+    // the child of `parent` with the given runtime id, elements without one are never matched
+    fn find_child(&self, parent: usize, runtime_id: &[i32]) -> Option<usize> {
+        if runtime_id.is_empty() {
+            return None;
+        }
+        self.tree.children(parent).iter().copied().find(|&child| self.tree.node(child).data.runtime_id == runtime_id)
+    }
+
+    fn set_z_order(&mut self, index: usize, z_order: usize) {
+        let mut stack: Vec<usize> = vec![index];
+        while let Some(index) = stack.pop() {
+            let node = self.tree.node(index);
+            let mut ui_elem_props = node.data.clone();
+            ui_elem_props.z_order = z_order;
+            let name = node.name.clone();
+            self.tree.update_node(index, &name, ui_elem_props);
+            stack.extend_from_slice(self.tree.children(index));
+        }
+    }
+
+    /// Returns the index of the element with the given runtime id
+    pub fn find_by_runtime_id(&self, runtime_id: &[i32]) -> Option<usize> {
+        if runtime_id.is_empty() {
+            return None;
+        }
+        let mut found: Option<usize> = None;
+        self.tree.for_each(|index, ui_elem_props| {
+            if found.is_none() && ui_elem_props.runtime_id == runtime_id {
+                found = Some(index);
+            }
+        });
+        found
+    }
+
+    /// Finds the element corresponding to the element at `index` of this tree in another 
+    /// snapshot, first by runtime id and then by matching control type, class name and 
+    /// name along the path from the top level window.
+    pub fn find_counterpart(&self, index: usize, other: &UITree) -> Option<usize> {
+        let ui_elem_props = &self.tree.node(index).data;
+
+        if !ui_elem_props.runtime_id.is_empty() {
+            let mut found: Option<usize> = None;
+            other.for_each(|other_index, other_props| {
+                if found.is_none() && other_props.runtime_id == ui_elem_props.runtime_id {
+                    found = Some(other_index);
+                }
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        let mut other_index = other.root();
+        for path_index in self.tree.get_path_to_element(index) {
+            let path_props = &self.tree.node(path_index).data;
+            other_index = other.children(other_index).iter().copied().find(|&child| {
+                let child_props = &other.tree.node(child).data;
+                child_props.control_type == path_props.control_type 
+                    && child_props.classname == path_props.classname 
+                    && child_props.name == path_props.name
+            })?;
+        }
+        Some(other_index)
+    }
+
 }
 
 
@@ -79,12 +365,45 @@ pub struct UIElementProps {
     pub control_type: String,
     pub localized_control_type: String,
     pub framework_id: String,
+    pub automation_id: String,
+    pub help_text: String,
     pub runtime_id: Vec<i32>,
+    pub process_id: i32,
     pub handle: isize,
     pub bounding_rect: uiautomation::types::Rect,
     pub bounding_rect_size: i32,
+    pub is_keyboard_focusable: bool,
+    pub is_offscreen: bool,
+    pub is_enabled: bool,
     pub level: usize,
     pub z_order: usize,
+    pub errors: Vec<String>,
+}
+
+// an element without any properties, e.g. to be filled from an imported file
+impl Default for UIElementProps {
+    fn default() -> Self {
+        UIElementProps {
+            name: String::new(),
+            classname: String::new(),
+            control_type: String::new(),
+            localized_control_type: String::new(),
+            framework_id: String::new(),
+            automation_id: String::new(),
+            help_text: String::new(),
+            runtime_id: Vec::new(),
+            process_id: 0,
+            handle: 0,
+            bounding_rect: Rect::new(0, 0, 0, 0),
+            bounding_rect_size: 0,
+            is_keyboard_focusable: false,
+            is_offscreen: false,
+            is_enabled: true,
+            level: 0,
+            z_order: 0,
+            errors: Vec::new(),
+        }
+    }
 }
 
 impl UIElementProps {
@@ -94,25 +413,54 @@ impl UIElementProps {
         elem.level = level;
         elem
     }
+
+    /// Returns the properties of the element as display name and formatted value
+    pub fn property_list(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Name", self.name.clone()),
+            ("Control Type", self.control_type.clone()),
+            ("Localized Control Type", self.localized_control_type.clone()),
+            ("Framework ID", self.framework_id.clone()),
+            ("Class Name", self.classname.clone()),
+            ("Automation ID", self.automation_id.clone()),
+            ("Help Text", self.help_text.clone()),
+            ("Runtime ID", self.runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-")),
+            ("Process ID", self.process_id.to_string()),
+            ("Surrounding Rectangle", format!("{:?}", self.bounding_rect)),
+            ("Is Keyboard Focusable", self.is_keyboard_focusable.to_string()),
+            ("Is Offscreen", self.is_offscreen.to_string()),
+            ("Is Enabled", self.is_enabled.to_string()),
+            ("Level", self.level.to_string()),
+            ("Z-Order", self.z_order.to_string()),
+            ("Errors", self.errors.join("; ")),
+        ]
+    }
 }
 
 impl From<UIElement> for UIElementProps {
     fn from(item: UIElement) -> Self {
 
-        let name: String = item.get_name().unwrap_or("".to_string());
-        let classname: String = item.get_classname().unwrap_or("".to_string());
-        
-        let mut control_type: String = "".to_string();
-        if let Ok(ctrl_type) =  item.get_control_type() {
-            control_type = ctrl_type.to_string();    
-        }
+        // properties which can't be retrieved are left empty and the error is recorded
+        let mut errors: Vec<String> = Vec::new();
 
-        let localized_control_type: String = item.get_localized_control_type().unwrap_or("".to_string());
-        let framework_id: String = item.get_framework_id().unwrap_or("".to_string());
-        let runtime_id: Vec<i32> = item.get_runtime_id().unwrap_or(Vec::new());
-        let handle : isize = item.get_native_window_handle().unwrap_or(Handle::from(0 as isize)).into();
-        let bounding_rect: uiautomation::types::Rect = item.get_bounding_rectangle().unwrap_or(uiautomation::types::Rect::new(0, 0, 0, 0));
+        let name: String = fetch_property("Name", item.get_name(), &mut errors);
+        let classname: String = fetch_property("Class Name", item.get_classname(), &mut errors);
+        let control_type: String = fetch_property("Control Type", item.get_control_type().map(|ctrl_type| ctrl_type.to_string()), &mut errors);
+        let localized_control_type: String = fetch_property("Localized Control Type", item.get_localized_control_type(), &mut errors);
+        let framework_id: String = fetch_property("Framework ID", item.get_framework_id(), &mut errors);
+        let automation_id: String = fetch_property("Automation ID", item.get_automation_id(), &mut errors);
+        let help_text: String = fetch_property("Help Text", item.get_help_text(), &mut errors);
+        let runtime_id: Vec<i32> = fetch_property("Runtime ID", item.get_runtime_id(), &mut errors);
+        let process_id: i32 = fetch_property("Process ID", item.get_process_id(), &mut errors);
+        let handle : isize = fetch_property("Native Window Handle", item.get_native_window_handle().map(|handle| handle.into()), &mut errors);
+        let bounding_rect: uiautomation::types::Rect = item.get_bounding_rectangle().unwrap_or_else(|e| {
+            errors.push(format!("Surrounding Rectangle: {}", e));
+            uiautomation::types::Rect::new(0, 0, 0, 0)
+        });
         let bounding_rect_size: i32 = (bounding_rect.get_right() - bounding_rect.get_left()) * (bounding_rect.get_bottom() - bounding_rect.get_top());            
+        let is_keyboard_focusable: bool = fetch_property("Is Keyboard Focusable", item.is_keyboard_focusable(), &mut errors);
+        let is_offscreen: bool = fetch_property("Is Offscreen", item.is_offscreen(), &mut errors);
+        let is_enabled: bool = fetch_property("Is Enabled", item.is_enabled(), &mut errors);
         
         UIElementProps {
             name,
@@ -120,86 +468,712 @@ impl From<UIElement> for UIElementProps {
             control_type,
             localized_control_type,
             framework_id,
+            automation_id,
+            help_text,
             runtime_id,
+            process_id,
             handle,
             bounding_rect,
             bounding_rect_size,
+            is_keyboard_focusable,
+            is_offscreen,
+            is_enabled,
             level: 0,
             z_order: 0,
+            errors,
         }
     }
 }
 
-pub fn get_all_elements(tx: Sender<UITree>, max_depth: Option<usize>)  {   
-    
-    let automation = UIAutomation::new().unwrap();
-    let walker = automation.get_control_view_walker().unwrap();
+impl UIElementProps {
+    /// Creates the properties from the values prefetched with a cache request,
+    /// without any further cross process calls
+    pub fn from_cached(item: &UIElement) -> Self {
+
+        let mut errors: Vec<String> = Vec::new();
+
+        let name: String = fetch_property("Name", item.get_cached_name(), &mut errors);
+        let classname: String = fetch_property("Class Name", item.get_cached_classname(), &mut errors);
+        let control_type: String = fetch_property("Control Type", item.get_cached_control_type().map(|ctrl_type| ctrl_type.to_string()), &mut errors);
+        let localized_control_type: String = fetch_property("Localized Control Type", item.get_cached_localized_control_type(), &mut errors);
+        let framework_id: String = fetch_property("Framework ID", item.get_cached_framework_id(), &mut errors);
+        let automation_id: String = fetch_property("Automation ID", item.get_cached_automation_id(), &mut errors);
+        let help_text: String = fetch_property("Help Text", item.get_cached_help_text(), &mut errors);
+        let runtime_id: Vec<i32> = fetch_property("Runtime ID", get_cached_runtime_id(item), &mut errors);
+        let process_id: i32 = fetch_property("Process ID", item.get_cached_process_id(), &mut errors);
+        let handle : isize = fetch_property("Native Window Handle", item.get_cached_native_window_handle().map(|handle| handle.into()), &mut errors);
+        let bounding_rect: uiautomation::types::Rect = item.get_cached_bounding_rectangle().unwrap_or_else(|e| {
+            errors.push(format!("Surrounding Rectangle: {}", e));
+            uiautomation::types::Rect::new(0, 0, 0, 0)
+        });
+        let bounding_rect_size: i32 = (bounding_rect.get_right() - bounding_rect.get_left()) * (bounding_rect.get_bottom() - bounding_rect.get_top());
+        let is_keyboard_focusable: bool = fetch_property("Is Keyboard Focusable", item.is_cached_keyboard_focusable(), &mut errors);
+        let is_offscreen: bool = fetch_property("Is Offscreen", item.is_cached_offscreen(), &mut errors);
+        let is_enabled: bool = fetch_property("Is Enabled", item.is_cached_enabled(), &mut errors);
+
+        UIElementProps {
+            name,
+            classname,
+            control_type,
+            localized_control_type,
+            framework_id,
+            automation_id,
+            help_text,
+            runtime_id,
+            process_id,
+            handle,
+            bounding_rect,
+            bounding_rect_size,
+            is_keyboard_focusable,
+            is_offscreen,
+            is_enabled,
+            level: 0,
+            z_order: 0,
+            errors,
+        }
+    }
+}
+
+// there is no typed getter for the cached runtime id
+fn get_cached_runtime_id(item: &UIElement) -> uiautomation::Result<Vec<i32>> {
+    let runtime_id = item.get_cached_property_value(UIProperty::RuntimeId)?;
+    match runtime_id.get_value()? {
+        Value::ArrayI4(runtime_id) => Ok(runtime_id),
+        Value::SAFEARRAY(runtime_id) => runtime_id.try_into(),
+        _ => Err(uiautomation::Error::new(uiautomation::errors::ERR_TYPE, "unexpected type of runtime id")),
+    }
+}
+
+fn fetch_property<T: Default>(property: &str, result: uiautomation::Result<T>, errors: &mut Vec<String>) -> T {
+    result.unwrap_or_else(|e| {
+        errors.push(format!("{}: {}", property, e));
+        T::default()
+    })
+}
+
+/// Defines which part of the desktop is captured
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureScope {
+    /// all top level windows on the desktop
+    Desktop,
+    /// a single top level window, identified by its native window handle
+    Window(isize),
+    /// all top level windows of a process
+    Process(i32),
+    /// the subtree below an element, identified by its runtime id and the 
+    /// native window handle of its closest ancestor having one
+    Subtree { handle: isize, runtime_id: Vec<i32> },
+}
+
+impl CaptureScope {
+    pub fn label(&self) -> String {
+        match self {
+            CaptureScope::Desktop => "Desktop".to_string(),
+            CaptureScope::Window(handle) => format!("Window {:#x}", handle),
+            CaptureScope::Process(process_id) => format!("Process {}", process_id),
+            CaptureScope::Subtree { runtime_id, .. } => format!("Subtree {}", runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-")),
+        }
+    }
+}
+
+/// The command line flags selecting what is captured, shared by the explorer and the headless check
+#[derive(Debug, Clone)]
+pub struct ScopeArgs {
+    pub scope: CaptureScope,
+    /// top level window whose title contains this text, resolved before the capture
+    pub title: Option<String>,
+}
+
+impl Default for ScopeArgs {
+    fn default() -> Self {
+        ScopeArgs { scope: CaptureScope::Desktop, title: None }
+    }
+}
+
+impl ScopeArgs {
+    pub const USAGE: &'static str = "[--window <handle>] [--handle <handle>] [--process <pid>] [--subtree <handle>:<runtime id>] [--title <text>]";
+
+    /// Parses `arg` if it is one of the scope flags, taking its value from `value`.
+    /// Returns false for any other argument.
+    pub fn parse_arg(&mut self, arg: &str, value: &mut dyn FnMut() -> Result<String, String>) -> Result<bool, String> {
+        match arg {
+            // any element owning a native window, usually a top level window
+            "--window" | "--handle" => self.scope = CaptureScope::Window(parse_handle(&value()?)?),
+            "--process" => self.scope = CaptureScope::Process(value()?.parse().map_err(|_| "invalid process id".to_string())?),
+            "--subtree" => self.scope = parse_subtree(&value()?)?,
+            "--title" => self.title = Some(value()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The scope to capture, a window given by its title is looked up on the desktop
+    pub fn resolve(&self) -> Result<CaptureScope, String> {
+        match &self.title {
+            Some(title) => Ok(CaptureScope::Window(find_window_by_title(title)?)),
+            None => Ok(self.scope.clone()),
+        }
+    }
+}
+
+// window handles are given in hex (0x...) as shown in the explorer, or in decimal
+fn parse_handle(text: &str) -> Result<isize, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid window handle '{}'", text))
+}
+
+// e.g. 0x1a2b:42-1234-5, the element is searched below the window with the handle,
+// or on the whole desktop if the handle is 0
+fn parse_subtree(text: &str) -> Result<CaptureScope, String> {
+    let (handle, runtime_id) = text.split_once(':').ok_or(format!("invalid subtree '{}', expected <handle>:<runtime id>", text))?;
+    let runtime_id: Vec<i32> = runtime_id.split('-')
+        .map(|part| part.trim().parse().map_err(|_| format!("invalid runtime id '{}'", runtime_id)))
+        .collect::<Result<Vec<i32>, String>>()?;
+    Ok(CaptureScope::Subtree { handle: parse_handle(handle)?, runtime_id })
+}
+
+fn find_window_by_title(title: &str) -> Result<isize, String> {
+    let automation = UIAutomation::new().map_err(|e| e.to_string())?;
+    let walker = automation.get_control_view_walker().map_err(|e| e.to_string())?;
+    let root = automation.get_root_element().map_err(|e| e.to_string())?;
+    let mut next = walker.get_first_child(&root).ok();
+    while let Some(window) = next {
+        if window.get_name().is_ok_and(|name| name.contains(title)) {
+            let handle: isize = window.get_native_window_handle().map_err(|e| e.to_string())?.into();
+            return Ok(handle);
+        }
+        next = walker.get_next_sibling(&window).ok();
+    }
+    Err(format!("no top level window with a title containing '{}'", title))
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    pub max_depth: Option<usize>,
+    pub scope: CaptureScope,
+    pub view: TreeView,
+    /// a window is skipped if no new element could be retrieved within this time
+    pub element_timeout: Option<Duration>,
+    /// windows not captured within this time are skipped
+    pub overall_timeout: Option<Duration>,
+    /// number of windows captured at the same time, one per core if not set
+    pub worker_threads: Option<usize>,
+    /// take a screenshot of the captured windows before walking the tree
+    pub screenshot: bool,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        CaptureOptions {
+            max_depth: None,
+            scope: CaptureScope::Desktop,
+            view: TreeView::Control,
+            element_timeout: Some(Duration::from_secs(10)),
+            overall_timeout: None,
+            worker_threads: None,
+            screenshot: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CaptureProgress {
+    pub nodes_visited: usize,
+    pub current_window: String,
+    pub windows_done: usize,
+    pub windows_total: usize,
+}
+
+pub enum CaptureMessage {
+    Progress(CaptureProgress),
+    /// the desktop and a placeholder for each window, sent before the windows are captured
+    Skeleton(UITree),
+    /// the content of a window, applying it with graft_subtree to the skeleton in the order 
+    /// of the messages results in the same tree as the one sent with Done
+    Subtree { placeholder: usize, result: SubtreeResult },
+    Done(UITree),
+    Cancelled,
+    Failed(String),
+}
+
+/// Shared flag to cancel a running capture
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Identifies an element in a worker thread, as ui automation elements can't be shared between threads
+#[derive(Debug, Clone)]
+struct ElementLocator {
+    handle: isize,
+    runtime_id: Vec<i32>,
+}
+
+impl ElementLocator {
+    fn new(ui_elem_props: &UIElementProps) -> Self {
+        ElementLocator { handle: ui_elem_props.handle, runtime_id: ui_elem_props.runtime_id.clone() }
+    }
+
+    fn locate(&self, automation: &UIAutomation, walker: &UITreeWalker) -> Option<UIElement> {
+        let start = if self.handle != 0 {
+            automation.element_from_handle(Handle::from(self.handle)).ok()?
+        } else {
+            automation.get_root_element().ok()?
+        };
+        find_by_runtime_id(walker, &start, &self.runtime_id)
+    }
+}
+
+/// An element from which a subtree is captured, together with its ancestors below the desktop
+struct CaptureTarget {
+    ancestors: Vec<UIElementProps>,
+    ui_elem_props: UIElementProps,
+}
+
+/// Captures the ui tree and sends progress messages and finally the tree (or the reason 
+/// why there is none) through the channel.
+pub fn get_all_elements(tx: Sender<CaptureMessage>, options: CaptureOptions, cancel: CancelToken)  {   
+    let msg = match capture_tree(&tx, options, &cancel) {
+        Ok(Some(ui_tree)) => CaptureMessage::Done(ui_tree),
+        Ok(None) => CaptureMessage::Cancelled,
+        Err(e) => CaptureMessage::Failed(e.to_string()),
+    };
+    // the receiver may already be gone if the capture was cancelled
+    let _res = tx.send(msg);
+}
+
+fn capture_tree(tx: &Sender<CaptureMessage>, options: CaptureOptions, cancel: &CancelToken) -> uiautomation::Result<Option<UITree>> {
+
+    let _span = logging::span("capture", &format!("scope={} view={}", options.scope.label(), options.view.label()));
+    let deadline = options.overall_timeout.map(|timeout| Instant::now() + timeout);
+
+    let automation = UIAutomation::new()?;
     
     // get the desktop and all UI elements below the desktop
-    let root = automation.get_root_element().unwrap();
-    let runtime_id = root.get_runtime_id().unwrap_or(vec![0, 0, 0, 0]).iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-");
-    let item = format!("'{}' {} ({} | {} | {})", root.get_name().unwrap(), root.get_localized_control_type().unwrap(), root.get_classname().unwrap(), root.get_framework_id().unwrap(), runtime_id);
+    let root = automation.get_root_element()?;
     let ui_elem_props = UIElementProps::new(root.clone(), 0, 999);
-    let mut tree = UITreeMap::new(item, ui_elem_props.clone());
-    let ui_elem_in_tree = UIElementInTree::new(ui_elem_props, 0);
-    let mut ui_elements: Vec<UIElementInTree> = vec![ui_elem_in_tree];
-    
-    // printfmt!("Root element: {}", debug_clone.name);
-    if let Ok(_first_child) = walker.get_first_child(&root) {     
-        // itarate over all child ui elements
-        get_element(&mut tree, &mut ui_elements,  0, &walker, &root, 0, 0, max_depth);
+    let item = format_element_label(&ui_elem_props);
+    let mut tree = UITreeMap::new(item.clone(), ui_elem_props.clone());
+    // the desktop is repeated as the single child of the root node
+    let desktop = tree.add_child(0, item.as_str(), ui_elem_props);
+
+    let Some(targets) = discover_capture_targets(tx, &options, deadline, cancel) else {
+        log::info!("capture cancelled while looking for the windows");
+        return Ok(None);
+    };
+
+    // add a placeholder for every target, so the tree can be shown before the targets are captured
+    let mut jobs: Vec<SubtreeJob> = Vec::new();
+    let mut placeholders: Vec<usize> = Vec::new();
+    for (z_order, target) in targets.into_iter().enumerate() {
+        let mut parent = desktop;
+        for (pos, ancestor) in target.ancestors.iter().enumerate() {
+            let mut ui_elem_props = ancestor.clone();
+            ui_elem_props.level = pos + 1;
+            ui_elem_props.z_order = z_order;
+            parent = tree.add_child(parent, format_element_label(&ui_elem_props).as_str(), ui_elem_props);
+        }
+
+        let mut ui_elem_props = target.ui_elem_props;
+        ui_elem_props.level = target.ancestors.len() + 1;
+        ui_elem_props.z_order = z_order;
+        // the max depth is relative to the target
+        let max_depth = options.max_depth.map(|depth| depth + target.ancestors.len());
+        placeholders.push(tree.add_child(parent, format_element_label(&ui_elem_props).as_str(), ui_elem_props.clone()));
+        jobs.push(SubtreeJob { ui_elem_props, max_depth });
     }
 
-    // sorting the elements by z_order and then by ascending size of the bounding rectangle
-    ui_elements.sort_by(|a, b| a.get_element_props().bounding_rect_size.cmp(&b.get_element_props().bounding_rect_size));
-    ui_elements.sort_by(|a, b| a.get_element_props().z_order.cmp(&b.get_element_props().z_order));
+    log::info!("capturing {} windows", jobs.len());
+    let mut ui_tree = UITree::new_skeleton(tree, options.clone(), placeholders.iter().copied().collect());
+    if options.screenshot {
+        let area = screenshot_area(&ui_tree, &placeholders);
+        match area.as_ref().map(Screenshot::capture) {
+            Some(Ok(screenshot)) => ui_tree.set_screenshot(screenshot),
+            Some(Err(msg)) => log::warn!("no screenshot: {}", msg),
+            None => log::info!("no screenshot, the captured windows aren't on the screen"),
+        }
+    }
+    let _res = tx.send(CaptureMessage::Skeleton(ui_tree.clone()));
 
-    // pack the tree and ui_elements vector into a single struct
-    let ui_tree = UITree::new(tree, ui_elements);
+    // the subtrees of the targets are captured in parallel and streamed as soon as they are complete
+    let limits = PoolLimits {
+        max_workers: options.worker_threads.unwrap_or_else(PoolLimits::default_workers),
+        element_timeout: options.element_timeout,
+        deadline,
+    };
+    let factory = Arc::new(UIASourceFactory { view: options.view.clone() });
+    let completed = parallel_capture::capture_subtrees(factory, jobs, limits, cancel, 
+        |progress| {
+            let _res = tx.send(CaptureMessage::Progress(progress.clone()));
+        },
+        |index, result| {
+            let placeholder = placeholders[index];
+            let _res = tx.send(CaptureMessage::Subtree { placeholder, result: result.clone() });
+            ui_tree.graft_subtree(placeholder, result);
+        });
+    if !completed {
+        log::info!("capture cancelled");
+        return Ok(None);
+    }
 
-    // send the tree containing all UI elements back to the main thread
-    tx.send(ui_tree).unwrap();
+    ui_tree.rebuild_elements();
+    log::info!("captured {} elements", ui_tree.get_elements().len());
+    Ok(Some(ui_tree))
+}
 
+/// The area covered by the captured windows, clipped to the desktop
+fn screenshot_area(ui_tree: &UITree, placeholders: &[usize]) -> Option<Rect> {
+    let desktop = ui_tree.node(ui_tree.root()).1.bounding_rect;
+    if ui_tree.get_scope() == &CaptureScope::Desktop {
+        return Some(desktop);
+    }
+    let mut area: Option<(i32, i32, i32, i32)> = None;
+    for &placeholder in placeholders {
+        let rect = ui_tree.node(placeholder).1.bounding_rect;
+        let left = rect.get_left().max(desktop.get_left());
+        let top = rect.get_top().max(desktop.get_top());
+        let right = rect.get_right().min(desktop.get_right());
+        let bottom = rect.get_bottom().min(desktop.get_bottom());
+        // e.g. minimized windows
+        if right <= left || bottom <= top {
+            continue;
+        }
+        area = Some(match area {
+            Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+            None => (left, top, right, bottom),
+        });
+    }
+    area.map(|(left, top, right, bottom)| Rect::new(left, top, right, bottom))
 }
 
+/// Walks the ui automation tree in a worker thread, the children of an element and 
+/// their properties are retrieved with a single cross process call
+struct UIASource {
+    cache_request: UICacheRequest,
+}
 
-fn get_element(mut tree: &mut UITreeMap<UIElementProps>, mut ui_elements: &mut Vec<UIElementInTree>, parent: usize, walker: &UITreeWalker, element: &UIElement, level: usize, mut z_order: usize, max_depth: Option<usize>)  {
+impl ElementSource for UIASource {
+    type Element = UIElement;
 
-    if let Some(limit) = max_depth {
-        if level > limit {
-            return;
-        }    
+    fn element_props(&self, element: &UIElement, level: usize, z_order: usize) -> UIElementProps {
+        let mut elem = UIElementProps::from_cached(element);
+        elem.level = level;
+        elem.z_order = z_order;
+        elem
     }
 
-    let runtime_id = element.get_runtime_id().unwrap_or(vec![0, 0, 0, 0]).iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-");
-    let item = format!("'{}' {} ({} | {} | {})", element.get_name().unwrap(), element.get_localized_control_type().unwrap(), element.get_classname().unwrap(), element.get_framework_id().unwrap(), runtime_id);
-    let ui_elem_props: UIElementProps;
+    fn children(&self, element: &UIElement) -> Vec<UIElement> {
+        // elements without children return an error rather than an empty list
+        element.build_updated_cache(&self.cache_request)
+            .and_then(|element| element.get_cached_children())
+            .unwrap_or_default()
+    }
+}
+
+/// Creates the cache request for the properties in UIElementProps of the children
+/// of an element, restricted to the elements in the view of the walker
+fn create_capture_cache_request(automation: &UIAutomation, walker: &UITreeWalker) -> uiautomation::Result<UICacheRequest> {
+    let cache_request = create_element_cache_request(automation)?;
+    cache_request.set_tree_filter(walker.get_condition()?)?;
+    cache_request.set_tree_scope(TreeScope::Children)?;
+    Ok(cache_request)
+}
 
-    if level == 0 {
-        // manually setting the z_order for the root element
-        ui_elem_props = UIElementProps::new(element.clone(), level, 999);
-    } else {
-        ui_elem_props = UIElementProps::new(element.clone(), level, z_order);
+/// Creates the cache request for the properties in UIElementProps of a single element,
+/// e.g. to receive them along with an event
+pub fn create_element_cache_request(automation: &UIAutomation) -> uiautomation::Result<UICacheRequest> {
+    let cache_request = automation.create_cache_request()?;
+    for property in CACHED_PROPERTIES {
+        cache_request.add_property(property)?;
     }
-    
-    let parent = tree.add_child(parent, item.as_str(), ui_elem_props.clone());
-    let ui_elem_in_tree = UIElementInTree::new(ui_elem_props, parent);
-    ui_elements.push(ui_elem_in_tree);
-
-    // walking children now
-    if let Ok(child) = walker.get_first_child(&element) {
-        // getting child elements
-        get_element(&mut tree, &mut ui_elements, parent, walker, &child, level + 1, z_order, max_depth);
-        let mut next = child;
-        // walking siblings
-        while let Ok(sibling) = walker.get_next_sibling(&next) {
-            // incrementing z_order for each sibling
-            if level + 1 == 1 {
-                z_order += 1;
-            }
-            get_element(&mut tree, &mut ui_elements, parent, walker, &sibling,  level + 1, z_order, max_depth);
-            next = sibling;
+    Ok(cache_request)
+}
+
+// the properties retrieved for every element during the capture
+const CACHED_PROPERTIES: [UIProperty; 14] = [
+    UIProperty::Name,
+    UIProperty::ClassName,
+    UIProperty::ControlType,
+    UIProperty::LocalizedControlType,
+    UIProperty::FrameworkId,
+    UIProperty::AutomationId,
+    UIProperty::HelpText,
+    UIProperty::RuntimeId,
+    UIProperty::ProcessId,
+    UIProperty::NativeWindowHandle,
+    UIProperty::BoundingRectangle,
+    UIProperty::IsKeyboardFocusable,
+    UIProperty::IsOffscreen,
+    UIProperty::IsEnabled,
+];
+
+struct UIASourceFactory {
+    view: TreeView,
+}
+
+impl SourceFactory for UIASourceFactory {
+    type Source = UIASource;
+
+    fn open(&self, ui_elem_props: &UIElementProps) -> Result<(UIASource, UIElement), String> {
+        // each worker thread uses its own automation instance
+        let automation = UIAutomation::new().map_err(|e| e.to_string())?;
+        let walker = self.view.create_walker(&automation).map_err(|e| e.to_string())?;
+        let element = ElementLocator::new(ui_elem_props).locate(&automation, &walker)
+            .ok_or("element no longer available".to_string())?;
+        let cache_request = create_capture_cache_request(&automation, &walker).map_err(|e| e.to_string())?;
+        Ok((UIASource { cache_request }, element))
+    }
+}
+
+/// Looks for the elements from which the subtrees are captured on a separate thread, as the calls
+/// into a hung application block. Returns None if the capture was cancelled meanwhile, the targets
+/// found so far if the search stalls for the element timeout or the overall timeout has passed.
+fn discover_capture_targets(tx: &Sender<CaptureMessage>, options: &CaptureOptions, deadline: Option<Instant>, cancel: &CancelToken) -> Option<Vec<CaptureTarget>> {
+    let (target_tx, target_rx): (Sender<CaptureTarget>, Receiver<CaptureTarget>) = channel();
+    let view = options.view.clone();
+    let scope = options.scope.clone();
+    thread::spawn(move || {
+        // stops as soon as the receiver is gone
+        let result = find_capture_targets(&view, &scope, &mut |target| target_tx.send(target).is_ok());
+        if let Err(e) = result {
+            log::warn!("failed to look for the windows to capture: {}", e);
         }
-    }    
-    
+    });
+
+    let mut targets: Vec<CaptureTarget> = Vec::new();
+    let mut last_change = Instant::now();
+    loop {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        match target_rx.recv_timeout(DISCOVERY_POLL_INTERVAL) {
+            Ok(target) => {
+                let progress = CaptureProgress { current_window: format_element_label(&target.ui_elem_props), ..Default::default() };
+                let _res = tx.send(CaptureMessage::Progress(progress));
+                targets.push(target);
+                last_change = Instant::now();
+            },
+            // all targets have been found
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                    log::warn!("overall capture timeout exceeded while looking for the windows, {} found so far", targets.len());
+                    break;
+                }
+                if options.element_timeout.is_some_and(|timeout| last_change.elapsed() > timeout) {
+                    log::warn!("no window found within {:?}, skipping the remaining ones after {} windows", options.element_timeout.unwrap_or_default(), targets.len());
+                    break;
+                }
+            },
+        }
+    }
+    Some(targets)
+}
+
+// interval in which the search for the capture targets is checked for cancellation and timeouts
+const DISCOVERY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Calls `on_target` with each element from which a subtree is captured, until it returns false
+fn find_capture_targets(view: &TreeView, scope: &CaptureScope, on_target: &mut dyn FnMut(CaptureTarget) -> bool) -> uiautomation::Result<()> {
+    let automation = UIAutomation::new()?;
+    let walker = view.create_walker(&automation)?;
+    let root = automation.get_root_element()?;
+
+    let scope_roots: Vec<UIElement> = match scope {
+        CaptureScope::Desktop => {
+            get_top_level_windows(&walker, &root)
+        },
+        CaptureScope::Window(handle) => {
+            automation.element_from_handle(Handle::from(*handle)).into_iter().collect()
+        },
+        CaptureScope::Process(process_id) => {
+            get_top_level_windows(&walker, &root)
+                .into_iter()
+                .filter(|window| window.get_process_id().is_ok_and(|id| id == *process_id))
+                .collect()
+        },
+        CaptureScope::Subtree { handle, runtime_id } => {
+            // without a window to start from, the element is searched on the whole desktop
+            let start = if *handle != 0 { automation.element_from_handle(Handle::from(*handle)).ok() } else { Some(root.clone()) };
+            start
+                .and_then(|start| find_by_runtime_id(&walker, &start, runtime_id))
+                .into_iter()
+                .collect()
+        },
+    };
+
+    for scope_root in scope_roots {
+        // scoped captures keep the ancestors of the scope roots,
+        // so the paths to the elements are the same as in a full capture
+        let mut ancestors: Vec<UIElementProps> = match scope {
+            CaptureScope::Desktop => vec![UIElementProps::from(scope_root.clone())],
+            _ => get_ancestor_chain(&automation, &walker, &root, &scope_root).into_iter().map(UIElementProps::from).collect(),
+        };
+        let ui_elem_props = ancestors.pop().unwrap_or_else(|| UIElementProps::from(scope_root.clone()));
+        if !on_target(CaptureTarget { ancestors, ui_elem_props }) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn get_top_level_windows(walker: &UITreeWalker, root: &UIElement) -> Vec<UIElement> {
+    let mut windows: Vec<UIElement> = Vec::new();
+    let mut next = walker.get_first_child(root).ok();
+    while let Some(window) = next {
+        next = walker.get_next_sibling(&window).ok();
+        // the highlights of the explorer itself are no part of the desktop
+        if window.get_classname().is_ok_and(|classname| classname == OVERLAY_CLASS_NAME) {
+            continue;
+        }
+        windows.push(window);
+    }
+    windows
+}
+
+/// Breadth first search for the element with the given runtime id below (and including) an element
+pub fn find_by_runtime_id(walker: &UITreeWalker, element: &UIElement, runtime_id: &[i32]) -> Option<UIElement> {
+    let mut queue: VecDeque<UIElement> = VecDeque::from([element.clone()]);
+    while let Some(current) = queue.pop_front() {
+        if current.get_runtime_id().is_ok_and(|id| id == *runtime_id) {
+            return Some(current);
+        }
+        let mut next = walker.get_first_child(&current).ok();
+        while let Some(child) = next {
+            next = walker.get_next_sibling(&child).ok();
+            queue.push_back(child);
+        }
+    }
+    None
+}
+
+/// Returns the element and its ancestors below the desktop, ordered from the top level window down to the element
+fn get_ancestor_chain(automation: &UIAutomation, walker: &UITreeWalker, root: &UIElement, element: &UIElement) -> Vec<UIElement> {
+    let mut chain: Vec<UIElement> = Vec::new();
+    let mut current = element.clone();
+    loop {
+        if automation.compare_elements(&current, root).unwrap_or(true) {
+            break;
+        }
+        chain.push(current.clone());
+        match walker.get_parent(&current) {
+            Ok(parent) => current = parent,
+            Err(_) => break,
+        }
+    }
+    chain.reverse();
+    chain
+}
+
+/// Retrieves the element at the given (physical) screen position together with all its 
+/// ancestors below the desktop, ordered from the top level window down to the element.
+pub fn get_element_chain_from_point(tx: Sender<Vec<UIElementProps>>, x: i32, y: i32, view: TreeView) {
+
+    let chain = get_element_chain(x, y, &view).unwrap_or_default();
+    let _res = tx.send(chain);
+}
+
+fn get_element_chain(x: i32, y: i32, view: &TreeView) -> uiautomation::Result<Vec<UIElementProps>> {
+    let automation = UIAutomation::new()?;
+    let walker = view.create_walker(&automation)?;
+    let root = automation.get_root_element()?;
+    let element = automation.element_from_point(Point::new(x, y))?;
+
+    Ok(get_ancestor_chain(&automation, &walker, &root, &element)
+        .into_iter()
+        .map(UIElementProps::from)
+        .collect())
+}
+
+/// Formats the label of an element as displayed in the tree
+pub fn format_element_label(ui_elem_props: &UIElementProps) -> String {
+    let runtime_id = if ui_elem_props.runtime_id.is_empty() { vec![0, 0, 0, 0] } else { ui_elem_props.runtime_id.clone() };
+    let runtime_id = runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("-");
+    // flag elements with missing properties or skipped children
+    let warning = if ui_elem_props.errors.is_empty() { "" } else { "⚠ " };
+    format!("{}'{}' {} ({} | {} | {})", warning, ui_elem_props.name, ui_elem_props.localized_control_type, ui_elem_props.classname, ui_elem_props.framework_id, runtime_id)
+}
+
+/// Sorts the elements by z_order and then by ascending size of the bounding rectangle,
+/// so the first element containing a point is the top most, smallest one
+pub fn sort_ui_elements(ui_elements: &mut Vec<UIElementInTree>) {
+    ui_elements.sort_by(|a, b| a.get_element_props().bounding_rect_size.cmp(&b.get_element_props().bounding_rect_size));
+    ui_elements.sort_by(|a, b| a.get_element_props().z_order.cmp(&b.get_element_props().z_order));
+}
+
+/// Builds trees with the layout of a capture for the tests of the modules working on them
+#[cfg(test)]
+pub mod test_tree {
+    use super::*;
+
+    pub struct TreeBuilder {
+        tree: UITreeMap<UIElementProps>,
+    }
+
+    impl TreeBuilder {
+        /// The desktop is repeated as the single child of the root node, like in a capture
+        pub fn new(desktop: UIElementProps) -> Self {
+            let item = format_element_label(&desktop);
+            let mut tree = UITreeMap::new(item.clone(), desktop.clone());
+            tree.add_child(0, item.as_str(), desktop);
+            TreeBuilder { tree }
+        }
+
+        pub fn desktop(&self) -> usize {
+            1
+        }
+
+        /// Adds an element one level below its parent, returns its tree index
+        pub fn add(&mut self, parent: usize, mut ui_elem_props: UIElementProps) -> usize {
+            ui_elem_props.level = self.tree.node(parent).data.level + 1;
+            self.tree.add_child(parent, format_element_label(&ui_elem_props).as_str(), ui_elem_props)
+        }
+
+        pub fn props(&self, index: usize) -> &UIElementProps {
+            &self.tree.node(index).data
+        }
+
+        pub fn parent(&self, index: usize) -> usize {
+            self.tree.node(index).parent
+        }
+
+        pub fn update(&mut self, index: usize, ui_elem_props: UIElementProps) {
+            self.tree.update_node(index, format_element_label(&ui_elem_props).as_str(), ui_elem_props);
+        }
+
+        pub fn build(self) -> UITree {
+            UITree::from_map(self.tree, CaptureOptions::default())
+        }
+    }
+
+    /// An element without a bounding rectangle
+    pub fn element(control_type: &str, name: &str) -> UIElementProps {
+        UIElementProps {
+            name: name.to_string(),
+            control_type: control_type.to_string(),
+            localized_control_type: control_type.to_lowercase(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rect(mut ui_elem_props: UIElementProps, left: i32, top: i32, right: i32, bottom: i32) -> UIElementProps {
+        ui_elem_props.bounding_rect = Rect::new(left, top, right, bottom);
+        ui_elem_props.bounding_rect_size = (right - left) * (bottom - top);
+        ui_elem_props
+    }
 }
@@ -23,7 +23,7 @@ fn create_event_handler(tx: Sender<WinEventInfo>) -> impl Fn(Event, OpaqueHandle
         tx.send(WinEventInfo { 
             event: ev, 
             hwnd: ohwnd 
-        }).unwrap_or_else(|e| eprintln!("Failed to send event: {}", e));
+        }).unwrap_or_else(|e| log::warn!("Failed to send event: {}", e));
     }
 }
 
@@ -61,7 +61,7 @@ fn create_hook() -> (WinEventHook, Receiver<WinEventInfo>) {
         .finish();
 
     // Create handler and install hook
-    println!("Installing hook");
+    log::debug!("Installing hook");
     let handler = create_event_handler(tx);
     let hook = win_event_hook::WinEventHook::install(config, handler).unwrap();
     (hook, rx)
@@ -0,0 +1,406 @@
+//! XML export and import of a ui tree in the layout used by common UIA inspectors
+//! (e.g. the page source of WinAppDriver): the element names are the control types and
+//! the attributes are the captured properties, named after their UIA properties.
+//!
+//! <Pane Name="Desktop 1" ClassName="#32769" RuntimeId="42.65552" x="0" y="0" width="1920" height="1080" ...>
+//!   <Window Name="Calculator" AutomationId="" ...>
+#![allow(dead_code)]
+
+use std::io::Cursor;
+
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::{UITree, UITreeMap};
+use crate::uiexplore::{self, CaptureOptions, UIElementProps};
+
+// class name of the desktop element
+const DESKTOP_CLASS_NAME: &str = "#32769";
+
+/// Formats the element at `start` and everything below it as XML
+pub fn to_xml(ui_tree: &UITree, start: usize) -> Result<String, String> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None))).map_err(|e| e.to_string())?;
+    write_element(&mut writer, ui_tree, start).map_err(|e| e.to_string())?;
+
+    String::from_utf8(writer.into_inner().into_inner()).map_err(|e| e.to_string())
+}
+
+fn write_element(writer: &mut Writer<Cursor<Vec<u8>>>, ui_tree: &UITree, index: usize) -> quick_xml::Result<()> {
+    let ui_elem_props = ui_tree.node(index).1;
+    let tag = element_tag(ui_elem_props);
+    let mut start = BytesStart::new(tag.as_str());
+    for (name, value) in attributes(ui_elem_props) {
+        start.push_attribute((name, value.as_str()));
+    }
+
+    let children = ui_tree.children(index);
+    if children.is_empty() {
+        writer.write_event(Event::Empty(start))?;
+    } else {
+        writer.write_event(Event::Start(start))?;
+        for &child in children {
+            write_element(writer, ui_tree, child)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new(tag.as_str())))?;
+    }
+    Ok(())
+}
+
+// elements without a control type are written as custom controls
+fn element_tag(ui_elem_props: &UIElementProps) -> String {
+    if ui_elem_props.control_type.is_empty() { "Custom".to_string() } else { ui_elem_props.control_type.clone() }
+}
+
+fn format_bool(value: bool) -> String {
+    if value { "True".to_string() } else { "False".to_string() }
+}
+
+fn attributes(ui_elem_props: &UIElementProps) -> Vec<(&'static str, String)> {
+    let rect = &ui_elem_props.bounding_rect;
+    let mut attributes = vec![
+        ("AutomationId", ui_elem_props.automation_id.clone()),
+        ("ClassName", ui_elem_props.classname.clone()),
+        ("FrameworkId", ui_elem_props.framework_id.clone()),
+        ("HelpText", ui_elem_props.help_text.clone()),
+        ("IsEnabled", format_bool(ui_elem_props.is_enabled)),
+        ("IsKeyboardFocusable", format_bool(ui_elem_props.is_keyboard_focusable)),
+        ("IsOffscreen", format_bool(ui_elem_props.is_offscreen)),
+        ("LocalizedControlType", ui_elem_props.localized_control_type.clone()),
+        ("Name", ui_elem_props.name.clone()),
+        ("NativeWindowHandle", ui_elem_props.handle.to_string()),
+        ("ProcessId", ui_elem_props.process_id.to_string()),
+        ("RuntimeId", ui_elem_props.runtime_id.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(".")),
+        ("x", rect.get_left().to_string()),
+        ("y", rect.get_top().to_string()),
+        ("width", (rect.get_right() - rect.get_left()).to_string()),
+        ("height", (rect.get_bottom() - rect.get_top()).to_string()),
+    ];
+    // not an UIA property, but needed to show why an element is incomplete after the import
+    if !ui_elem_props.errors.is_empty() {
+        attributes.push(("Errors", ui_elem_props.errors.join("; ")));
+    }
+    attributes
+}
+
+/// Reads a tree written by to_xml or by an UIA inspector. Files starting with an element
+/// other than the desktop get a desktop added, so their root becomes a top level window.
+pub fn from_xml(xml: &str) -> Result<UITree, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut tree: Option<UITreeMap<UIElementProps>> = None;
+    // the open elements, the innermost last
+    let mut stack: Vec<usize> = Vec::new();
+
+    loop {
+        let event = reader.read_event().map_err(|e| format!("invalid xml at position {}: {}", reader.error_position(), e))?;
+        let (start, is_empty) = match &event {
+            Event::Start(start) => (start, false),
+            Event::Empty(start) => (start, true),
+            Event::End(_) => {
+                stack.pop();
+                continue;
+            },
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let mut ui_elem_props = parse_element(start)?;
+        let index = match (tree.as_mut(), stack.last()) {
+            (Some(tree), Some(&parent)) => {
+                let parent_props = &tree.node(parent).data;
+                ui_elem_props.level = parent_props.level + 1;
+                // top level windows are numbered in their order below the desktop, their content inherits the number
+                ui_elem_props.z_order = if parent_props.level == 0 { tree.children(parent).len() } else { parent_props.z_order };
+                tree.add_child(parent, uiexplore::format_element_label(&ui_elem_props).as_str(), ui_elem_props)
+            },
+            (Some(_), None) => return Err("more than one root element".to_string()),
+            (None, _) => {
+                let (new_tree, index) = new_tree_with_root(ui_elem_props);
+                tree = Some(new_tree);
+                index
+            },
+        };
+
+        if !is_empty {
+            stack.push(index);
+        }
+    }
+
+    let tree = tree.ok_or("no elements found".to_string())?;
+    Ok(UITree::from_map(tree, CaptureOptions::default()))
+}
+
+// the desktop is repeated as the single child of the root node, returns the tree
+// and the index of the node representing the root element
+fn new_tree_with_root(mut ui_elem_props: UIElementProps) -> (UITreeMap<UIElementProps>, usize) {
+    if ui_elem_props.classname == DESKTOP_CLASS_NAME {
+        ui_elem_props.z_order = 999;
+        let item = uiexplore::format_element_label(&ui_elem_props);
+        let mut tree = UITreeMap::new(item.clone(), ui_elem_props.clone());
+        let desktop = tree.add_child(0, item.as_str(), ui_elem_props);
+        (tree, desktop)
+    } else {
+        let desktop_props = synthetic_desktop();
+        let item = uiexplore::format_element_label(&desktop_props);
+        let mut tree = UITreeMap::new(item.clone(), desktop_props.clone());
+        let desktop = tree.add_child(0, item.as_str(), desktop_props);
+        ui_elem_props.level = 1;
+        let index = tree.add_child(desktop, uiexplore::format_element_label(&ui_elem_props).as_str(), ui_elem_props);
+        (tree, index)
+    }
+}
+
+fn synthetic_desktop() -> UIElementProps {
+    let mut desktop = UIElementProps::default();
+    desktop.name = "Desktop".to_string();
+    desktop.control_type = "Pane".to_string();
+    desktop.localized_control_type = "pane".to_string();
+    desktop.classname = DESKTOP_CLASS_NAME.to_string();
+    desktop.z_order = 999;
+    desktop
+}
+
+fn parse_element(start: &BytesStart) -> Result<UIElementProps, String> {
+    let mut ui_elem_props = UIElementProps::default();
+    ui_elem_props.control_type = String::from_utf8_lossy(start.name().as_ref()).to_string();
+    let (mut x, mut y, mut width, mut height) = (0, 0, 0, 0);
+
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| e.to_string())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        let value = attribute.unescape_value().map_err(|e| e.to_string())?.to_string();
+        match key.as_str() {
+            "AutomationId" => ui_elem_props.automation_id = value,
+            "ClassName" => ui_elem_props.classname = value,
+            "FrameworkId" => ui_elem_props.framework_id = value,
+            "HelpText" => ui_elem_props.help_text = value,
+            "IsEnabled" => ui_elem_props.is_enabled = parse_bool(&value),
+            "IsKeyboardFocusable" => ui_elem_props.is_keyboard_focusable = parse_bool(&value),
+            "IsOffscreen" => ui_elem_props.is_offscreen = parse_bool(&value),
+            "LocalizedControlType" => ui_elem_props.localized_control_type = value,
+            "Name" => ui_elem_props.name = value,
+            "NativeWindowHandle" => ui_elem_props.handle = parse_number(&value).unwrap_or_default() as isize,
+            "ProcessId" => ui_elem_props.process_id = parse_number(&value).unwrap_or_default() as i32,
+            "RuntimeId" => ui_elem_props.runtime_id = parse_numbers(&value).into_iter().map(|x| x as i32).collect(),
+            "x" => x = parse_number(&value).unwrap_or_default() as i32,
+            "y" => y = parse_number(&value).unwrap_or_default() as i32,
+            "width" => width = parse_number(&value).unwrap_or_default() as i32,
+            "height" => height = parse_number(&value).unwrap_or_default() as i32,
+            // some inspectors write the rectangle as a single property: left, top, width, height
+            "BoundingRectangle" => {
+                if let [left, top, rect_width, rect_height] = parse_numbers(&value)[..] {
+                    (x, y, width, height) = (left as i32, top as i32, rect_width as i32, rect_height as i32);
+                }
+            },
+            "Errors" => ui_elem_props.errors = value.split("; ").map(|error| error.to_string()).collect(),
+            _ => (),
+        }
+    }
+
+    ui_elem_props.bounding_rect = uiautomation::types::Rect::new(x, y, x + width, y + height);
+    ui_elem_props.bounding_rect_size = width * height;
+    if ui_elem_props.localized_control_type.is_empty() {
+        ui_elem_props.localized_control_type = ui_elem_props.control_type.to_lowercase();
+    }
+    Ok(ui_elem_props)
+}
+
+fn parse_bool(value: &str) -> bool {
+    value.eq_ignore_ascii_case("true") || value == "1"
+}
+
+// decimal or hex (0x...) numbers, as written by the different inspectors
+fn parse_number(value: &str) -> Option<i64> {
+    let value = value.trim();
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+// lists of numbers like runtime ids and rectangles are separated differently by each tool,
+// e.g. "42.1234", "42-1234", "[0,0,100,20]" or "0 0 100 20"
+fn parse_numbers(value: &str) -> Vec<i64> {
+    value.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| match parse_number(part) {
+            Some(number) => vec![number],
+            // runtime ids as shown by this explorer, e.g. 42-1234
+            None => part.split('-').filter_map(parse_number).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uiexplore::test_tree::{element, with_rect, TreeBuilder};
+
+    // desktop > [calculator > [equals button], notepad]
+    fn sample_tree() -> UITree {
+        let mut desktop = with_rect(element("Pane", "Desktop 1"), 0, 0, 1920, 1080);
+        desktop.classname = DESKTOP_CLASS_NAME.to_string();
+        desktop.z_order = 999;
+        let mut tree = TreeBuilder::new(desktop);
+
+        let mut calculator = with_rect(element("Window", "Calculator"), 100, 100, 420, 600);
+        calculator.classname = "ApplicationFrameWindow".to_string();
+        calculator.runtime_id = vec![42, 1234];
+        calculator.handle = 0x1A2B;
+        calculator.process_id = 4711;
+        calculator.framework_id = "Win32".to_string();
+        let calculator = tree.add(tree.desktop(), calculator);
+
+        let mut equals = with_rect(element("Button", "Equals"), 340, 540, 410, 590);
+        equals.classname = "Button".to_string();
+        equals.automation_id = "equalButton".to_string();
+        equals.help_text = "Calculates the result".to_string();
+        equals.is_keyboard_focusable = true;
+        equals.errors = vec!["HelpText: not supported".to_string(), "skipped children".to_string()];
+        tree.add(calculator, equals);
+
+        let mut notepad = with_rect(element("Window", "a & <b> \"c\""), -8, -8, 800, 600);
+        notepad.classname = "Notepad".to_string();
+        notepad.z_order = 1;
+        notepad.is_enabled = false;
+        notepad.is_offscreen = true;
+        tree.add(tree.desktop(), notepad);
+
+        tree.build()
+    }
+
+    fn rect(ui_elem_props: &UIElementProps) -> (i32, i32, i32, i32) {
+        let rect = &ui_elem_props.bounding_rect;
+        (rect.get_left(), rect.get_top(), rect.get_right(), rect.get_bottom())
+    }
+
+    fn assert_same_subtree(expected: &UITree, expected_index: usize, actual: &UITree, actual_index: usize) {
+        let (_, expected_props) = expected.node(expected_index);
+        let (_, actual_props) = actual.node(actual_index);
+        assert_eq!(actual_props.control_type, expected_props.control_type);
+        assert_eq!(actual_props.localized_control_type, expected_props.localized_control_type);
+        assert_eq!(actual_props.name, expected_props.name);
+        assert_eq!(actual_props.classname, expected_props.classname);
+        assert_eq!(actual_props.framework_id, expected_props.framework_id);
+        assert_eq!(actual_props.automation_id, expected_props.automation_id);
+        assert_eq!(actual_props.help_text, expected_props.help_text);
+        assert_eq!(actual_props.runtime_id, expected_props.runtime_id);
+        assert_eq!(actual_props.process_id, expected_props.process_id);
+        assert_eq!(actual_props.handle, expected_props.handle);
+        assert_eq!(rect(actual_props), rect(expected_props));
+        assert_eq!(actual_props.bounding_rect_size, expected_props.bounding_rect_size);
+        assert_eq!(actual_props.is_enabled, expected_props.is_enabled);
+        assert_eq!(actual_props.is_keyboard_focusable, expected_props.is_keyboard_focusable);
+        assert_eq!(actual_props.is_offscreen, expected_props.is_offscreen);
+        assert_eq!(actual_props.errors, expected_props.errors);
+        assert_eq!(actual_props.level, expected_props.level, "level of {}", expected_props.name);
+        assert_eq!(actual_props.z_order, expected_props.z_order, "z-order of {}", expected_props.name);
+
+        let expected_children = expected.children(expected_index);
+        let actual_children = actual.children(actual_index);
+        assert_eq!(actual_children.len(), expected_children.len(), "children of {}", expected_props.name);
+        for (&expected_child, &actual_child) in expected_children.iter().zip(actual_children) {
+            assert_same_subtree(expected, expected_child, actual, actual_child);
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_the_tree() {
+        let ui_tree = sample_tree();
+        let xml = to_xml(&ui_tree, ui_tree.desktop()).unwrap();
+        let imported = from_xml(&xml).unwrap();
+
+        // the desktop is repeated as the single child of the root node, like in a capture
+        assert_eq!(imported.children(imported.root()).len(), 1);
+        assert_same_subtree(&ui_tree, ui_tree.root(), &imported, imported.root());
+        assert_same_subtree(&ui_tree, ui_tree.desktop(), &imported, imported.desktop());
+        assert_eq!(imported.get_elements().len(), ui_tree.get_elements().len());
+    }
+
+    #[test]
+    fn export_writes_control_types_and_uia_attributes() {
+        let ui_tree = sample_tree();
+        let xml = to_xml(&ui_tree, ui_tree.desktop()).unwrap();
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>"));
+        assert!(xml.contains("<Pane "));
+        assert!(xml.contains("<Window "));
+        assert!(xml.contains("<Button "));
+        assert!(xml.contains("RuntimeId=\"42.1234\""));
+        assert!(xml.contains("NativeWindowHandle=\"6699\""));
+        assert!(xml.contains("x=\"100\" y=\"100\" width=\"320\" height=\"500\""));
+        assert!(xml.contains("IsEnabled=\"False\""));
+        assert!(xml.contains("Name=\"a &amp; &lt;b&gt; &quot;c&quot;\""));
+        assert!(xml.contains("Errors=\"HelpText: not supported; skipped children\""));
+    }
+
+    #[test]
+    fn window_without_desktop_gets_a_synthetic_desktop() {
+        let ui_tree = sample_tree();
+        let calculator = ui_tree.children(ui_tree.desktop())[0];
+        let xml = to_xml(&ui_tree, calculator).unwrap();
+        let imported = from_xml(&xml).unwrap();
+
+        for index in [imported.root(), imported.desktop()] {
+            let (_, desktop) = imported.node(index);
+            assert_eq!(desktop.name, "Desktop");
+            assert_eq!(desktop.classname, DESKTOP_CLASS_NAME);
+            assert_eq!(desktop.level, 0);
+            assert_eq!(desktop.z_order, 999);
+        }
+        assert_eq!(imported.children(imported.desktop()).len(), 1);
+        assert_same_subtree(&ui_tree, calculator, &imported, imported.children(imported.desktop())[0]);
+    }
+
+    #[test]
+    fn imports_the_layout_of_other_inspectors() {
+        let xml = r#"<Window Name="Editor" RuntimeId="42-1234" NativeWindowHandle="0x1A2B" BoundingRectangle="[10,20,300,200]" IsEnabled="true">
+                       <Edit Name="Text" />
+                       <Button Name="Close" IsKeyboardFocusable="1" BoundingRectangle="250 20 50 20" />
+                     </Window>"#;
+        let imported = from_xml(xml).unwrap();
+
+        let window = imported.children(imported.desktop())[0];
+        let (_, window_props) = imported.node(window);
+        assert_eq!(window_props.runtime_id, vec![42, 1234]);
+        assert_eq!(window_props.handle, 0x1A2B);
+        assert_eq!(rect(window_props), (10, 20, 310, 220));
+        assert_eq!(window_props.localized_control_type, "window");
+        assert_eq!((window_props.level, window_props.z_order), (1, 0));
+
+        let children: Vec<&UIElementProps> = imported.children(window).iter().map(|&child| imported.node(child).1).collect();
+        assert_eq!(children.iter().map(|props| props.control_type.as_str()).collect::<Vec<_>>(), ["Edit", "Button"]);
+        assert!(children.iter().all(|props| props.level == 2 && props.z_order == 0));
+        assert!(children[1].is_keyboard_focusable);
+        assert_eq!(rect(children[1]), (250, 20, 300, 40));
+    }
+
+    #[test]
+    fn invalid_xml_is_rejected() {
+        assert!(from_xml("").is_err());
+        assert!(from_xml("<Window><Button></Window>").is_err());
+        assert!(from_xml("<Window /><Window />").is_err());
+    }
+
+    #[test]
+    fn parse_numbers_of_the_different_tools() {
+        assert_eq!(parse_numbers("42.1234"), vec![42, 1234]);
+        assert_eq!(parse_numbers("42-1234"), vec![42, 1234]);
+        assert_eq!(parse_numbers("[0,0,100,20]"), vec![0, 0, 100, 20]);
+        assert_eq!(parse_numbers("0 0 100 20"), vec![0, 0, 100, 20]);
+        assert_eq!(parse_numbers("[-8,-8,1936,1056]"), vec![-8, -8, 1936, 1056]);
+        assert_eq!(parse_numbers("42.0x1A2B"), vec![42, 0x1A2B]);
+        assert_eq!(parse_numbers(""), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn parse_number_reads_decimal_and_hex() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number(" -8 "), Some(-8));
+        assert_eq!(parse_number("0x1A2B"), Some(0x1A2B));
+        assert_eq!(parse_number("0X1a2b"), Some(0x1A2B));
+        assert_eq!(parse_number("window"), None);
+    }
+}